@@ -1,6 +1,6 @@
-use super::intcode::Machine;
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
+use aoc_intcode::{read_tape, Machine, Status};
 
 pub struct Day02;
 
@@ -13,21 +13,16 @@ impl AocDay for Day02 {
         2
     }
     fn load() -> Res<Self::Input> {
-        Machine::load_tape_from_file("data/2019/day_2.in")
+        read_tape("data/2019/day_2.in")
     }
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
-        let mut mem: Vec<i64> = input.clone();
-        mem[1] = 12;
-        mem[2] = 2;
+        let mut machine = Machine::new(input);
+        machine.poke(1, 12);
+        machine.poke(2, 2);
 
-        let mut machine = Machine {
-            mem,
-            ..Machine::default()
-        };
-
-        machine.run_to_completion();
-        machine.mem[0]
+        machine.run_to_halt().unwrap();
+        machine.peek(0)
     }
 
     fn part_2(input: &Vec<Self::Result2>) -> Self::Result2 {
@@ -35,17 +30,11 @@ impl AocDay for Day02 {
 
         for noun in 0..100 {
             for verb in 0..100 {
-                let mut mem = input.clone();
-                mem[1] = noun;
-                mem[2] = verb;
-
-                let mut machine = Machine {
-                    mem,
-                    ..Machine::default()
-                };
+                let mut machine = Machine::new(input);
+                machine.poke(1, noun);
+                machine.poke(2, verb);
 
-                machine.run_to_completion();
-                if machine.mem[0] == 19_690_720 {
+                if machine.run() == Status::Halted && machine.peek(0) == 19_690_720 {
                     pairs.push((noun, verb))
                 }
             }
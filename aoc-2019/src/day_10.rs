@@ -1,4 +1,5 @@
 use aoc_common::files::Res;
+use aoc_common::numtheory::gcd;
 use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
 
@@ -107,7 +108,7 @@ fn rationalize(coord: Pos2D) -> RationalVector {
         (0, y) if y > 0 => (0, 1, y, 0.0),
         (0, y) if y < 0 => (0, -1, y.abs(), 180.0),
         (x, y) => {
-            let f = gcf(x, y).abs();
+            let f = gcd(x, y);
             let angle = (x as f32).atan2(y as f32).to_degrees();
             let angle = match angle {
                 a if a < 0.0 => 360.0 + a,
@@ -118,13 +119,6 @@ fn rationalize(coord: Pos2D) -> RationalVector {
     }
 }
 
-fn gcf(a: i32, b: i32) -> i32 {
-    match (a, b) {
-        (a, 0) => a,
-        (a, b) => gcf(b, a % b),
-    }
-}
-
 pub fn day_10() -> Res<((i32, i32, i32), Pos2D)> {
     let day_10_in: String = read_to_string("data/2019/day_10.in")?;
     println!("Day 10");
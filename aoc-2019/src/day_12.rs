@@ -1,3 +1,4 @@
+use aoc_common::cycle::combined_period;
 use aoc_common::files::Res;
 use std::fs::read_to_string;
 
@@ -64,82 +65,16 @@ fn calc_energy(sys: &[Asteroid]) -> i32 {
         .sum()
 }
 
+/// Each axis moves independently, so the whole system repeats once every axis has.
 fn until_stable(starting: &[Asteroid]) -> i64 {
-    let mut stable_x: Option<i64> = None;
-    let mut stable_y: Option<i64> = None;
-    let mut stable_z: Option<i64> = None;
-
-    let start_x = starting
-        .iter()
-        .map(|a| (a.pos.0, a.vel.0))
-        .collect::<Vec<_>>();
-    let start_y = starting
-        .iter()
-        .map(|a| (a.pos.1, a.vel.1))
-        .collect::<Vec<_>>();
-    let start_z = starting
-        .iter()
-        .map(|a| (a.pos.2, a.vel.2))
-        .collect::<Vec<_>>();
-
-    let mut sys = starting.to_owned();
-    let mut i = 0;
-
-    loop {
-        sim_step(&mut sys);
-        i += 1;
-
-        if stable_x.is_none()
-            && sys
-                .iter()
-                .map(|a| (a.pos.0, a.vel.0))
-                .enumerate()
-                .all(|(i, e)| e == start_x[i])
-        {
-            stable_x = Some(i);
-        }
-        if stable_y.is_none()
-            && sys
-                .iter()
-                .map(|a| (a.pos.1, a.vel.1))
-                .enumerate()
-                .all(|(i, e)| e == start_y[i])
-        {
-            stable_y = Some(i);
-        }
-        if stable_z.is_none()
-            && sys
-                .iter()
-                .map(|a| (a.pos.2, a.vel.2))
-                .enumerate()
-                .all(|(i, e)| e == start_z[i])
-        {
-            stable_z = Some(i);
-        }
-
-        if stable_x.is_some() && stable_y.is_some() && stable_z.is_some() {
-            break;
-        }
-    }
-
-    let x = stable_x.unwrap();
-    let y = stable_y.unwrap();
-    let z = stable_z.unwrap();
-
-    dbg!(x, y, z);
-
-    lcm(z, lcm(x, y))
-}
-
-fn lcm(a: i64, b: i64) -> i64 {
-    (a * b) / gcf(a, b)
-}
-
-fn gcf(a: i64, b: i64) -> i64 {
-    match (a, b) {
-        (a, 0) => a,
-        (a, b) => gcf(b, a % b),
-    }
+    let axes = |sys: &Vec<Asteroid>| {
+        vec![
+            sys.iter().map(|a| (a.pos.0, a.vel.0)).collect::<Vec<_>>(),
+            sys.iter().map(|a| (a.pos.1, a.vel.1)).collect::<Vec<_>>(),
+            sys.iter().map(|a| (a.pos.2, a.vel.2)).collect::<Vec<_>>(),
+        ]
+    };
+    combined_period(&starting.to_vec(), sim_step, axes) as i64
 }
 
 pub fn day_12() -> Res<i32> {
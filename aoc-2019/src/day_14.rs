@@ -1,5 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::{read_lines, Res};
+use aoc_common::graph::Graph;
 use std::collections::HashMap;
 
 pub struct Day14;
@@ -32,9 +33,19 @@ impl AocDay for Day14 {
         }
         map.insert(&ore, &ore_rule);
 
-        // A topologically ordered list of chemicals
-        let mut ordered = topo_order(&map, &fuel);
-        ordered.push(&ore);
+        // A topologically ordered list of chemicals, each before everything it's made from
+        let made_from: Graph = rules
+            .iter()
+            .flat_map(|(inputs, (out, _))| {
+                inputs.iter().map(move |(c, _)| (out.as_str(), c.as_str()))
+            })
+            .collect();
+        let ordered: Vec<&str> = made_from
+            .topo_order()
+            .expect("reactions can't be circular")
+            .into_iter()
+            .map(|id| made_from.label(id))
+            .collect();
 
         let mut need_map: HashMap<&str, u64> = HashMap::new();
         for o in &ordered {
@@ -126,30 +137,6 @@ fn can_create(
         .all(|(c, a)| can_create(map, bank, c, reactions * a))
 }
 
-fn topo_order<'a>(map: &HashMap<&str, &'a Rule>, chem: &'a str) -> Vec<&'a str> {
-    if chem == "ORE" {
-        return vec![];
-    }
-
-    let (inputs, _) = map[chem];
-
-    let mut ordered: Vec<&str> =
-        inputs
-            .iter()
-            .map(|(c, _)| topo_order(map, c))
-            .fold(Vec::new(), |mut acc, tp| {
-                let mut new = tp
-                    .into_iter()
-                    .filter(|c| !acc.contains(c))
-                    .collect::<Vec<_>>();
-                new.append(&mut acc);
-                new
-            });
-
-    ordered.insert(0, chem);
-    ordered
-}
-
 fn parse_rule(s: &str) -> Rule {
     let (ins, out) = s.split_once(" => ").unwrap();
     let inputs = ins
@@ -1,10 +1,10 @@
-use super::intcode;
 use aoc_common::aoc_day::AocDay;
+use aoc_common::distances::bfs_distances;
 use aoc_common::files::Res;
 use aoc_common::geometry::{self, Direction};
-use aoc_common::search::{search, HeapElem};
-use std::collections::VecDeque;
-use std::collections::{HashMap, HashSet};
+use aoc_common::viz::Recorder;
+use aoc_intcode::{read_tape, Machine};
+use std::collections::{HashMap, VecDeque};
 
 pub struct Day15;
 
@@ -12,12 +12,6 @@ type Point = (i64, i64);
 
 type Map = HashMap<Point, char>;
 
-struct MazeSim {
-    machine: intcode::Machine,
-    map: Map,
-    pos: Point,
-}
-
 fn input_from_direction(dir: &Direction) -> i64 {
     use Direction::*;
     match dir {
@@ -28,68 +22,62 @@ fn input_from_direction(dir: &Direction) -> i64 {
     }
 }
 
-impl MazeSim {
-    fn new(input: &[i64], map: Option<Map>) -> Self {
-        MazeSim {
-            machine: intcode::Machine::new(input, vec![]),
-            map: map.unwrap_or_default(),
-            pos: (0, 0),
-        }
-    }
+/// An intcode maze, found by trying every move from every square the droid can get to.
+struct Maze {
+    map: Map,
+    oxygen: Point,
+    /// How many moves it takes to get to the oxygen system.
+    moves: i64,
+}
 
-    /// try to move in the given direction, return the character that was there (or is there if you can't move).
-    fn try_move(&mut self, dir: Direction) -> char {
-        let mut res = ' ';
-        if let Some(output) = self.machine.run_to_output(Some(input_from_direction(&dir))) {
-            match output {
-                0 => {
-                    self.map.insert(dir.next_point(self.pos), '#');
-                    res = '#'
+impl Maze {
+    /// A breadth first search, keeping a snapshot of the droid on each square found, so that
+    /// trying a move from there is restoring it rather than walking it back.
+    fn explore(input: &[i64]) -> Self {
+        let mut recorder = Recorder::new("2019_day_15");
+        let mut droid = Machine::new(input);
+        let mut map = Map::new();
+        map.insert((0, 0), 'S');
+        let mut oxygen = None;
+
+        let mut todo = VecDeque::new();
+        todo.push_back(((0, 0), 0, droid.snapshot()));
+        while let Some((pos, moves, snapshot)) = todo.pop_front() {
+            for dir in Direction::array().iter() {
+                let next = dir.next_point(pos);
+                if map.contains_key(&next) {
+                    continue;
                 }
-                1 => {
-                    self.map.insert(self.pos, '.');
-                    self.pos = dir.next_point(self.pos);
-                    self.map.insert(self.pos, 'D');
-                    res = '.'
+                droid.restore(&snapshot);
+                droid.push_input(input_from_direction(dir));
+                let c = match droid.next_output() {
+                    Ok(Some(0)) => '#',
+                    Ok(Some(1)) => '.',
+                    Ok(Some(2)) => 'O',
+                    other => unreachable!("the droid said {:?}", other),
+                };
+                map.insert(next, c);
+                if c == 'O' {
+                    oxygen = Some((next, moves + 1));
                 }
-                2 => {
-                    self.map.insert(self.pos, '.');
-                    self.pos = dir.next_point(self.pos);
-                    self.map.insert(self.pos, 'O');
-                    res = 'O'
+                if c != '#' {
+                    todo.push_back((next, moves + 1, droid.snapshot()));
                 }
-                _ => unreachable!("not be here"),
             }
+            recorder.frame(|| geometry::format_map(&map));
         }
-        res
-    }
 
-    /// Explore an intcode maze by finding a wall and then keeping ones left hand on it (or right when acw is true)
-    fn explore_maze(mut self, acw: bool) -> Self {
-        let mut dir = Direction::North;
-        self.map.insert(self.pos, 'D');
-
-        while self.try_move(dir) != '#' {}
-        dir = dir.rotate(acw);
-
-        loop {
-            //try to move forward
-            let next = self.try_move(dir);
-            match next {
-                '#' => {
-                    // turn right and move (loop)
-                    dir = dir.rotate(acw);
-                }
-                '.' => {
-                    // we moved, so turn left and sww if we can move that way
-                    dir = dir.rotate(!acw);
-                }
-                _ => break,
-            }
-        }
+        let (oxygen, moves) = oxygen.expect("no oxygen system");
+        Maze { map, oxygen, moves }
+    }
 
-        self.map.insert((0, 0), 'S');
-        self
+    /// We only care about routes, throw away the walls
+    fn open(&self) -> Map {
+        self.map
+            .iter()
+            .filter(|(_, &v)| v != '#')
+            .map(|(&p, &v)| (p, v))
+            .collect()
     }
 }
 
@@ -102,87 +90,32 @@ impl AocDay for Day15 {
         15
     }
     fn load() -> Res<Self::Input> {
-        intcode::Machine::load_tape_from_file("data/2019/day_15.in")
+        read_tape("data/2019/day_15.in")
     }
 
-    /// Do two maze explorations (one left and one right, to ensure that it's fully explored),
-    /// and then search (A*) through the open spaces to find the shortest path.
+    /// Explore the maze breadth first, which finds the shortest path on the way.
     fn part_1(input: &Self::Input) -> Self::Result1 {
-        // Go both directions to fully explore the maze
-        let map = MazeSim::new(input, None).explore_maze(false).map;
-        let map = MazeSim::new(input, Some(map)).explore_maze(true).map;
-
-        // We only care about routes, throw away the walls
-        let map: Map = map.into_iter().filter(|(_, v)| *v != '#').collect();
-        let map_ref = &map;
-        geometry::output_map(&map);
-
-        let (&goal, _) = map.iter().find(|(_, &v)| v == 'O').unwrap();
-
-        let start: (i64, i64) = (0, 0);
-
-        search(
-            start,
-            |p| *p == goal,
-            |p, distance| {
-                Direction::array().iter().filter_map(move |direction| {
-                    let successor = direction.next_point(p);
-                    if !map_ref.contains_key(&successor) {
-                        // not on the path
-                        return None;
-                    }
-                    Some(HeapElem {
-                        elem: successor,
-                        distance: distance + 1,
-                        heuristic: ((goal.0 - successor.0).abs() + (goal.1 - successor.1).abs())
-                            as u64,
-                    })
-                })
-            },
-        )
-        .1 as i64
+        let maze = Maze::explore(input);
+        geometry::output_map(&maze.open());
+        maze.moves
     }
 
-    /// Do two maze explorations (one left and one right, to ensure that it's fully explored),
-    /// and then do a breadth first search to find the "depth" of the "tree".
+    /// Explore the maze, and then do a breadth first search from the oxygen system to find the
+    /// "depth" of the "tree".
     fn part_2(input: &Self::Input) -> Self::Result2 {
-        // Go both directions to fully explore the maze
-        let map = MazeSim::new(input, None).explore_maze(false).map;
-        let map = MazeSim::new(input, Some(map)).explore_maze(true).map;
-
-        // We only care about routes, throw away the walls
-        let map: Map = map.into_iter().filter(|(_, v)| *v != '#').collect();
-        let (&goal, _) = map.iter().find(|(_, &v)| v == 'O').unwrap();
-
-        depth(&map, goal)
+        let maze = Maze::explore(input);
+        depth(&maze.open(), maze.oxygen)
     }
 }
 
 fn depth(map: &Map, start: Point) -> i64 {
-    let mut queue: VecDeque<(Point, i64)> = VecDeque::new();
-    let mut queued: HashSet<Point> = HashSet::new();
-    queue.push_back((start, 0));
-    queued.insert(start);
-    let mut depth = 0;
-
-    while let Some(point) = queue.pop_front() {
-        let ((x, y), d) = point;
-        depth = d;
-        let successors = [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)];
-        for successor in successors.iter() {
-            if !map.contains_key(successor) {
-                // not on the path
-                continue;
-            }
-            if queued.contains(successor) {
-                continue;
-            }
-            queue.push_back((*successor, depth + 1));
-            queued.insert((x, y));
-        }
-    }
-
-    depth
+    bfs_distances(vec![start], |&(x, y)| {
+        vec![(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .into_iter()
+            // not on the path
+            .filter(|successor| map.contains_key(successor))
+    })
+    .max_distance() as i64
 }
 
 #[cfg(test)]
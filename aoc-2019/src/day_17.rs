@@ -1,7 +1,7 @@
-use super::intcode;
-use aoc_common::files::Res;
 use aoc_common::aoc_day::AocDay;
+use aoc_common::files::Res;
 use aoc_common::geometry::{self, Direction, Point2D};
+use aoc_intcode::{read_tape, AsciiConsole, Machine};
 use std::collections::{HashMap, HashSet};
 
 pub struct Day17;
@@ -17,18 +17,16 @@ impl AocDay for Day17 {
         17
     }
     fn load() -> Res<Self::Input> {
-        intcode::Machine::load_tape_from_file("data/2019/day_17.in")
+        read_tape("data/2019/day_17.in")
     }
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
-        let mut machine = intcode::Machine::new(input, vec![]);
-        machine.run_to_completion();
+        let output = AsciiConsole::new(Machine::new(input)).read().text;
         let mut map: Map = HashMap::new();
 
         let mut row = 0;
         let mut col = 0;
-        for v in machine.output.iter() {
-            let c = *v as u8 as char;
+        for c in output.chars() {
             if c == '\n' {
                 row += 1;
                 col = 0;
@@ -77,24 +75,16 @@ impl AocDay for Day17 {
     fn part_2(input: &Self::Input) -> Self::Result2 {
         let mut mem_cloned = input.clone();
         mem_cloned[0] = 2;
-        let input_string = r#"A,B,A,C,B,C,A,C,B,C
-L,8,R,10,L,10
-R,10,L,8,L,8,L,10
-L,4,L,6,L,8,L,8
-n
-"#;
-        let mut machine = intcode::Machine::new(
-            &mem_cloned,
-            input_string
-                .as_bytes()
-                .iter()
-                .cloned()
-                .map(i64::from)
-                .collect(),
-        );
-
-        machine.run_to_completion();
-        *machine.output.last().unwrap()
+        let routine = [
+            "A,B,A,C,B,C,A,C,B,C",
+            "L,8,R,10,L,10",
+            "R,10,L,8,L,8,L,10",
+            "L,4,L,6,L,8,L,8",
+            "n",
+        ];
+        let mut console = AsciiConsole::new(Machine::new(&mem_cloned));
+
+        *console.run_script(routine).values.last().unwrap()
     }
 }
 
@@ -1,9 +1,10 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::bitset::Bitset;
+use aoc_common::distances::bfs_distances;
 use aoc_common::files::Res;
 use aoc_common::geometry::{Direction, Point2D};
-use aoc_common::search::{search, HeapElem as ExHeapElem};
-use std::collections::{HashMap, HashSet, VecDeque};
+use aoc_common::search::astar;
+use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
 
 pub struct Day18;
@@ -81,10 +82,9 @@ impl AocDay for Day18 {
         let kr = &keys_required;
 
         let start: (Bitset, u8) = (Bitset::empty(), b'@');
-        search(
-            start,
-            |(collected, _)| collected.count() == key_count,
-            |(collected, point), distance| {
+        astar(
+            vec![start],
+            |&(collected, point)| {
                 keys.iter().filter_map(move |&k| {
                     if collected.contains((k - b'a').into()) {
                         // visited in this route
@@ -93,15 +93,17 @@ impl AocDay for Day18 {
                     if !collected.contains_all(kr[&k]) {
                         return None;
                     }
-                    Some(ExHeapElem {
-                        elem: (collected.set((k - b'a').into()), k),
-                        distance: distance + dc[&(point, k)] as u64,
-                        heuristic: key_count - collected.count(),
-                    })
+                    Some((
+                        (collected.set((k - b'a').into()), k),
+                        dc[&(point, k)] as u64,
+                    ))
                 })
             },
+            |(collected, _)| key_count - collected.count(),
+            |(collected, _)| collected.count() == key_count,
         )
-        .1
+        .unwrap()
+        .cost
     }
 
     fn part_2(input: &Self::Input) -> Self::Result2 {
@@ -191,10 +193,9 @@ impl AocDay for Day18 {
         let kr = &keys_required;
 
         let start: (Bitset, [u8; 4]) = (Bitset::empty(), [b'@', b'?', b'>', b'=']);
-        search(
-            start,
-            |(collected, _)| collected.count() == key_count,
-            |(collected, points), distance| {
+        astar(
+            vec![start],
+            |&(collected, points)| {
                 keys.iter().filter_map(move |&k| {
                     if collected.contains((k - b'a').into()) {
                         // visited in this route
@@ -212,15 +213,14 @@ impl AocDay for Day18 {
                     #[allow(clippy::clone_on_copy)]
                     let mut new_point = points.clone();
                     new_point[i] = k;
-                    Some(ExHeapElem {
-                        elem: (collected.set((k - b'a').into()), new_point),
-                        distance: distance + *d as u64,
-                        heuristic: key_count - collected.count(),
-                    })
+                    Some(((collected.set((k - b'a').into()), new_point), *d as u64))
                 })
             },
+            |(collected, _)| key_count - collected.count(),
+            |(collected, _)| collected.count() == key_count,
         )
-        .1
+        .unwrap()
+        .cost
     }
 }
 
@@ -234,40 +234,37 @@ fn bfs(
     start_symbol: u8,
     fill_keys: bool,
 ) {
-    let mut queue: VecDeque<(Point2D<i64>, Bitset, usize)> = VecDeque::new();
-    queue.push_back((pos, Bitset::empty(), 0));
-    let mut visited: HashSet<Point2D<i64>> = HashSet::new();
+    let symbol_at = |Point2D(x, y): Point2D<i64>| {
+        map.get(y as usize)
+            .and_then(|r| r.get(x as usize))
+            .copied()
+            .unwrap_or(b'#')
+    };
 
-    while let Some((pos, keys, steps)) = queue.pop_front() {
-        // dbg!(&queue);
-        if visited.contains(&pos)
-            || !(0..=map[0].len() as i64).contains(&pos.0)
-            || !(0..=map.len() as i64).contains(&pos.1)
-            || map[pos.1 as usize][pos.0 as usize] == b'#'
-        {
-            continue;
-        }
-        visited.insert(pos);
-
-        let symbol = map[pos.1 as usize][pos.0 as usize];
+    let distances = bfs_distances(vec![pos], |&p| {
+        Direction::array()
+            .iter()
+            .map(move |d| d.next_point(p))
+            .filter(|&p| symbol_at(p) != b'#')
+    });
 
-        let new_keys = if (b'A'..=b'Z').contains(&symbol) && fill_keys {
-            keys.set(((symbol + 32) - b'a').into())
-        } else {
-            keys
-        };
-        if (b'a'..=b'z').contains(&symbol) {
-            if fill_keys {
-                keys_required.insert(symbol, new_keys);
-            }
-            distance_cache.insert((start_symbol, symbol), steps as i64);
-            // we also know what doors we've passed through
-            // could we add some distance cache entries here?
+    for (&pos, steps) in distances.iter() {
+        let symbol = symbol_at(pos);
+        if !symbol.is_ascii_lowercase() {
+            continue;
         }
-
-        for d in Direction::array() {
-            let pos = d.next_point(pos);
-            queue.push_back((pos, new_keys, steps + 1))
+        distance_cache.insert((start_symbol, symbol), steps as i64);
+        if fill_keys {
+            // the doors passed through on the way here
+            let doors: Bitset = distances
+                .path_to(&pos)
+                .unwrap()
+                .into_iter()
+                .map(symbol_at)
+                .filter(u8::is_ascii_uppercase)
+                .map(|door| (door.to_ascii_lowercase() - b'a').into())
+                .collect();
+            keys_required.insert(symbol, doors);
         }
     }
 }
@@ -1,9 +1,16 @@
-use super::intcode::Machine;
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
+use aoc_intcode::{read_tape, Machine};
 
 pub struct Day19;
 
+/// Each drone is sent to one place, so they're all spawned from this one.
+fn drone(input: &[i64]) -> Machine {
+    let mut machine = Machine::new(input);
+    machine.predecode();
+    machine
+}
+
 impl AocDay for Day19 {
     type Input = Vec<i64>;
     type Result1 = usize;
@@ -13,16 +20,17 @@ impl AocDay for Day19 {
         19
     }
     fn load() -> Res<Self::Input> {
-        Machine::load_tape_from_file("data/2019/day_19.in")
+        read_tape("data/2019/day_19.in")
     }
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
+        let drone = drone(input);
         (0..50)
             .map(|x| {
                 (0..50)
                     .filter(|&y| {
-                        let mut machine = Machine::new(input, vec![x, y]);
-                        machine.run_to_output(None) == Some(1)
+                        let mut machine = drone.spawn_with_input(vec![x, y]);
+                        machine.next_output() == Ok(Some(1))
                     })
                     .count()
             })
@@ -32,9 +40,10 @@ impl AocDay for Day19 {
     fn part_2(input: &Self::Input) -> Self::Result2 {
         let mut min_x = 0;
 
+        let drone = drone(input);
         let check = |x: i64, y: i64| -> bool {
-            let mut machine = Machine::new(input, vec![x, y]);
-            machine.run_to_output(None) == Some(1)
+            let mut machine = drone.spawn_with_input(vec![x, y]);
+            machine.next_output() == Ok(Some(1))
         };
 
         for y in 100.. {
@@ -1,8 +1,9 @@
 use aoc_common::aoc_day::AocDay;
+use aoc_common::distances::poi_graph;
 use aoc_common::files::Res;
 use aoc_common::geometry::Direction;
-use aoc_common::search::{search, HeapElem};
-use std::collections::{HashMap, HashSet, VecDeque};
+use aoc_common::search::dijkstra;
+use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
 
 /// Note that most of the time here is spent in the "setup" phase.
@@ -42,24 +43,22 @@ impl AocDay for Day20 {
     fn part_1(input: &Self::Input) -> Self::Result1 {
         let graph = &input;
 
-        search(
-            Portal('A', 'A', false),
-            |p| matches!(p, Portal('Z', 'Z', _)),
-            |portal, distance| {
-                graph[&portal].iter().filter_map(move |(p, d)| {
+        dijkstra(
+            vec![Portal('A', 'A', false)],
+            |portal| {
+                graph[portal].iter().filter_map(move |(p, d)| {
                     if p.0 == 'A' && p.1 == 'A' {
                         None
                     } else {
-                        Some(HeapElem {
-                            elem: Portal(p.0, p.1, !p.2),
-                            distance: *d as u64 + distance + 1,
-                            heuristic: 0,
-                        })
+                        Some((Portal(p.0, p.1, !p.2), *d as u64 + 1))
                     }
                 })
             },
+            |p| matches!(p, Portal('Z', 'Z', _)),
         )
-        .1 - 1
+        .unwrap()
+        .cost
+            - 1
     }
 
     /// Simply process the input, then find all the portals reachable from other portals, then do an A*.
@@ -68,84 +67,68 @@ impl AocDay for Day20 {
     fn part_2(input: &Self::Input) -> Self::Result2 {
         let graph = &input;
 
-        search(
-            (Portal('A', 'A', false), 0),
-            |p| matches!(p, (Portal('Z', 'Z', _), -1)),
-            move |(portal, depth), distance| {
+        dijkstra(
+            vec![(Portal('A', 'A', false), 0)],
+            move |&(portal, depth)| {
                 graph[&portal]
                     .iter()
-                    .map(move |(p, d)| HeapElem {
-                        elem: (Portal(p.0, p.1, !p.2), depth + if p.2 { 1 } else { -1 }),
-                        distance: *d as u64 + distance + 1,
-                        heuristic: 0,
+                    .map(move |(p, d)| {
+                        (
+                            (Portal(p.0, p.1, !p.2), depth + if p.2 { 1 } else { -1 }),
+                            *d as u64 + 1,
+                        )
                     })
-                    .filter(|he| he.elem.1 >= -1)
-                    .filter(|he| he.elem.0 .0 != 'A' || he.elem.0 .1 != 'A')
-                    .filter(|he| !(he.elem.0 .0 == 'Z' && he.elem.0 .1 == 'Z') || he.elem.1 == -1)
+                    .filter(|((_, depth), _)| *depth >= -1)
+                    .filter(|((p, _), _)| p.0 != 'A' || p.1 != 'A')
+                    .filter(|((p, depth), _)| !(p.0 == 'Z' && p.1 == 'Z') || *depth == -1)
                     // since it's recursive, just stop at 50 mazes deep
-                    .filter(|he| he.elem.1 < 50)
+                    .filter(|((_, depth), _)| *depth < 50)
             },
+            |p| matches!(p, (Portal('Z', 'Z', _), -1)),
         )
-        .1 - 1
+        .unwrap()
+        .cost
+            - 1
     }
 }
 
 /// Setup a graph of `Portal -> [(Portal, distance)]`
 fn setup_graph(map: &[Vec<MapElem>]) -> HashMap<Portal, Vec<(Portal, i64)>> {
-    let mut graph = HashMap::new();
-
-    let mut queue: VecDeque<(i64, i64, i64)> = VecDeque::new();
-    let mut visited: HashSet<(i64, i64)> = HashSet::new();
-    for (y, row) in map.iter().enumerate() {
-        for (x, p) in row.iter().enumerate() {
-            if let MapElem::Portal(start) = p {
-                queue.clear();
-                visited.clear();
-
-                let mut successors: Vec<(Portal, i64)> = Vec::new();
-                // TODO, start distance is -2
-                queue.push_back((x as i64, y as i64, -2));
-                // visited.insert((x as i64, y as i64));
-
-                while let Some((x, y, dist)) = queue.pop_front() {
-                    if visited.contains(&(x, y)) {
-                        continue;
-                    }
-                    let (x, y) = if (0..(row.len() as i64)).contains(&x)
-                        && (0..(map.len() as i64)).contains(&y)
-                    {
-                        (x, y)
-                    } else {
-                        continue;
-                    };
-                    visited.insert((x as i64, y as i64));
-                    if let Some(MapElem::Portal(succ)) =
-                        map.get(y as usize).and_then(|r| r.get(x as usize))
-                    {
-                        if succ != start {
-                            successors.push((*succ, dist));
-                            continue;
-                        }
-                        for dir in Direction::array() {
-                            let (x, y) = dir.next_point((x, y));
-                            queue.push_back((x, y, dist + 1));
-                        }
-                    }
-                    if let Some(MapElem::Path) = map.get(y as usize).and_then(|r| r.get(x as usize))
-                    {
-                        for dir in Direction::array() {
-                            let (x, y) = dir.next_point((x, y));
-                            queue.push_back((x, y, dist + 1));
-                        }
-                    }
-                }
-
-                graph.insert(*start, successors);
-            }
-        }
-    }
-
-    graph
+    let at = |(x, y): (i64, i64)| map.get(y as usize).and_then(|r| r.get(x as usize)).copied();
+    let portal_at = |p| match at(p) {
+        Some(MapElem::Portal(portal)) => portal,
+        _ => unreachable!("Not a portal at {:?}", p),
+    };
+
+    let portals = map.iter().enumerate().flat_map(|(y, row)| {
+        row.iter()
+            .enumerate()
+            .filter(|(_, p)| matches!(p, MapElem::Portal(_)))
+            .map(move |(x, _)| (x as i64, y as i64))
+    });
+
+    poi_graph(
+        portals,
+        |&p| {
+            Direction::array()
+                .iter()
+                .map(move |dir| dir.next_point(p))
+                .filter(|&p| matches!(at(p), Some(MapElem::Path | MapElem::Portal(_))))
+        },
+        |&p| matches!(at(p), Some(MapElem::Portal(_))),
+    )
+    .into_iter()
+    .map(|(p, successors)| {
+        (
+            portal_at(p),
+            successors
+                .into_iter()
+                // the portal tiles are a step outside the path at each end
+                .map(|(s, dist)| (portal_at(s), dist as i64 - 2))
+                .collect(),
+        )
+    })
+    .collect()
 }
 
 #[derive(std::fmt::Debug, Copy, Clone, Hash, Eq, PartialEq)]
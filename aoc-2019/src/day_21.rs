@@ -1,10 +1,8 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::bitset::Bitset;
 use aoc_common::files::Res;
+use aoc_intcode::{read_tape, AsciiConsole, Machine, Reply};
 use itertools::Itertools;
-use std::convert::TryFrom;
-
-use super::intcode::Machine;
 
 pub struct Day21;
 
@@ -17,7 +15,7 @@ impl AocDay for Day21 {
         21
     }
     fn load() -> Res<Self::Input> {
-        Machine::load_tape_from_file("data/2019/day_21.in")
+        read_tape("data/2019/day_21.in")
     }
 
     fn part_1(code: &Self::Input) -> Self::Result1 {
@@ -29,19 +27,12 @@ impl AocDay for Day21 {
             Rule(OR, 'T', 'J'),  // OR the above two rules
         ];
 
-        let mut input = input.iter().map(Rule::to_string).join("\n");
-        input.push_str("\nWALK\n");
-        let input: Vec<i64> = input.bytes().map(|b| b as i64).collect();
-
-        let mut machine = Machine::new(code, input);
-        machine.run_to_completion();
+        let reply = run_springscript(code, &input, "WALK");
 
-        let result = machine.output.iter().find(|&&b| u8::try_from(b).is_err());
-
-        if let Some(res) = result {
-            *res
+        if let Some(&res) = reply.values.first() {
+            res
         } else {
-            let output: String = machine.output.iter().map(|&b| b as u8 as char).collect();
+            let output = reply.text;
 
             println!("Output: {}", output);
             -1
@@ -68,19 +59,12 @@ impl AocDay for Day21 {
             Rule(OR, 'T', 'J'), //
         ];
 
-        let mut input = input.iter().map(Rule::to_string).join("\n");
-        input.push_str("\nRUN\n");
-        let input: Vec<i64> = input.bytes().map(|b| b as i64).collect();
-
-        let mut machine = Machine::new(code, input);
-        machine.run_to_completion();
+        let reply = run_springscript(code, &input, "RUN");
 
-        let result = machine.output.iter().find(|&&b| u8::try_from(b).is_err());
-
-        if let Some(res) = result {
-            *res
+        if let Some(&res) = reply.values.first() {
+            res
         } else {
-            let output: String = machine.output.iter().map(|&b| b as u8 as char).collect();
+            let output = reply.text;
             let output = output.lines().skip(7).join("\n");
 
             println!("Output: \n{}", output);
@@ -90,6 +74,15 @@ impl AocDay for Day21 {
     }
 }
 
+/// Run the rules, then walk or run, giving what the springdroid says.
+fn run_springscript(code: &[i64], rules: &[Rule], mode: &str) -> Reply {
+    let script = rules
+        .iter()
+        .map(Rule::to_string)
+        .chain(Some(mode.to_string()));
+    AsciiConsole::new(Machine::new(code)).run_script(script)
+}
+
 #[allow(unused)]
 fn run_springstep<const N: usize>(rules: &[Rule], input: Bitset, log: bool) -> bool {
     use Op::*;
@@ -0,0 +1,143 @@
+use aoc_common::aoc_day::AocDay;
+use aoc_common::files::Res;
+use aoc_intcode::{read_tape, Event, Machine, Message, Network, Route};
+
+pub struct Day23;
+
+const COMPUTERS: usize = 50;
+const NAT: i64 = 255;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Packet {
+    dest: i64,
+    x: i64,
+    y: i64,
+}
+
+impl Message for Packet {
+    const LEN: usize = 3;
+
+    fn from_output(values: &[i64]) -> Self {
+        Packet {
+            dest: values[0],
+            x: values[1],
+            y: values[2],
+        }
+    }
+
+    fn to_input(&self) -> Vec<i64> {
+        vec![self.x, self.y]
+    }
+}
+
+/// Every computer booted with its address, with packets for the NAT coming out.
+fn network(code: &[i64]) -> Network<Packet> {
+    let machines = (0..COMPUTERS)
+        .map(|address| Machine::new(code).with_input(vec![address as i64]))
+        .collect();
+    Network::new(machines)
+        .with_empty_input(-1)
+        .with_router(|_, packet: &Packet| match packet.dest {
+            NAT => Route::Out,
+            dest => Route::To(dest as usize),
+        })
+}
+
+impl AocDay for Day23 {
+    type Input = Vec<i64>;
+    type Result1 = i64;
+    type Result2 = i64;
+
+    fn day() -> usize {
+        23
+    }
+    fn load() -> Res<Self::Input> {
+        read_tape("data/2019/day_23.in")
+    }
+
+    fn part_1(code: &Self::Input) -> Self::Result1 {
+        match network(code).run() {
+            Event::Output(_, packet) => packet.y,
+            event => panic!("network stopped with {:?}", event),
+        }
+    }
+
+    fn part_2(code: &Self::Input) -> Self::Result2 {
+        let mut network = network(code);
+        let mut nat = None;
+        let mut last_y = None;
+        loop {
+            match network.run() {
+                Event::Output(_, packet) => nat = Some(packet),
+                Event::Idle => {
+                    let packet = nat.expect("the network is idle with nothing to wake it");
+                    if last_y == Some(packet.y) {
+                        return packet.y;
+                    }
+                    last_y = Some(packet.y);
+                    network.send(0, packet);
+                }
+                event => panic!("network stopped with {:?}", event),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc_intcode::assemble;
+
+    /// Computer 1 starts by sending 5 to the NAT. Anything sent to computers 0 to 2 is passed
+    /// along to the next one, and computer 3 sends it to the NAT, adding 10 while it's under 20.
+    fn nic() -> Vec<i64> {
+        assemble(
+            "
+                in -> [address]
+                eq [address], #1 -> [t]
+                jz [t], #loop
+                out #255
+                out #0
+                out #5
+            loop:
+                in -> [x]
+                eq [x], #-1 -> [t]
+                jnz [t], #loop
+                in -> [y]
+                eq [address], #3 -> [t]
+                jnz [t], #last
+                add [address], #1 -> [dest]
+                out [dest]
+                out [x]
+                out [y]
+                jz #0, #loop
+            last:
+                lt [y], #20 -> [t]
+                jz [t], #send
+                add [y], #10 -> [y]
+            send:
+                out #255
+                out [x]
+                out [y]
+                jz #0, #loop
+            address: data 0
+            t: data 0
+            x: data 0
+            y: data 0
+            dest: data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn part_1_test() {
+        assert_eq!(Day23::part_1(&nic()), 5);
+    }
+
+    #[test]
+    fn part_2_test() {
+        // the NAT sends 5, 15, 25, then 25 again
+        assert_eq!(Day23::part_2(&nic()), 25);
+    }
+}
@@ -0,0 +1,341 @@
+use aoc_common::aoc_day::{AocDay, NoPuzzle};
+use aoc_common::files::Res;
+use aoc_intcode::{read_tape, AsciiConsole, Machine};
+use std::collections::HashSet;
+
+pub struct Day25;
+
+/// Items that end the game, or leave the droid stuck, when they're picked up.
+const DANGEROUS: [&str; 5] = [
+    "escape pod",
+    "giant electromagnet",
+    "infinite loop",
+    "molten lava",
+    "photons",
+];
+
+/// Something that takes the droid's commands and says what happened.
+trait Droid {
+    fn command(&mut self, command: &str) -> String;
+}
+
+impl Droid for AsciiConsole {
+    fn command(&mut self, command: &str) -> String {
+        AsciiConsole::command(self, command).text
+    }
+}
+
+impl AocDay for Day25 {
+    type Input = Vec<i64>;
+    type Result1 = i64;
+    type Result2 = NoPuzzle;
+
+    fn day() -> usize {
+        25
+    }
+    fn load() -> Res<Self::Input> {
+        read_tape("data/2019/day_25.in")
+    }
+
+    fn part_1(code: &Self::Input) -> Self::Result1 {
+        let mut console = AsciiConsole::new(Machine::new(code));
+        let intro = console.read().text;
+        let text = get_through(&mut console, &intro).expect("no way past the checkpoint");
+        password(&text).expect("no password")
+    }
+
+    fn part_2(_code: &Self::Input) -> Self::Result2 {
+        NoPuzzle
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Room {
+    name: String,
+    doors: Vec<String>,
+    items: Vec<String>,
+}
+
+/// The last room in `text`, which is where the droid ended up.
+fn parse_room(text: &str) -> Option<Room> {
+    let start = text.rfind("== ")?;
+    let mut lines = text[start..].lines();
+    let name = lines.next()?.trim_matches(|c| c == '=' || c == ' ');
+    let mut room = Room {
+        name: name.to_string(),
+        doors: vec![],
+        items: vec![],
+    };
+
+    let mut list = None;
+    for line in lines {
+        match line {
+            "Doors here lead:" => list = Some(&mut room.doors),
+            "Items here:" => list = Some(&mut room.items),
+            _ => match (line.strip_prefix("- "), &mut list) {
+                (Some(entry), Some(list)) => list.push(entry.to_string()),
+                _ => list = None,
+            },
+        }
+    }
+    Some(room)
+}
+
+fn opposite(door: &str) -> &'static str {
+    match door {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        _ => panic!("no way back through {}", door),
+    }
+}
+
+/// Walking every room from the start, picking up everything that's safe to.
+struct Explorer<'a, D> {
+    droid: &'a mut D,
+    seen: HashSet<String>,
+    path: Vec<String>,
+    items: Vec<String>,
+    /// The way from the start to the checkpoint, and the door from there to the floor that
+    /// weighs the droid.
+    checkpoint: Option<(Vec<String>, String)>,
+}
+
+impl<'a, D: Droid> Explorer<'a, D> {
+    /// Explore everything past `room`, ending back in it.
+    fn explore(&mut self, room: &Room) {
+        for item in &room.items {
+            if !DANGEROUS.contains(&item.as_str()) {
+                self.droid.command(&format!("take {}", item));
+                self.items.push(item.clone());
+            }
+        }
+
+        for door in &room.doors {
+            if self.path.last().map(|last| opposite(last)) == Some(door.as_str()) {
+                continue;
+            }
+            let text = self.droid.command(door);
+            let next = parse_room(&text).expect("the droid is lost");
+            if next.name == room.name {
+                // thrown back by the pressure-sensitive floor
+                self.checkpoint = Some((self.path.clone(), door.clone()));
+                continue;
+            }
+            if self.seen.insert(next.name.clone()) {
+                self.path.push(door.clone());
+                self.explore(&next);
+                self.path.pop();
+            }
+            self.droid.command(opposite(door));
+        }
+    }
+}
+
+/// Pick up everything, go to the checkpoint, and try carrying different items onto the floor
+/// until the droid weighs the right amount. Too heavy with some items means it's too heavy
+/// with any more, and too light means it's too light with any fewer, so those aren't tried.
+/// Gives what it says when it gets through.
+fn get_through(droid: &mut impl Droid, intro: &str) -> Option<String> {
+    let start = parse_room(intro)?;
+    let mut explorer = Explorer {
+        droid,
+        seen: Some(start.name.clone()).into_iter().collect(),
+        path: vec![],
+        items: vec![],
+        checkpoint: None,
+    };
+    explorer.explore(&start);
+    let Explorer {
+        droid,
+        items,
+        checkpoint,
+        ..
+    } = explorer;
+    let (path, floor) = checkpoint?;
+    for door in &path {
+        droid.command(door);
+    }
+
+    let all = (1u32 << items.len()) - 1;
+    let mut held = all;
+    let mut too_heavy: Vec<u32> = vec![];
+    let mut too_light: Vec<u32> = vec![];
+    for i in 0..=all {
+        // gray code, so only one item changes each time
+        let want = all ^ i ^ (i >> 1);
+        for (n, item) in items.iter().enumerate() {
+            match ((held >> n) & 1, (want >> n) & 1) {
+                (1, 0) => droid.command(&format!("drop {}", item)),
+                (0, 1) => droid.command(&format!("take {}", item)),
+                _ => continue,
+            };
+        }
+        held = want;
+
+        if too_heavy.iter().any(|&heavy| heavy & !want == 0)
+            || too_light.iter().any(|&light| want & !light == 0)
+        {
+            continue;
+        }
+        let text = droid.command(&floor);
+        if text.contains("Droids on this ship are heavier") {
+            too_light.push(want);
+        } else if text.contains("Droids on this ship are lighter") {
+            too_heavy.push(want);
+        } else {
+            return Some(text);
+        }
+    }
+    None
+}
+
+/// The last number in `text`.
+fn password(text: &str) -> Option<i64> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .rfind(|digits| !digits.is_empty())?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A ship laid out like this, where the floor is east of the checkpoint and wants the
+    /// droid carrying 11 of the weights:
+    ///
+    /// ```text
+    /// [Pantry] - [Hull Breach] - [Lab] - [Checkpoint] - [Floor]
+    ///                 |
+    ///             [Storage]
+    /// ```
+    struct Ship {
+        rooms: HashMap<&'static str, Vec<(&'static str, &'static str)>>,
+        items: HashMap<&'static str, Vec<String>>,
+        weights: HashMap<&'static str, u32>,
+        here: &'static str,
+        held: Vec<String>,
+    }
+
+    impl Ship {
+        fn new() -> Self {
+            let rooms = vec![
+                (
+                    "Hull Breach",
+                    vec![("west", "Pantry"), ("east", "Lab"), ("south", "Storage")],
+                ),
+                ("Pantry", vec![("east", "Hull Breach")]),
+                ("Storage", vec![("north", "Hull Breach")]),
+                (
+                    "Lab",
+                    vec![("west", "Hull Breach"), ("east", "Security Checkpoint")],
+                ),
+                (
+                    "Security Checkpoint",
+                    vec![("west", "Lab"), ("east", "Floor")],
+                ),
+            ];
+            let items = vec![
+                ("Pantry", vec!["mug".to_string(), "molten lava".to_string()]),
+                ("Storage", vec!["coin".to_string(), "ring".to_string()]),
+                ("Lab", vec!["boot".to_string()]),
+            ];
+            let weights = vec![("mug", 1), ("coin", 2), ("ring", 8), ("boot", 4)];
+            Ship {
+                rooms: rooms.into_iter().collect(),
+                items: items.into_iter().collect(),
+                weights: weights.into_iter().collect(),
+                here: "Hull Breach",
+                held: vec![],
+            }
+        }
+
+        fn describe(&self, room: &str) -> String {
+            let mut text = format!("\n\n\n== {} ==\nA room.\n\nDoors here lead:\n", room);
+            for (door, _) in &self.rooms[room] {
+                text.push_str(&format!("- {}\n", door));
+            }
+            if let Some(items) = self.items.get(room).filter(|items| !items.is_empty()) {
+                text.push_str("\nItems here:\n");
+                for item in items {
+                    text.push_str(&format!("- {}\n", item));
+                }
+            }
+            text + "\nCommand?\n"
+        }
+    }
+
+    impl Droid for Ship {
+        fn command(&mut self, command: &str) -> String {
+            if let Some(item) = command.strip_prefix("take ") {
+                let items = self.items.get_mut(self.here).unwrap();
+                items.retain(|i| i != item);
+                assert_ne!(item, "molten lava", "the droid melted");
+                self.held.push(item.to_string());
+                return format!("\nYou take the {}.\n\nCommand?\n", item);
+            }
+            if let Some(item) = command.strip_prefix("drop ") {
+                self.held.retain(|i| i != item);
+                self.items
+                    .entry(self.here)
+                    .or_default()
+                    .push(item.to_string());
+                return format!("\nYou drop the {}.\n\nCommand?\n", item);
+            }
+
+            let &(_, to) = self.rooms[self.here]
+                .iter()
+                .find(|&&(door, _)| door == command)
+                .expect("no door there");
+            if to != "Floor" {
+                self.here = to;
+                return self.describe(to);
+            }
+            let weight: u32 = self
+                .held
+                .iter()
+                .map(|item| self.weights[item.as_str()])
+                .sum();
+            let mut text = "\n\n\n== Pressure-Sensitive Floor ==\nA scale.\n\n".to_string();
+            match weight {
+                11 => text + "Analysis complete! You may proceed.\nType 4096 at the airlock.\n",
+                w if w < 11 => {
+                    text += "Alert! Droids on this ship are heavier than the detected value!\n";
+                    text + &self.describe(self.here)
+                }
+                _ => {
+                    text += "Alert! Droids on this ship are lighter than the detected value!\n";
+                    text + &self.describe(self.here)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rooms() {
+        let ship = Ship::new();
+        assert_eq!(
+            parse_room(&ship.describe("Pantry")),
+            Some(Room {
+                name: "Pantry".to_string(),
+                doors: vec!["east".to_string()],
+                items: vec!["mug".to_string(), "molten lava".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn part_1_test() {
+        let mut ship = Ship::new();
+        let intro = ship.describe("Hull Breach");
+        let text = get_through(&mut ship, &intro).unwrap();
+        assert_eq!(password(&text), Some(4096));
+
+        let mut held = ship.held.clone();
+        held.sort();
+        assert_eq!(held, vec!["coin", "mug", "ring"]);
+    }
+}
@@ -1,5 +1,7 @@
-use aoc_common::files::{read_better, Res};
+use aoc_common::files::Res;
 use aoc_common::geometry::{self, Direction};
+use aoc_common::viz::Recorder;
+use aoc_intcode::{read_tape, Event, Machine, Network, Status};
 use std::collections::{HashMap, HashSet};
 
 struct Permutations {
@@ -47,305 +49,56 @@ impl Iterator for Permutations {
     }
 }
 
-#[derive(Debug, Default)]
-pub struct Machine {
-    pub mem: Vec<i64>,
-    pub op_index: usize,
-    pub input: Vec<i64>,
-    pub input_index: usize,
-    pub output: Vec<i64>,
-    pub relative_base: i64,
-}
-
-#[derive(Debug)]
-enum Mode {
-    Immediate,
-    Address,
-    Relative,
-}
-impl Mode {
-    fn from(val: i64) -> Self {
-        match val {
-            0 => Mode::Address,
-            1 => Mode::Immediate,
-            2 => Mode::Relative,
-            x => panic!("Unknown mode - {}", x),
-        }
-    }
-}
-
-impl Machine {
-    pub fn new(mem: &[i64], input: Vec<i64>) -> Self {
-        Machine {
-            mem: mem.to_owned(),
-            input,
-            ..Machine::default()
-        }
-    }
-
-    fn ck_inst(&self, val: i64) -> usize {
-        if (0..=9).contains(&val) || val == 99 {
-            val as usize
-        } else {
-            panic!("Invalid instruction {}!\n{:?}", val, self)
-        }
-    }
-
-    fn ck_addr(&mut self, val: i64) -> usize {
-        if 0 <= val && val < self.mem.len() as i64 {
-            val as usize
-        } else {
-            self.mem.resize(val as usize + 1, 0);
-            val as usize
-        }
-    }
-
-    pub fn run_to_completion(&mut self) {
-        while self.compute_step().is_some() {}
-    }
+pub fn day_5() -> Res<(i64, i64)> {
+    println!("Day 5");
 
-    pub fn run_to_output(&mut self, input: Option<i64>) -> Option<i64> {
-        if let Some(in_val) = input {
-            self.input.push(in_val);
-        }
-        let out_length = self.output.len();
-        loop {
-            let next = self.compute_step();
-            if next.is_none() {
-                break None;
-            }
-            if self.output.len() != out_length {
-                break Some(self.output[out_length]);
-            }
-        }
-    }
+    let mem = read_tape("data/2019/day_5.in")?;
 
-    #[allow(dead_code)]
-    pub fn run_to_next_input(&mut self, input: Option<i64>) -> Option<usize> {
-        if let Some(in_val) = input {
-            self.input.push(in_val);
-        }
-        loop {
-            if self.mem[self.op_index] == 99 {
-                break None;
-            }
-            let next = self.compute_step();
-            if next.is_none() {
-                break None;
-            }
-            if self.mem[self.op_index] == 3 {
-                break Some(self.op_index);
-            }
-        }
-    }
+    let output = Machine::new(&mem).with_input(vec![1]).run_to_halt()?;
+    println!("  part 1 {:?}", output);
 
-    fn read_op_to_value(&mut self, op: i64, mode: Mode) -> i64 {
-        match mode {
-            Mode::Address => {
-                let addr = self.ck_addr(op);
-                self.mem[addr]
-            }
-            Mode::Immediate => op as i64,
-            Mode::Relative => {
-                let addr = self.ck_addr(self.relative_base + op);
-                self.mem[addr]
-            }
-        }
-    }
+    let output_2 = Machine::new(&mem).with_input(vec![5]).run_to_halt()?;
+    println!("  part 2 {:?}", output_2);
+    Ok((*output.last().unwrap(), *output_2.last().unwrap()))
+}
 
-    fn write_op_to_addr(&mut self, op: i64, mode: Mode) -> usize {
-        self.ck_addr(match mode {
-            Mode::Address => op,
-            Mode::Immediate => panic!("Writing in Immediate mode is not supported"),
-            Mode::Relative => self.relative_base + op,
-        })
+/// Run a chain of amplifiers with the given phases, feeding the last one back to the first if
+/// there's a `feedback` loop. The last signal out of the last amplifier is what's sent to the
+/// thrusters.
+fn amplify(mem: &[i64], phases: [i64; 5], feedback: bool) -> i64 {
+    let machines = phases
+        .iter()
+        .map(|&phase| Machine::new(mem).with_input(vec![phase]))
+        .collect();
+    let mut network = Network::new(machines);
+    for i in 0..4 {
+        network.connect(i, i + 1);
     }
-
-    fn read_op_and_modes(&self) -> (usize, Mode, Mode, Mode) {
-        let raw_val = self.mem[self.op_index];
-        (
-            self.ck_inst(raw_val % 100),
-            Mode::from(raw_val / 100 % 10),
-            Mode::from(raw_val / 1000 % 10),
-            Mode::from(raw_val / 10000),
-        )
+    if feedback {
+        network.connect(4, 0);
     }
+    network.send(0, 0);
 
-    fn read_operands_1(&mut self, mode: Mode) -> (i64, usize) {
-        (
-            self.read_op_to_value(self.mem[self.op_index + 1], mode),
-            self.op_index + 2,
-        )
-    }
-    fn read_operands_2(&mut self, mode_1: Mode, mode_2: Mode) -> (i64, i64, usize) {
-        (
-            self.read_op_to_value(self.mem[self.op_index + 1], mode_1),
-            self.read_op_to_value(self.mem[self.op_index + 2], mode_2),
-            self.op_index + 3,
-        )
-    }
-    fn read_operands_3(
-        &mut self,
-        mode_1: Mode,
-        mode_2: Mode,
-        mode_3: Mode,
-    ) -> (i64, i64, i64, usize) {
-        (
-            self.read_op_to_value(self.mem[self.op_index + 1], mode_1),
-            self.read_op_to_value(self.mem[self.op_index + 2], mode_2),
-            self.read_op_to_value(self.mem[self.op_index + 3], mode_3),
-            self.op_index + 4,
-        )
-    }
-
-    fn compute_step(&mut self) -> Option<usize> {
-        let (instruction, mode_1, mode_2, mode_3) = self.read_op_and_modes();
-        match instruction {
-            1 => {
-                let (val_1, val_2, dest, next) =
-                    self.read_operands_3(mode_1, mode_2, Mode::Immediate);
-                let dest = self.write_op_to_addr(dest, mode_3);
-                self.mem[dest] = val_1 + val_2;
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            2 => {
-                let (val_1, val_2, dest, next) =
-                    self.read_operands_3(mode_1, mode_2, Mode::Immediate);
-                let dest = self.write_op_to_addr(dest, mode_3);
-                self.mem[dest] = val_1 * val_2;
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            3 => {
-                let (dest, next) = self.read_operands_1(Mode::Immediate);
-                let dest = self.write_op_to_addr(dest, mode_1);
-                self.mem[dest] = self.input[self.input_index];
-                self.input_index += 1;
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            4 => {
-                let (val_1, next) = self.read_operands_1(mode_1);
-                self.output.push(val_1);
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            5 => {
-                let (val_1, val_2, maybe_next) = self.read_operands_2(mode_1, mode_2);
-                let next = if val_1 != 0 {
-                    self.ck_addr(val_2)
-                } else {
-                    maybe_next
-                };
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            6 => {
-                let (val_1, val_2, maybe_next) = self.read_operands_2(mode_1, mode_2);
-                let next = if val_1 == 0 {
-                    self.ck_addr(val_2)
-                } else {
-                    maybe_next
-                };
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            7 => {
-                let (val_1, val_2, dest, next) =
-                    self.read_operands_3(mode_1, mode_2, Mode::Immediate);
-                let dest = self.write_op_to_addr(dest, mode_3);
-                self.mem[dest] = if val_1 < val_2 { 1 } else { 0 };
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            8 => {
-                let (val_1, val_2, dest, next) =
-                    self.read_operands_3(mode_1, mode_2, Mode::Immediate);
-                let dest = self.write_op_to_addr(dest, mode_3);
-                self.mem[dest] = if val_1 == val_2 { 1 } else { 0 };
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            9 => {
-                let (val_1, next) = self.read_operands_1(mode_1);
-                self.relative_base += val_1;
-                self.op_index = next;
-                Some(self.op_index)
-            }
-            99 => Option::None,
-            x => panic!("Invalid instruction {}", x),
-        }
-    }
-
-    pub fn load_tape_from_file(file: &str) -> Res<Vec<i64>> {
-        Ok(read_better(file, &|s| s.parse::<i64>().unwrap())?
-            .next()
-            .unwrap())
+    match network.run() {
+        Event::Output(4, signal) => signal,
+        // the signal is left for the first amplifier, which has already halted
+        Event::Halted => *network.inbox(0).back().unwrap(),
+        event => panic!("amplifiers stopped with {:?}", event),
     }
 }
 
-pub fn day_5() -> Res<(i64, i64)> {
-    println!("Day 5");
-
-    let mem: Vec<i64> = read_better("data/2019/day_5.in", &|s| s.parse::<i64>().unwrap())?
-        .next()
-        .unwrap();
-
-    let mut machine = Machine::new(&mem, vec![1]);
-    machine.run_to_completion();
-    println!("  part 1 {:?}", machine.output);
-
-    let mut machine_2 = Machine::new(&mem, vec![5]);
-    machine_2.run_to_completion();
-    println!("  part 2 {:?}", machine_2.output);
-    Ok((
-        *machine.output.last().unwrap(),
-        *machine_2.output.last().unwrap(),
-    ))
-}
-
 pub fn day_7() -> Res<(i64, i64)> {
     println!("Day 7");
 
-    let mem: Vec<i64> = read_better("data/2019/day_7.in", &|s| s.parse::<i64>().unwrap())?
-        .next()
-        .unwrap();
-    let memref = &mem;
+    let mem = read_tape("data/2019/day_7.in")?;
 
     let thruster = Permutations::permute(1234, 43210)
-        .map(move |config| {
-            config.iter().fold(0, |acc, &v| {
-                let mut machine_a = Machine::new(memref, vec![v]);
-                machine_a.run_to_output(Some(acc));
-                machine_a.output[0]
-            })
-        })
+        .map(|config| amplify(&mem, config, false))
         .max();
     println!("  part 1 {:?}", thruster);
 
     let thruster_2 = Permutations::permute(56789, 98765)
-        .map(move |config| {
-            let mut machines: Vec<Machine> = config
-                .iter()
-                .map(|&v| Machine::new(memref, vec![v]))
-                .collect();
-
-            let mut next_machine = 0;
-            let mut last_output = 0;
-
-            loop {
-                let m: &mut Machine = &mut (machines[next_machine]);
-                next_machine = (next_machine + 1) % 5;
-                let output = m.run_to_output(Some(last_output));
-                if let Some(v) = output {
-                    last_output = v;
-                } else {
-                    let e = &machines[4];
-                    break e.output[e.output.len() - 1];
-                }
-            }
-        })
+        .map(|config| amplify(&mem, config, true))
         .max();
     println!("  part 2 {:?}", thruster_2);
 
@@ -355,28 +108,23 @@ pub fn day_7() -> Res<(i64, i64)> {
 pub fn day_9() -> Res<(i64, i64)> {
     println!("Day 9");
 
-    let mem: Vec<i64> = read_better("data/2019/day_9.in", &|s| s.parse::<i64>().unwrap())?
-        .next()
-        .unwrap();
-
-    let mut machine = Machine::new(&mem, vec![1]);
-    machine.run_to_completion();
-    println!("  part 1 {:?}", machine.output);
-    println!("    max mem content {:?}", machine.mem.iter().max());
-    println!("    machine mem size {:?}", machine.mem.len());
-
-    let mut machine_2 = Machine::new(&mem, vec![2]);
-    machine_2.run_to_completion();
-    println!("  part 2 {:?}", machine_2.output);
-    println!("    max mem content {:?}", machine_2.mem.iter().max());
-    println!("    machine mem size {:?}", machine_2.mem.len());
-    Ok((
-        *machine.output.last().unwrap(),
-        *machine_2.output.last().unwrap(),
-    ))
+    let mem = read_tape("data/2019/day_9.in")?;
+
+    let mut machine = Machine::new(&mem).with_input(vec![1]);
+    let output = machine.run_to_halt()?;
+    println!("  part 1 {:?}", output);
+    println!("    max mem content {:?}", machine.memory().iter().max());
+    println!("    machine mem size {:?}", machine.memory().len());
+
+    let mut machine_2 = Machine::new(&mem).with_input(vec![2]);
+    let output_2 = machine_2.run_to_halt()?;
+    println!("  part 2 {:?}", output_2);
+    println!("    max mem content {:?}", machine_2.memory().iter().max());
+    println!("    machine mem size {:?}", machine_2.memory().len());
+    Ok((*output.last().unwrap(), *output_2.last().unwrap()))
 }
 
-fn painter(machine: &mut Machine, start: bool) -> i64 {
+fn painter(machine: &mut Machine, start: bool) -> Res<i64> {
     let mut surface: HashMap<(i64, i64), bool> = HashMap::new();
     let mut pos = (0, 0);
     surface.insert(pos, start);
@@ -388,13 +136,14 @@ fn painter(machine: &mut Machine, start: bool) -> i64 {
         } else {
             0
         };
-        if let Some(out_val) = machine.run_to_output(Some(current)) {
+        machine.push_input(current);
+        if let Some(out_val) = machine.next_output()? {
             surface.insert(pos, out_val > 0);
         } else {
             break;
         }
 
-        let out_2 = machine.run_to_output(None);
+        let out_2 = machine.next_output()?;
         if let Some(out_val) = out_2 {
             dir = dir.rotate_cw_amount(out_val);
             pos = dir.next_point(pos);
@@ -404,24 +153,22 @@ fn painter(machine: &mut Machine, start: bool) -> i64 {
     }
 
     geometry::output_map(&surface);
-    surface.len() as i64
+    Ok(surface.len() as i64)
 }
 
 pub fn day_11() -> Res<(i64, i64)> {
     println!("Day 11");
 
-    let mem: Vec<i64> = read_better("data/2019/day_11.in", &|s| s.parse::<i64>().unwrap())?
-        .next()
-        .unwrap();
+    let mem = read_tape("data/2019/day_11.in")?;
 
-    let mut machine = Machine::new(&mem, vec![]);
+    let mut machine = Machine::new(&mem);
 
-    let res_1 = painter(&mut machine, false);
+    let res_1 = painter(&mut machine, false)?;
     println!("  part 1 {:?}", res_1);
 
-    let mut machine = Machine::new(&mem, vec![]);
+    let mut machine = Machine::new(&mem);
 
-    let res_2 = painter(&mut machine, true);
+    let res_2 = painter(&mut machine, true)?;
     println!("  part 2 {:?}", res_2);
 
     Ok((res_1, res_2))
@@ -430,16 +177,11 @@ pub fn day_11() -> Res<(i64, i64)> {
 pub fn day_13() -> Res<i64> {
     println!("Day 13");
 
-    let mem: Vec<i64> = read_better("data/2019/day_13.in", &|s| s.parse::<i64>().unwrap())?
-        .next()
-        .unwrap();
-
-    let mut machine = Machine::new(&mem, vec![]);
+    let mem = read_tape("data/2019/day_13.in")?;
 
-    machine.run_to_completion();
+    let output = Machine::new(&mem).run_to_halt()?;
 
-    let squares = machine
-        .output
+    let squares = output
         .chunks_exact(3)
         .map(|chunk| (chunk[0], chunk[1], chunk[2]))
         .collect::<Vec<_>>();
@@ -458,31 +200,33 @@ pub fn day_13() -> Res<i64> {
 
     println!("  part 1 {:?}", set.len());
 
-    let mut machine = Machine::new(&mem, vec![]);
-    machine.mem[0] = 2;
-    // let mut map: HashMap<(i64, i64), i64> = HashMap::new();
-    // let mut count = 0;
-    let mut finish = false;
+    let mut machine = Machine::new(&mem);
+    machine.poke(0, 2);
+    let mut recorder = Recorder::new("2019_day_13");
+    let mut map: HashMap<(i64, i64), i64> = HashMap::new();
+    let mut drawn = 0;
+    let mut score = 0;
+    let mut output = vec![];
 
-    machine.run_to_next_input(None);
     loop {
-        // for i in inputs {
-        // count += 1;
-        let squares = machine
-            .output
+        let (more, status) = machine.run_until_blocked();
+        output.extend(more);
+        let squares = output
             .chunks_exact(3)
             .map(|chunk| (chunk[0], chunk[1], chunk[2]))
             .collect::<Vec<_>>();
-        // Uncomment for visualisation.
-        // for &(x, y, ty) in &squares {
-        //     if x == -1 {
-        //         // this is the score
-        //         // dbg!(ty);
-        //     } else {
-        //         map.insert((x, y), ty);
-        //     }
-        // }
-        // geometry::output_map(&map);
+
+        recorder.frame(|| {
+            for &(x, y, ty) in &squares[drawn..] {
+                if x == -1 {
+                    score = ty;
+                } else {
+                    map.insert((x, y), ty);
+                }
+            }
+            drawn = squares.len();
+            format!("score: {}\n{}", score, geometry::format_map(&map))
+        });
 
         let ball = squares.iter().rev().find(|s| s.2 == 4).unwrap();
         let paddle = squares.iter().rev().find(|s| s.2 == 3).unwrap();
@@ -499,12 +243,14 @@ pub fn day_13() -> Res<i64> {
             _ => 0,
         };
         // dbg!((count, next, score));
-        if finish {
-            println!("  part 2 {:?}", score);
-            return Ok(score);
-        }
-        if None == machine.run_to_next_input(Some(next)) {
-            finish = true;
+        match status {
+            Status::NeedsInput => machine.push_input(next),
+            Status::Halted => {
+                println!("  part 2 {:?}", score);
+                return Ok(score);
+            }
+            Status::Output(_) => unreachable!("outputs are collected"),
+            Status::Fault(e) => return Err(e.into()),
         }
     }
 }
@@ -513,45 +259,6 @@ pub fn day_13() -> Res<i64> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn produce_copy() {
-        let input = vec![
-            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
-        ];
-        let mut machine = Machine::new(&input, vec![]);
-        machine.run_to_completion();
-        assert_eq!(machine.output, input);
-    }
-
-    #[test]
-    fn output_long_number() {
-        let input = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
-        let mut machine = Machine::new(&input, vec![]);
-        machine.run_to_completion();
-        let length = format!("{}", machine.output[0]).len();
-        assert_eq!(length, 16);
-    }
-
-    #[test]
-    fn output_middle() {
-        let input = vec![104, 1125899906842624, 99];
-        let mut machine = Machine::new(&input, vec![]);
-        machine.run_to_completion();
-        assert_eq!(machine.output[0], input[1]);
-    }
-
-    #[test]
-    fn increases_relative_base() {
-        let input = vec![109, 19];
-        let mut machine = Machine {
-            mem: input,
-            relative_base: 2000,
-            ..Machine::default()
-        };
-        machine.compute_step();
-        assert_eq!(machine.relative_base, 2019);
-    }
-
     #[test]
     fn day_5_test() -> Res<()> {
         let res = day_5()?;
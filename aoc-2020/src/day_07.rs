@@ -1,6 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::{read_lines, Res};
-use std::collections::{HashMap, HashSet, VecDeque};
+use aoc_common::graph::{Graph, NodeId};
 
 pub struct Day07;
 
@@ -20,60 +20,36 @@ impl AocDay for Day07 {
     }
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
-        let mut inverse_map: HashMap<&str, Vec<&str>> = HashMap::new();
-        let mut containing_bags: HashSet<&str> = HashSet::new();
-
-        for Rule { bag, contains } in input {
-            for (_, x) in contains {
-                if let Some(vec) = inverse_map.get_mut(x.as_str()) {
-                    vec.push(bag);
-                } else {
-                    inverse_map.insert(x, vec![bag]);
-                }
-            }
-        }
+        let contained_by = bag_graph(input).reversed();
+        let target = contained_by.id("shiny gold").unwrap();
 
-        let mut queue: VecDeque<&str> = VecDeque::new();
-        let target = "shiny gold".to_owned();
-        queue.push_back(&target);
-
-        while let Some(b) = queue.pop_front() {
-            if containing_bags.contains(b) {
-                continue;
-            }
-
-            containing_bags.insert(b);
-            if let Some(contains) = inverse_map.get(b) {
-                for x in contains.iter() {
-                    if containing_bags.contains(x) {
-                        continue;
-                    }
-                    queue.push_back(x);
-                }
-            }
-        }
-
-        containing_bags.len() - 1
+        contained_by.reachable_from(target).len() - 1
     }
 
     fn part_2(input: &Self::Input) -> Self::Result2 {
-        let mut map: HashMap<&str, &Rule> = HashMap::new();
+        let graph = bag_graph(input);
+        let target = graph.id("shiny gold").unwrap();
 
-        for r in input {
-            map.insert(&r.bag, r);
-        }
-        let target = "shiny gold".to_owned();
+        bag_contains(&graph, target) - 1
+    }
+}
 
-        bag_contains(&map, &target) - 1
+/// Edges from each bag to the bags it holds, weighted by how many.
+fn bag_graph(input: &[Rule]) -> Graph<u64> {
+    let mut graph = Graph::new();
+    for Rule { bag, contains } in input {
+        graph.node(bag);
+        for (count, x) in contains {
+            graph.add_edge(bag, x, *count as u64);
+        }
     }
+    graph
 }
 
-fn bag_contains(map: &HashMap<&str, &Rule>, bag: &str) -> u64 {
+fn bag_contains(graph: &Graph<u64>, bag: NodeId) -> u64 {
     let mut count = 1_u64;
-    if let Some(rule) = map.get(bag) {
-        for (c, b2) in &rule.contains {
-            count += *c as u64 * bag_contains(map, b2);
-        }
+    for (b2, c) in graph.edges(bag) {
+        count += c * bag_contains(graph, b2);
     }
 
     count
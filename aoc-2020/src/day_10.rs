@@ -1,5 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::{read_lines, Res};
+use aoc_common::memo::Memo;
 
 pub struct Day10;
 
@@ -44,25 +45,21 @@ impl AocDay for Day10 {
     fn part_2(input: &Self::Input) -> Self::Result2 {
         let mut input = input.clone();
         input.insert(0, 0);
-        let mut run = [1; 4];
-        for i in (0..(input.len() - 2)).rev() {
-            run.rotate_right(1);
-            run[0] = 0;
-
-            let max = input[i] + 3;
+        arrangements(&input, 0, &mut Memo::new())
+    }
+}
 
-            for x in 1..=3 {
-                if x + i >= input.len() {
-                    continue;
-                }
-                if input[x + i] <= max {
-                    run[0] += run[x];
-                }
-            }
+/// The ways to chain the adapters from `i` to the last, each within 3 jolts of the one before.
+fn arrangements(adapters: &[u64], i: usize, memo: &mut Memo<usize, u64>) -> u64 {
+    memo.get_or_insert_with(&i, |memo| {
+        if i == adapters.len() - 1 {
+            return 1;
         }
-
-        run[0]
-    }
+        (i + 1..adapters.len())
+            .take_while(|&j| adapters[j] <= adapters[i] + 3)
+            .map(|j| arrangements(adapters, j, memo))
+            .sum()
+    })
 }
 
 #[cfg(test)]
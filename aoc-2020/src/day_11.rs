@@ -1,6 +1,8 @@
-use aoc_common::files::Res;
 use aoc_common::aoc_day::AocDay;
-use std::{fs::read_to_string, mem::swap};
+use aoc_common::automaton::{Automaton, Dense, Neighbourhood};
+use aoc_common::files::Res;
+use aoc_common::viz::Recorder;
+use std::fs::read_to_string;
 
 pub struct Day11;
 
@@ -22,127 +24,61 @@ impl AocDay for Day11 {
 
     /// Model the input as cellular automata and run it until it is stable and count the number of seats.
     fn part_1((input, width, height): &Self::Input) -> Self::Result1 {
-        // double buffer, to cut down on allocation and freeing
-        let mut ca = input.clone();
-        let mut cb = input.clone();
-        loop {
-            run_step_vec(&ca, &mut cb, *width, *height, 4, false);
-
-            if ca == cb {
-                return cb.iter().filter(|&c| *c == '#').count();
-            } else {
-                swap(&mut ca, &mut cb);
-            }
-        }
+        let seats = Dense::new(
+            *width,
+            *height,
+            input.clone(),
+            &Neighbourhood::moore(),
+            |&c| c == '#',
+            |&c, occupied| seat_rule(c, occupied, 4),
+        );
+        settle(seats, "2020_day_11_part_1")
     }
 
     /// As part 1, but using a "sight" based neighbourhood.
     fn part_2((input, width, height): &Self::Input) -> Self::Result2 {
-        // double buffer, to cut down on allocation and freeing
-        let mut ca = input.clone();
-        let mut cb = input.clone();
-        loop {
-            run_step_vec(&ca, &mut cb, *width, *height, 5, true);
-
-            if ca == cb {
-                return cb.iter().filter(|&c| *c == '#').count();
-            } else {
-                swap(&mut ca, &mut cb);
-            }
-        }
-    }
-}
-
-fn run_step_vec(
-    ca: &[char],
-    new: &mut [char],
-    width: usize,
-    height: usize,
-    crowd: usize,
-    sight: bool,
-) {
-    for (i, c) in ca.iter().enumerate() {
-        new[i] = match c {
-            '#' => {
-                let occupied_neighbours =
-                    get_occupied_neighbours_vec(ca, i, (width as i32, height as i32), sight);
-                if occupied_neighbours >= crowd {
-                    'L'
-                } else {
-                    '#'
-                }
-            }
-            'L' => {
-                let occupied_neighbours =
-                    get_occupied_neighbours_vec(ca, i, (width as i32, height as i32), sight);
-                if occupied_neighbours == 0 {
-                    '#'
-                } else {
-                    'L'
-                }
-            }
-            c => *c,
-        }
+        let seats = Dense::new(
+            *width,
+            *height,
+            input.clone(),
+            &Neighbourhood::moore(),
+            |&c| c == '#',
+            |&c, occupied| seat_rule(c, occupied, 5),
+        )
+        .with_sight(|&c| c == '.');
+        settle(seats, "2020_day_11_part_2")
     }
-
-    // new
 }
 
-/// an empty string means off-grid
-fn get(ca: &[char], column: i32, row: i32, width: i32, height: i32) -> char {
-    // faster without this!
-    // if (0..width as i32).contains(&column) && (0..height as i32).contains(&row) {
-    if column >= 0 && row >= 0 && column < width && row < height {
-        ca[(column + width * row) as usize]
-    } else {
-        ' '
+/// People leave a seat when it is too crowded, and take one with no-one around.
+fn seat_rule(seat: char, occupied: usize, crowd: usize) -> char {
+    match seat {
+        '#' if occupied >= crowd => 'L',
+        'L' if occupied == 0 => '#',
+        c => c,
     }
 }
 
-type Point = (i32, i32);
-
-/// get the first non-empty cell value along the vector (checking the point first)
-fn get_vec(
-    ca: &[char],
-    (column, row): Point,
-    (c_vec, r_vec): Point,
-    (width, height): Point,
-    sight: bool,
-) -> char {
-    let cn = column + c_vec;
-    let rn = row + r_vec;
-    let hit = get(ca, cn, rn, width, height);
-    if hit == '.' && sight {
-        get_vec(ca, (cn, rn), (c_vec, r_vec), (width, height), sight)
-    } else {
-        hit
+/// Run it until it is stable and count the number of occupied seats.
+fn settle<A, F>(mut seats: Dense<char, A, F>, name: &str) -> usize
+where
+    A: Fn(&char) -> bool + Sync,
+    F: Fn(&char, usize) -> char + Sync,
+{
+    let mut recorder = Recorder::new(name).with_frame_time(0.2);
+    loop {
+        recorder.frame(|| draw(seats.cells(), seats.width()));
+        if !seats.step() {
+            return seats.population();
+        }
     }
 }
 
-fn get_occupied_neighbours_vec(
-    ca: &[char],
-    i: usize,
-    (width, height): Point,
-    sight: bool,
-) -> usize {
-    const NEIGHBOURS: [Point; 8] = [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-    let i = i as i32;
-    let row = i / width;
-    let col = i - (width * row);
-
-    NEIGHBOURS
-        .iter()
-        .filter(|(r, c)| '#' == get_vec(ca, (col, row), (*c, *r), (width, height), sight))
-        .count()
+fn draw(ca: &[char], width: usize) -> String {
+    ca.chunks(width)
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
+use aoc_common::numtheory::crt;
 use std::fs::read_to_string;
 
 pub struct Day13;
@@ -44,7 +45,7 @@ impl AocDay for Day13 {
     /// i.e. for timetable 7,13:
     ///   for 7 the offset is 0 (the pattern starts from position 0), and the period is 7 (when this bus next departs)
     ///   for 13 the offset is 77 (the first time we see a pattern that we accept) and the period is 91 (the number of steps until it appears again).
-    /// By always jumping by the period, we quickly find a start value which produces a valid pattern.
+    /// Each step is the Chinese remainder theorem on the pattern so far and the next bus.
     fn part_2((_, busses): &Self::Input) -> Self::Result2 {
         busses
             .iter()
@@ -57,24 +58,9 @@ impl AocDay for Day13 {
     }
 }
 
+/// Combine the pattern so far with the next bus, so that it also departs `o` minutes after the start.
 fn find_pattern((o, b): (usize, i64), offset: i64, period: i64) -> (i64, i64) {
-    let mut new_offset = offset;
-    loop {
-        if (new_offset + o as i64) % b == 0 {
-            break;
-        }
-        new_offset += period;
-    }
-
-    let mut new_period = period;
-    loop {
-        if (new_offset + new_period + o as i64) % b == 0 {
-            break;
-        }
-        new_period += period;
-    }
-
-    (new_offset, new_period)
+    crt(vec![(offset, period), (-(o as i64), b)]).expect("bus times are coprime")
 }
 
 #[cfg(test)]
@@ -1,5 +1,7 @@
 use aoc_common::aoc_day::AocDay;
+use aoc_common::exact_cover::ExactCover;
 use aoc_common::files::Res;
+use aoc_common::intervals::RangeSet;
 use std::fs::read_to_string;
 
 pub struct Day16;
@@ -33,59 +35,38 @@ impl AocDay for Day16 {
     }
 
     fn part_1((rules, _, tickets): &Self::Input) -> Self::Result1 {
+        let any = any_rule(rules);
         tickets
             .iter()
-            .map(|t| {
-                t.iter()
-                    .filter(|&v| !rules.iter().any(|r| r.valid(*v)))
-                    .sum::<u64>()
-            })
+            .map(|t| t.iter().filter(|&&v| !any.contains(v)).sum::<u64>())
             .sum()
     }
 
     fn part_2((rules, mine, tickets): &Self::Input) -> Self::Result2 {
+        let any = any_rule(rules);
         let valid_tickets = tickets
             .iter()
-            .filter(|t| t.iter().all(|&v| rules.iter().any(|r| r.valid(v))))
+            .filter(|t| t.iter().all(|&v| any.contains(v)))
             .collect::<Vec<_>>();
 
-        let mut possible_rules_in_position: [usize; 0x14] = [0b11111111111111111111; 20];
-
-        // get rid of all the rules that don't fit a column
-        for (col, c_rs) in possible_rules_in_position.iter_mut().enumerate() {
+        // each position takes exactly one rule and each rule exactly one position
+        let n = rules.len();
+        let mut cover = ExactCover::new(2 * n, 0);
+        let mut pairs = vec![];
+        for col in 0..n {
             for (r_i, r) in rules.iter().enumerate() {
-                if !valid_tickets.iter().all(|t| r.valid(t[col])) {
-                    *c_rs &= !(1 << r_i);
-                }
-            }
-        }
-
-        loop {
-            for col in 0..possible_rules_in_position.len() {
-                let p_rs = possible_rules_in_position[col];
-                if p_rs.count_ones() == 1 {
-                    // remove this from all others
-                    for r in possible_rules_in_position.iter_mut() {
-                        if p_rs == *r {
-                            continue;
-                        }
-                        *r &= !p_rs;
-                    }
+                if valid_tickets.iter().all(|t| r.valid(t[col])) {
+                    cover.add_row(&[col, n + r_i]);
+                    pairs.push((col, r));
                 }
             }
-
-            if possible_rules_in_position
-                .iter()
-                .all(|rs| rs.count_ones() == 1)
-            {
-                break;
-            }
         }
 
-        possible_rules_in_position
-            .iter()
-            .enumerate()
-            .map(|(i, r)| (i, &rules[r.trailing_zeros() as usize]))
+        cover
+            .first_solution()
+            .unwrap()
+            .into_iter()
+            .map(|row| pairs[row])
             .filter(|(_, r)| r.name.starts_with("departure"))
             .map(|(i, _)| mine[i])
             .product()
@@ -93,32 +74,31 @@ impl AocDay for Day16 {
 }
 
 fn parse_rule(s: &str) -> Rule {
-    let (name, cs) = s.split_once(": ").unwrap();
-    let (c1, c2) = cs.split_once(" or ").unwrap();
-    let (c1_min, c1_max) = c1.split_once('-').unwrap();
-    let (c2_min, c2_max) = c2.split_once('-').unwrap();
+    let (name, ranges) = s.split_once(": ").unwrap();
     Rule {
         name: name.to_owned(),
-        clause_1: (c1_min.parse().unwrap(), c1_max.parse().unwrap()),
-        clause_2: (c2_min.parse().unwrap(), c2_max.parse().unwrap()),
+        ranges: ranges.parse().unwrap(),
     }
 }
 
-type Clause = (u64, u64);
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct Rule {
     name: String,
-    clause_1: Clause,
-    clause_2: Clause,
+    ranges: RangeSet<u64>,
 }
 impl Rule {
     fn valid(&self, v: u64) -> bool {
-        (self.clause_1.0..=self.clause_1.1).contains(&v)
-            || (self.clause_2.0..=self.clause_2.1).contains(&v)
+        self.ranges.contains(v)
     }
 }
 
+/// Every value that fits at least one rule.
+fn any_rule(rules: &[Rule]) -> RangeSet<u64> {
+    rules
+        .iter()
+        .fold(RangeSet::new(), |any, r| any.union(&r.ranges))
+}
+
 type Ticket = [u64; 20];
 
 fn parse_ticket(s: &str) -> Ticket {
@@ -1,6 +1,7 @@
 use aoc_common::aoc_day::AocDay;
+use aoc_common::automaton::{Automaton, Growing, LifeRule, Neighbourhood};
 use aoc_common::files::Res;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fs::read_to_string;
 
 pub struct Day17;
@@ -30,54 +31,16 @@ impl AocDay for Day17 {
     }
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
-        let mut ca = input.clone();
-        for _ in 0..6 {
-            ca = run_generation(&ca, false);
-        }
-
-        ca.len()
+        let cubes = input.iter().map(|&(x, y, z, _)| [x, y, z]);
+        let mut ca = Growing::new(cubes, Neighbourhood::moore(), LifeRule::conway());
+        ca.run(6);
+        ca.population()
     }
 
     fn part_2(input: &Self::Input) -> Self::Result2 {
-        let mut ca = input.clone();
-        for _ in 0..6 {
-            ca = run_generation(&ca, true);
-        }
-
-        ca.len()
-    }
-}
-
-fn run_generation(points: &Points, is_4d: bool) -> Points {
-    let mut adjacents: HashMap<Point4d, usize> = HashMap::new();
-
-    for p in points {
-        if points.contains(p) {
-            add_adjacents(&mut adjacents, *p, is_4d);
-        }
-    }
-
-    let mut next = Points::new();
-
-    for (p, v) in adjacents {
-        if v == 3 || (v == 2 && points.contains(&p)) {
-            next.insert(p);
-        }
+        let cubes = input.iter().map(|&(x, y, z, w)| [x, y, z, w]);
+        let mut ca = Growing::new(cubes, Neighbourhood::moore(), LifeRule::conway());
+        ca.run(6);
+        ca.population()
     }
-
-    next
-}
-
-fn add_adjacents(adjacents: &mut HashMap<Point4d, usize>, p: Point4d, is_4d: bool) {
-    (-1..=1)
-        .flat_map(|x| {
-            (-1..=1).flat_map(move |y| {
-                (-1..=1).flat_map(move |z| {
-                    (if is_4d { -1..=1 } else { 0..=0 })
-                        .map(move |w| (p.0 + x, p.1 + y, p.2 + z, p.3 + w))
-                })
-            })
-        })
-        .filter(|&n| n != p)
-        .for_each(|p| *adjacents.entry(p).or_insert(0) += 1);
 }
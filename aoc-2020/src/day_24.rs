@@ -1,8 +1,8 @@
 use aoc_common::aoc_day::AocDay;
+use aoc_common::automaton::{Automaton, LifeRule, Neighbourhood, Sparse};
 use aoc_common::files::Res;
 use itertools::Itertools;
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
 
 pub struct Day24;
@@ -24,30 +24,14 @@ impl AocDay for Day24 {
         get_starting_tiles(input).len()
     }
 
+    /// Black tiles with one or two black neighbours stay black, white ones with two turn black.
     fn part_2(input: &Self::Input) -> Self::Result2 {
-        const NEIGHBOURS: [Point; 6] = [(0, 1), (-1, 1), (1, -1), (0, -1), (1, 0), (-1, 0)];
-        let mut populated_tiles: HashSet<Point> = get_starting_tiles(input).into_iter().collect();
-        let mut neighbours: HashMap<Point, usize> = HashMap::new();
-
-        for _generation in 0..100 {
-            for (n, ne) in populated_tiles.iter() {
-                for neighbour in NEIGHBOURS.iter().map(|(n2, ne2)| (n + n2, ne + ne2)) {
-                    *neighbours.entry(neighbour).or_insert(0) += 1;
-                }
-            }
-
-            populated_tiles = neighbours
-                .drain()
-                .filter(|(k, v)| match (populated_tiles.contains(k), v) {
-                    (true, &x) if x == 1 || x == 2 => true,
-                    (false, 2) => true,
-                    _ => false,
-                })
-                .map(|(k, _)| k)
-                .collect();
-        }
-
-        populated_tiles.len()
+        let tiles = get_starting_tiles(input)
+            .into_iter()
+            .map(|(e, ne)| [e as i64, ne as i64]);
+        let mut floor = Sparse::new(tiles, Neighbourhood::hex(), LifeRule::new(&[2], &[1, 2]));
+        floor.run(100);
+        floor.population()
     }
 }
 
@@ -1,5 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
+use aoc_common::numtheory::{discrete_log, powmod};
 use std::fs::read_to_string;
 
 pub struct Day25;
@@ -28,23 +29,14 @@ impl AocDay for Day25 {
     }
 }
 
+const MODULUS: u64 = 20201227;
+
 fn inverse_transform(subject: u64, public_key: u64) -> u64 {
-    let mut value = 1;
-    let mut loop_size = 0;
-    while value != public_key {
-        value = value * subject % 20201227;
-        loop_size += 1;
-    }
-    loop_size
+    discrete_log(subject, public_key, MODULUS).unwrap()
 }
 
-fn transform(subject: u64, mut loop_size: u64) -> u64 {
-    let mut value = 1;
-    while loop_size > 0 {
-        value = value * subject % 20201227;
-        loop_size -= 1;
-    }
-    value
+fn transform(subject: u64, loop_size: u64) -> u64 {
+    powmod(subject, loop_size, MODULUS)
 }
 
 #[cfg(test)]
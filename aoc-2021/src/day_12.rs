@@ -1,11 +1,13 @@
 use aoc_common::aoc_day::AocDay;
+use aoc_common::bitset::BitVec;
 use aoc_common::files::Res;
+use aoc_common::graph::{Graph, NodeId};
 use std::fs::read_to_string;
 
 pub struct Day12;
 
 impl AocDay for Day12 {
-    type Input = Vec<(String, String)>;
+    type Input = Graph;
     type Result1 = usize;
     type Result2 = usize;
 
@@ -19,62 +21,65 @@ impl AocDay for Day12 {
             .collect())
     }
 
-    fn part_1(input: &Self::Input) -> Self::Result1 {
-        find_all_routes(input, &mut vec!["start".to_owned()], true)
+    fn part_1(caves: &Self::Input) -> Self::Result1 {
+        Caves::new(caves).routes_from(caves.id("start").unwrap(), &mut BitVec::new(), true)
     }
 
-    fn part_2(input: &Self::Input) -> Self::Result2 {
-        find_all_routes(input, &mut vec!["start".to_owned()], false)
+    fn part_2(caves: &Self::Input) -> Self::Result2 {
+        Caves::new(caves).routes_from(caves.id("start").unwrap(), &mut BitVec::new(), false)
     }
 }
 
-fn parse(line: &str) -> Vec<(String, String)> {
+fn parse(line: &str) -> Vec<(&str, &str)> {
     let (left, right) = line.split_once("-").unwrap();
-    vec![
-        (left.to_owned(), right.to_owned()),
-        (right.to_owned(), left.to_owned()),
-    ]
+    vec![(left, right), (right, left)]
 }
 
-fn find_all_routes(
-    paths: &[(String, String)],
-    from: &mut Vec<String>,
-    visited_small: bool,
-) -> usize {
-    if *from.last().unwrap() == "end" {
-        1
-    } else {
-        paths
-            .iter()
-            .filter_map(|(start, end)| {
-                if start != from.last().unwrap() || "start" == end {
-                    None
+struct Caves<'a> {
+    graph: &'a Graph,
+    big: Vec<bool>,
+    start: NodeId,
+    end: NodeId,
+}
+
+impl<'a> Caves<'a> {
+    fn new(graph: &'a Graph) -> Self {
+        Caves {
+            graph,
+            big: graph.nodes().map(|n| is_big(graph.label(n))).collect(),
+            start: graph.id("start").unwrap(),
+            end: graph.id("end").unwrap(),
+        }
+    }
+
+    /// Count the routes to the end, with `visited` the small caves already seen.
+    fn routes_from(&self, from: NodeId, visited: &mut BitVec, visited_small: bool) -> usize {
+        if from == self.end {
+            return 1;
+        }
+        // on a second visit the cave is already there, and stays for the first one
+        let added = !self.big[from] && visited.insert(from);
+
+        let routes = self
+            .graph
+            .successors(from)
+            .filter(|&next| next != self.start)
+            .map(|next| {
+                let unseen = self.big[next] || !visited.contains(next);
+                if unseen {
+                    self.routes_from(next, visited, visited_small)
+                } else if !visited_small {
+                    self.routes_from(next, visited, true)
                 } else {
-                    let big = is_big(end);
-                    let unseen = big || unseen(end, from);
-
-                    if big || unseen || !visited_small {
-                        // I could replace all strings with small structs e.g. `(label, big)`.
-                        // This would prevent these clones.
-                        // Or I could try to RC, not done that in 12 months!
-                        from.push(end.clone());
-                        let sum = find_all_routes(
-                            paths,
-                            from,
-                            if big {
-                                visited_small
-                            } else {
-                                !unseen || visited_small
-                            },
-                        );
-                        from.pop();
-                        Some(sum)
-                    } else {
-                        None
-                    }
+                    0
                 }
             })
-            .sum()
+            .sum();
+
+        if added {
+            visited.remove(from);
+        }
+        routes
     }
 }
 
@@ -82,6 +87,30 @@ fn is_big(s: &str) -> bool {
     s.chars().all(|c| c.is_uppercase())
 }
 
-fn unseen(s: &str, path: &[String]) -> bool {
-    path.iter().all(|e| e != s)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+start-A
+start-b
+A-c
+A-b
+b-d
+A-end
+b-end";
+
+    fn example() -> Graph {
+        EXAMPLE.lines().flat_map(parse).collect()
+    }
+
+    #[test]
+    fn part_1_test() {
+        assert_eq!(Day12::part_1(&example()), 10);
+    }
+
+    #[test]
+    fn part_2_test() {
+        assert_eq!(Day12::part_2(&example()), 36);
+    }
 }
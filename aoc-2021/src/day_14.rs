@@ -1,5 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
+use aoc_common::memo::Memo;
 use std::collections::HashMap;
 use std::fs::read_to_string;
 
@@ -15,21 +16,7 @@ impl AocDay for Day14 {
     }
     fn load() -> Res<Self::Input> {
         let input_str = read_to_string("data/2021/day_14.in")?;
-
-        Ok((
-            input_str.lines().next().unwrap().chars().collect(),
-            input_str
-                .lines()
-                .skip(2)
-                .map(|l| {
-                    let (left, right) = l.split_once(" -> ").unwrap();
-                    (
-                        (left.chars().next().unwrap(), left.chars().nth(1).unwrap()),
-                        right.chars().next().unwrap(),
-                    )
-                })
-                .collect(),
-        ))
+        Ok(parse(&input_str))
     }
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
@@ -41,35 +28,99 @@ impl AocDay for Day14 {
     }
 }
 
-fn do_it((input, rules): &<Day14 as AocDay>::Input, iterations: usize) -> i64 {
-    let paired: Vec<(char, char)> = input.windows(2).map(|arr| (arr[0], arr[1])).collect();
+fn parse(input_str: &str) -> <Day14 as AocDay>::Input {
+    (
+        input_str.lines().next().unwrap().chars().collect(),
+        input_str
+            .lines()
+            .skip(2)
+            .map(|l| {
+                let (left, right) = l.split_once(" -> ").unwrap();
+                (
+                    (left.chars().next().unwrap(), left.chars().nth(1).unwrap()),
+                    right.chars().next().unwrap(),
+                )
+            })
+            .collect(),
+    )
+}
 
-    let mut pairs: HashMap<(char, char), i64> = HashMap::new();
-    for c in paired.iter() {
-        *pairs.entry(*c).or_insert(0) += 1;
+fn do_it((input, rules): &<Day14 as AocDay>::Input, iterations: usize) -> i64 {
+    let mut memo = Memo::new();
+    let mut counts = [0; 26];
+    for c in input.iter() {
+        counts[letter(*c)] += 1;
     }
-
-    let mut next_pairs: HashMap<(char, char), i64> = HashMap::new();
-    for _ in 0..iterations {
-        next_pairs.clear();
-        for (p, count) in pairs.drain() {
-            let nc = rules[&p];
-            let np1 = (p.0, nc);
-            let np2 = (nc, p.1);
-            *next_pairs.entry(np1).or_insert(0) += count;
-            *next_pairs.entry(np2).or_insert(0) += count;
+    for pair in input.windows(2) {
+        let inserted = insertions(rules, (pair[0], pair[1]), iterations, &mut memo);
+        for (count, i) in counts.iter_mut().zip(inserted.iter()) {
+            *count += i;
         }
-        // swap to avoid alloc
-        let t = pairs;
-        pairs = next_pairs;
-        next_pairs = t;
     }
 
-    let mut freq_map: HashMap<char, i64> = HashMap::new();
-    for ((l, _), c) in pairs.iter() {
-        *freq_map.entry(*l).or_insert(0) += c;
+    let present = counts.iter().filter(|&&c| c > 0);
+    present.clone().max().unwrap() - present.min().unwrap()
+}
+
+fn letter(c: char) -> usize {
+    (c as u8 - b'A') as usize
+}
+
+/// How many of each letter end up between the two of a pair after some steps.
+fn insertions(
+    rules: &HashMap<(char, char), char>,
+    pair: (char, char),
+    steps: usize,
+    memo: &mut Memo<((char, char), usize), [i64; 26]>,
+) -> [i64; 26] {
+    memo.get_or_insert_with(&(pair, steps), |memo| {
+        let mut counts = [0; 26];
+        if steps == 0 {
+            return counts;
+        }
+        let middle = rules[&pair];
+        counts[letter(middle)] += 1;
+        for side in [(pair.0, middle), (middle, pair.1)].iter() {
+            let inner = insertions(rules, *side, steps - 1, memo);
+            for (count, i) in counts.iter_mut().zip(inner.iter()) {
+                *count += i;
+            }
+        }
+        counts
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+NNCB
+
+CH -> B
+HH -> N
+CB -> H
+NH -> C
+HB -> C
+HC -> B
+HN -> C
+NN -> C
+BH -> H
+NC -> B
+NB -> B
+BN -> B
+BB -> N
+BC -> B
+CC -> N
+CN -> C";
+
+    #[test]
+    fn part_1_test() {
+        assert_eq!(Day14::part_1(&parse(EXAMPLE)), 1588);
     }
-    *freq_map.entry(*input.last().unwrap()).or_insert(0) += 1;
 
-    freq_map.values().max().unwrap() - freq_map.values().min().unwrap()
+    #[test]
+    fn part_2_test() {
+        assert_eq!(Day14::part_2(&parse(EXAMPLE)), 2188189693529);
+    }
 }
@@ -1,7 +1,7 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
 use aoc_common::geometry::Direction;
-use aoc_common::search::{search, HeapElem};
+use aoc_common::search::astar;
 use std::fs::read_to_string;
 
 pub struct Day15;
@@ -25,26 +25,25 @@ impl AocDay for Day15 {
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
         let end = (input[0].len() as i64 - 1, input.len() as i64 - 1);
-        search(
-            (0i64, 0i64),
-            |p| *p == end,
-            |p, distance| {
+        astar(
+            vec![(0i64, 0i64)],
+            |&p| {
                 Direction::array().iter().filter_map(move |direction| {
                     let successor = direction.next_point(p);
                     match successor {
                         (x, y) if x < 0 || y < 0 || x > end.0 || y > end.1 => None,
-                        successor => Some(HeapElem {
-                            elem: successor,
-                            distance: distance
-                                + input[successor.1 as usize][successor.0 as usize] as u64,
-                            heuristic: ((end.0 - successor.0).abs() + (end.1 - successor.1).abs())
-                                as u64,
-                        }),
+                        successor => Some((
+                            successor,
+                            input[successor.1 as usize][successor.0 as usize] as u64,
+                        )),
                     }
                 })
             },
+            |p| ((end.0 - p.0).abs() + (end.1 - p.1).abs()) as u64,
+            |p| *p == end,
         )
-        .1 as i64
+        .unwrap()
+        .cost as i64
     }
 
     fn part_2(input: &Self::Input) -> Self::Result2 {
@@ -53,10 +52,9 @@ impl AocDay for Day15 {
         let width = w_mod * 5;
         let height = h_mod * 5;
         let end = (width - 1, height - 1);
-        search(
-            (0i64, 0i64),
-            |p| *p == end,
-            |p, distance| {
+        astar(
+            vec![(0i64, 0i64)],
+            |&p| {
                 Direction::array().iter().filter_map(move |direction| {
                     let successor = direction.next_point(p);
                     match successor {
@@ -69,18 +67,15 @@ impl AocDay for Day15 {
                             let n_v = o_v + w_wrap + h_wrap;
                             // I feel like this shouldn't work for 9+5+5
                             let v = if n_v > 9 { n_v - 9 } else { n_v };
-                            Some(HeapElem {
-                                elem: successor,
-                                distance: distance + v as u64,
-                                heuristic: ((end.0 - successor.0).abs()
-                                    + (end.1 - successor.1).abs())
-                                    as u64,
-                            })
+                            Some((successor, v as u64))
                         }
                     }
                 })
             },
+            |p| ((end.0 - p.0).abs() + (end.1 - p.1).abs()) as u64,
+            |p| *p == end,
         )
-        .1 as i64
+        .unwrap()
+        .cost as i64
     }
 }
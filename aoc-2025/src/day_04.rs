@@ -1,6 +1,7 @@
-use aoc_common::files::Res;
 use aoc_common::aoc_day::AocDay;
-use std::{fs::read_to_string, mem::swap};
+use aoc_common::automaton::{Automaton, Dense, Neighbourhood};
+use aoc_common::files::Res;
+use std::fs::read_to_string;
 
 pub struct Day04;
 
@@ -22,108 +23,33 @@ impl AocDay for Day04 {
 
     /// Model the input as cellular automata and run it once.
     fn part_1((input, width, height): &Self::Input) -> Self::Result1 {
-        let mut new = input.clone();
-
-        run_step_vec(input, &mut new, *width, *height, 4, false);
-        new.iter().filter(|&c| *c == 'x').count()
+        let mut rolls = rolls(input, *width, *height);
+        rolls.step();
+        rolls.cells().iter().filter(|&c| *c == 'x').count()
     }
 
     /// Model the input as cellular automata and run it until it is stable and count the number of seats.
     fn part_2((input, width, height): &Self::Input) -> Self::Result2 {
-        // double buffer, to cut down on allocation and freeing
-        let mut ca = input.clone();
-        let mut cb = input.clone();
-        loop {
-            run_step_vec(&ca, &mut cb, *width, *height, 4, false);
-
-            if ca == cb {
-                return cb.iter().filter(|&c| *c == 'x').count();
-            } else {
-                swap(&mut ca, &mut cb);
-            }
-        }
+        let mut rolls = rolls(input, *width, *height);
+        rolls.run_to_fixed_point();
+        rolls.cells().iter().filter(|&c| *c == 'x').count()
     }
 }
 
-fn run_step_vec(
-    old: &[char],
-    new: &mut [char],
+/// Rolls of paper with fewer than four others around them can be taken away, marked `x`.
+fn rolls(
+    input: &[char],
     width: usize,
     height: usize,
-    crowd: usize,
-    sight: bool,
-) {
-    for (i, c) in old.iter().enumerate() {
-        new[i] = match c {
-            '@' => {
-                let occupied_neighbours =
-                    get_occupied_neighbours_vec(old, i, (width as i32, height as i32), sight);
-                if occupied_neighbours < crowd {
-                    'x'
-                } else {
-                    '@'
-                }
-            }
-
-            c => *c,
-        }
-    }
-
-}
-
-/// an empty string means off-grid
-fn get(ca: &[char], column: i32, row: i32, width: i32, height: i32) -> char {
-    if column >= 0 && row >= 0 && column < width && row < height {
-        ca[(column + width * row) as usize]
-    } else {
-        ' '
-    }
-}
-
-type Point = (i32, i32);
-
-/// get the first non-empty cell value along the vector (checking the point first)
-fn get_vec(
-    grid: &[char],
-    (column, row): Point,
-    (c_vec, r_vec): Point,
-    (width, height): Point,
-    sight: bool,
-) -> char {
-    let cn = column + c_vec;
-    let rn = row + r_vec;
-    let hit = get(grid, cn, rn, width, height);
-    if hit == '.' && sight {
-        get_vec(grid, (cn, rn), (c_vec, r_vec), (width, height), sight)
-    } else {
-        hit
-    }
-}
-
-fn get_occupied_neighbours_vec(
-    grid: &[char],
-    i: usize,
-    (width, height): Point,
-    sight: bool,
-) -> usize {
-    const NEIGHBOURS: [Point; 8] = [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-    let i = i as i32;
-    let row = i / width;
-    let col = i - (width * row);
-
-    NEIGHBOURS
-        .iter()
-        .filter(|(r, c)| '@' == get_vec(grid, (col, row), (*c, *r), (width, height), sight))
-        .count()
+) -> Dense<char, impl Fn(&char) -> bool + Sync, impl Fn(&char, usize) -> char + Sync> {
+    Dense::new(
+        width,
+        height,
+        input.to_vec(),
+        &Neighbourhood::moore(),
+        |&c| c == '@',
+        |&c, rolls| if c == '@' && rolls < 4 { 'x' } else { c },
+    )
 }
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
+use aoc_common::viz::Recorder;
 use std::fs::read_to_string;
 use std::mem::swap;
 
@@ -21,8 +22,10 @@ impl AocDay for Day07 {
     fn part_1(input: &Self::Input) -> Self::Result1 {
         let mut splits = 0;
         let mut lines = input.clone();
+        let mut recorder = Recorder::new("2025_day_07").with_frame_time(0.05);
 
         for i in 1..lines.len() {
+            recorder.frame(|| draw(&lines));
             let (above, below) = lines.split_at_mut(i);
             let line_above = &above[i - 1];
             let line = &mut below[0];
@@ -44,6 +47,7 @@ impl AocDay for Day07 {
                 }
             }
         }
+        recorder.frame(|| draw(&lines));
         splits
     }
 
@@ -84,6 +88,14 @@ impl AocDay for Day07 {
     }
 }
 
+fn draw(lines: &[Vec<u8>]) -> String {
+    lines
+        .iter()
+        .map(|l| String::from_utf8_lossy(l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn parse(input: &str) -> <Day07 as AocDay>::Input {
     input.lines().map(|line| line.bytes().collect()).collect()
 }
@@ -1,6 +1,6 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
-use itertools::Itertools;
+use aoc_common::polygon::Polygon;
 use std::fs::read_to_string;
 
 pub struct Day09;
@@ -34,78 +34,22 @@ impl AocDay for Day09 {
             .unwrap() as i64
     }
 
+    /// The largest rectangle between two red tiles that only covers red and green tiles,
+    /// those on or inside the loop through the red ones.
     fn part_2(input: &Self::Input) -> Self::Result2 {
-        let mut lines = input.clone();
-        lines.push(input[0]);
-        let mut lines = lines.into_iter().tuple_windows().collect::<Vec<((i64, i64),(i64, i64))>>();
-        // longer lines are more likely to intersect
-        lines.sort_unstable_by_key(|&((x1, y1), (x2, y2))| {
-            -((x1.abs_diff(x2) + y1.abs_diff(y2)) as i64)
-        });
-
+        let tiles = Polygon::new(input.clone()).compress();
+        let cells: Vec<_> = input.iter().map(|&p| tiles.cell(p).unwrap()).collect();
         let mut max = 0;
-
-        input
-            .iter()
-            .enumerate()
-            .for_each(|(i, &(x1, y1))| {
-                input
-                    .iter()
-                    .skip(i + 1)
-                    .for_each(|&(x2, y2)| {
-                        let size = (x1.abs_diff(x2) + 1) * (y1.abs_diff(y2) + 1);
-                        if size <= max {
-                            return;
-                        }
-
-                        let (min_x, max_x) = if x1 < x2 { (x1, x2) } else { (x2, x1) };
-                        let (min_y, max_y) = if y1 < y2 { (y1, y2) } else { (y2, y1) };
-
-
-                        if lines
-                            .iter()
-                            .any(|&((lx1, ly1), (lx2, ly2))| {
-                               intersects((min_x, min_y), (max_x, max_y), ((lx1, ly1), (lx2, ly2)))
-                            })
-                        {
-                            // if there's any intersecting line then this is invalid
-                        } else {
-                            max = size;
-                        }
-                    })
-            });
-        max as i64
-    }
-}
-
-fn intersects((x_min, y_min): (i64, i64), (x_max, y_max): (i64, i64), line: ((i64, i64),(i64, i64))) -> bool {
-    let ((lx1, ly1), (lx2, ly2)) = line;
-    let (lmin_x, lmax_x) = if lx1 < lx2 { (lx1, lx2) } else { (lx2, lx1) };
-    let (lmin_y, lmax_y) = if ly1 < ly2 { (ly1, ly2) } else { (ly2, ly1) };
-
-
-    if lmin_x == lmax_x {
-        // vertical line within x bound
-        if lmin_x > x_min && lmin_x < x_max {
-            // crosses top line
-            if (lmin_y <= y_min && lmax_y > y_min)
-                // crosses bottom line
-                || (lmin_y < y_max && lmax_y >= y_max) {
-                return true;
-            }
-        }
-    } else if lmin_y == lmax_y {
-        // horizontal line within y bounds
-        if lmin_y > y_min && lmin_y < y_max {
-            // crosses left line
-            if (lmin_x <= x_min && lmax_x > x_min)
-                // crosses right line
-                || (lmin_x < x_max && lmax_x >= x_max) {
-                return true;
+        for (i, &(x1, y1)) in input.iter().enumerate() {
+            for (j, &(x2, y2)) in input.iter().enumerate().skip(i + 1) {
+                let size = (x1.abs_diff(x2) + 1) * (y1.abs_diff(y2) + 1);
+                if size > max && tiles.contains_cells(cells[i], cells[j]) {
+                    max = size;
+                }
             }
         }
+        max as i64
     }
-    false
 }
 
 fn parse(input: &str) -> Res<Vec<(i64, i64)>> {
@@ -158,11 +102,22 @@ mod tests {
     }
 
     #[test]
-    fn test_intersects() {
-        assert!(intersects((3, 3), (6, 6), ((1, 4), (7, 4))));
-        assert!(intersects((3, 3), (6, 6), ((1, 4), (5, 4))));
-        assert!(!intersects((3, 3), (6, 6), ((1, 2), (7, 2))));
-        assert!(!intersects((2, 3), (9, 7), ((9, 7), (9, 5))));
-        assert!(intersects((2, 3), (9, 7), ((9, 5), (2, 5))));
+    fn rectangles_inside() {
+        let input = parse(
+            r"7,1
+11,1
+11,7
+9,7
+9,5
+2,5
+2,3
+7,3",
+        )
+        .unwrap();
+        let tiles = Polygon::new(input).compress();
+        assert!(tiles.contains_tiles((9, 5), (2, 3)));
+        assert!(tiles.contains_tiles((9, 7), (11, 1)));
+        assert!(!tiles.contains_tiles((2, 3), (9, 7)));
+        assert!(!tiles.contains_tiles((7, 1), (11, 7)));
     }
 }
@@ -1,6 +1,7 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::bitset::Bitset;
 use aoc_common::files::Res;
+use aoc_common::linalg::minimise_integer;
 use std::collections::VecDeque;
 use std::fs::read_to_string;
 use tinyvec::ArrayVec;
@@ -40,7 +41,7 @@ impl AocDay for Day10 {
 
                 while let Some((current, d)) = queue.pop_front() {
                     for &switch in switches {
-                        let switched = current.disjoint(switch);
+                        let switched = current.symmetric_difference(switch);
                         if switched == *goal {
                             return d as i64 + 1;
                         }
@@ -55,164 +56,46 @@ impl AocDay for Day10 {
             .sum()
     }
 
+    /// Each counter gives an equation over how many times each button is pressed, so this is
+    /// the smallest non-negative integer solution of `A x = joltage`.
+    /// A button can't be pressed more times than the smallest counter it feeds.
     fn part_2(input: &Self::Input) -> Self::Result2 {
-        return -1;
-        // possible optimisations
-        // - are there any indices that are only ever in a single switch?
-        //   - if so, we can just apply that switch the required number of times
-        // - are there any single index switches?
-        //   - if so, we can solve for the other numbers, and add that later
-        // - order joltage targets by number of switches affecting them asc?
-        //   - order of switching doesn't mater, only the number of times each switch was used
-        // - is there an index that is set everytime another index is?
-        //   - if so, we can ... what?
-        // - we can remove all rules that build the smallest joltage
-
-        let mut sum = 0;
-        for machine in input {
-            println!("New machine");
-            let (_, _, switches_vec, joltage) = machine;
-            let mut switches_vec = switches_vec.clone();
-            let mut start: TV64 = joltage.iter().map(|_x| 0).collect();
-            let mut preopt_distance = 0;
-
-            println!("  joltage width: {:?}", joltage.len());
-
-            preopt_distance += remove_least_common_switches(&mut switches_vec, joltage, &mut start);
-
-            let most_common = joltage
-                .iter()
-                .enumerate()
-                .map(|(i, &_j)| {
-                    (
-                        i,
+        input
+            .iter()
+            .map(|(_, _, switches_vec, joltage)| {
+                let a: Vec<Vec<i64>> = (0..joltage.len())
+                    .map(|i| {
                         switches_vec
                             .iter()
-                            .filter(|switch_vec| switch_vec.contains(&i))
-                            .count(),
-                    )
-                })
-                .max_by_key(|&(_i, c)| c)
-                .unwrap();
-
-            println!(
-                "  most common index: {}, count: {}, joltage: {}",
-                most_common.0, most_common.1, joltage[most_common.0]
-            );
-
-            let single_switch = switches_vec
-                .iter()
-                .filter(|switch_vec| switch_vec.len() == 1)
-                .map(|switch_vec| switch_vec[0])
-                .collect::<Vec<usize>>();
-            println!("  single index switches: {:?}", single_switch);
-
-            println!("  starting search: {:?}", start);
-
-            let res = (0, 0);
-            // let res = search::search(
-            //     start,
-            //     |state| state == joltage,
-            //     |state, distance| {
-            //         switches_vec.iter().filter_map(move |switch_vec| {
-            //             let mut new_state = state.clone();
-            //             for &switch in switch_vec {
-            //                 new_state[switch] += 1;
-            //             }
-            //             if joltage.iter().zip(new_state.iter()).any(|(j, s)| s > j) {
-            //                 return None;
-            //             }
-            //             let heuristic = 0;
-            //             let heuristic = joltage
-            //                 .iter()
-            //                 .zip(new_state.iter())
-            //                 .map(|(j, s)| j.abs_diff(*s))
-            //                 .max()
-            //                 .unwrap();
-            //             Some(search::HeapElem {
-            //                 elem: new_state,
-            //                 distance: distance + 1,
-            //                 heuristic: heuristic as u64,
-            //             })
-            //         })
-            //     },
-            // );
-            sum += res.1 as i64 + preopt_distance;
-        }
-        sum
-    }
-}
-
-fn remove_least_common_switches(
-    switches_vec: &mut Vec<TinyVec>,
-    joltage: &TV64,
-    start: &mut TV64,
-) -> i64 {
-    let mut total_distance = 0;
-    let mut removed_indices: TinyVec = tinyvec::array_vec!([usize; 10]);
-
-    loop {
-        let least_common = joltage
-            .iter()
-            .enumerate()
-            .map(|(i, &_j)| {
-                (
-                    i,
-                    switches_vec
-                        .iter()
-                        .filter(|switch_vec| switch_vec.contains(&i))
-                        .count(),
-                )
+                            .map(|switch| switch.contains(&i) as i64)
+                            .collect()
+                    })
+                    .collect();
+                let upper: Vec<i64> = switches_vec
+                    .iter()
+                    .map(|switch| switch.iter().map(|&i| joltage[i]).min().unwrap_or(0))
+                    .collect();
+                let cost = vec![1; switches_vec.len()];
+
+                minimise_integer(&a, joltage, &upper, &cost).unwrap().0
             })
-            .filter(|(i, _)| !removed_indices.contains(i))
-            .min_by_key(|&(_i, c)| c)
-            .unwrap();
-
-        // if least_common.1 > 1 {
-        //     break;
-        // }
-
-        println!(
-            "  least common index: {}, count: {}, joltage: {}",
-            least_common.0, least_common.1, joltage[least_common.0]
-        );
-
-        if least_common.1 > 1 {
-            break;
-        }
-
-        let s = switches_vec
-            .iter()
-            .find(|switch_vec| switch_vec.contains(&least_common.0))
-            .unwrap();
-
-        let j = joltage[least_common.0];
-        for i in s.iter() {
-            start[*i] += j;
-        }
-        total_distance += j;
-
-        println!("    removing switch {:?}, increasing distance by {}", s, j);
-
-        removed_indices.push(least_common.0);
-        switches_vec.retain(|switch_vec| !switch_vec.contains(&least_common.0));
+            .sum()
     }
-
-    total_distance
 }
 
 fn parse(s: &str) -> Vec<(Bitset, Vec<Bitset>, Vec<TinyVec>, TV64)> {
     s.split('\n')
         .map(|l| {
             let mut it = l.split(' ');
-            let mut goal: Bitset = Bitset::empty();
-            let goals = it.next().unwrap();
-            goals.bytes().skip(1).enumerate().for_each(|(i, b)| {
-                goal = match b {
-                    b'#' => goal.set(i),
-                    _ => goal,
-                };
-            });
+            let goal: Bitset = it
+                .next()
+                .unwrap()
+                .bytes()
+                .skip(1)
+                .enumerate()
+                .filter(|(_, b)| *b == b'#')
+                .map(|(i, _)| i)
+                .collect();
 
             let switches_bs: Vec<Bitset> = it
                 .filter_map(|xs| {
@@ -224,7 +107,7 @@ fn parse(s: &str) -> Vec<(Bitset, Vec<Bitset>, Vec<TinyVec>, TV64)> {
                         .trim_suffix(')')
                         .split(',')
                         .map(|x| x.parse::<usize>().unwrap())
-                        .fold(Bitset::empty(), |acc, x| acc.set(x));
+                        .collect();
                     Some(bs)
                 })
                 .collect();
@@ -318,6 +201,6 @@ mod tests {
 [.###.#] (0,1,2,3,4) (0,3,4) (0,1,2,4,5) (1,2) {10,11,11,5,10,5}",
         );
         let res = Day10::part_2(&input);
-        assert_eq!(res, 5); //33);
+        assert_eq!(res, 33);
     }
 }
@@ -1,13 +1,12 @@
 use aoc_common::aoc_day::AocDay;
 use aoc_common::files::Res;
-use hashbrown::{HashMap, HashSet};
-use std::collections::VecDeque;
+use aoc_common::graph::{Graph, NodeId};
 use std::fs::read_to_string;
 
 pub struct Day11;
 
 impl AocDay for Day11 {
-    type Input = Vec<(String, Vec<String>)>;
+    type Input = Graph;
     type Result1 = i64;
     type Result2 = i64;
 
@@ -19,121 +18,29 @@ impl AocDay for Day11 {
         Ok(parse(&read_to_string("data/2025/day_11.in")?))
     }
 
-    fn part_1(input: &Self::Input) -> Self::Result1 {
-        let reversed_graph = reverse_graph(input);
-        paths_from(input, &reversed_graph, "you", "out")
+    fn part_1(graph: &Self::Input) -> Self::Result1 {
+        let [you, out] = ids(graph, ["you", "out"]);
+        graph.count_paths(you, out).unwrap() as i64
     }
 
-    fn part_2(input: &Self::Input) -> Self::Result2 {
-        let route = ["svr", "fft", "dac", "out"];
-
-        let reversed_graph = reverse_graph(input);
+    fn part_2(graph: &Self::Input) -> Self::Result2 {
+        let [svr, fft, dac, out] = ids(graph, ["svr", "fft", "dac", "out"]);
 
         // 349322478796032
-        paths_from(input, &reversed_graph, route[0], route[1])
-            * paths_from(input, &reversed_graph, route[1], route[2])
-            * paths_from(input, &reversed_graph, route[2], route[3])
+        graph.count_paths_via(svr, out, &[fft, dac]).unwrap() as i64
     }
 }
 
-fn reverse_graph<'a>(input: &'a Vec<(String, Vec<String>)>) -> HashMap<&'a str, Vec<&'a str>> {
-    let mut rev_graph: HashMap<&str, Vec<&str>> = HashMap::with_capacity(input.len() * 2);
-
-    for (node, children) in input.iter() {
-        for child in children.iter() {
-            rev_graph
-                .entry(child.as_str())
-                .or_default()
-                .push(node.as_str());
-        }
-    }
-
-    rev_graph
-}
-
-fn paths_from<'a>(
-    input: &Vec<(String, Vec<String>)>,
-    reversed_graph: &HashMap<&'a str, Vec<&'a str>>,
-    start: &str,
-    goal: &str,
-) -> i64 {
-    let mut costs: HashMap<&str, i64> = HashMap::with_capacity(input.len() * 2);
-
-    costs.insert(start, 1);
-
-    let mut iterations = 0;
-
-    let mut nodeset = HashSet::with_capacity(reversed_graph.len());
-    for (key, vs) in reversed_graph.iter() {
-        nodeset.insert(*key);
-        for v in vs.iter() {
-            nodeset.insert(*v);
-        }
-    }
-
-    let mut queue: VecDeque<&str> = nodeset.clone().into_iter().collect();
-
-    queue.push_front(goal);
-
-    while (costs.get(goal).is_none()) && !queue.is_empty() {
-        iterations += 1;
-        if iterations == 1_000_000 {
-            println!("Queue: {:?}", queue);
-            println!("Costs so far: {:?}", costs);
-            unreachable!("Stuck in loop computing costs");
-        }
-        let node = queue.pop_front().unwrap();
-        if costs.get(node).is_some() {
-            continue;
-        }
-        let parents = match reversed_graph.get(node) {
-            Some(p) => p,
-            None => {
-                // unreachable node, so 0 cost
-                costs.insert(node, 0);
-                continue;
-            }
-        };
-        let mut all_parents_known = true;
-        let mut total_cost: i64 = 0;
-
-        if parents.is_empty() {
-            costs.insert(node, 0);
-            continue;
-        }
-
-        for parent in parents.iter() {
-            match costs.get(parent) {
-                Some(c) => total_cost += c,
-                None => {
-                    all_parents_known = false;
-                    break;
-                }
-            }
-        }
-
-        if all_parents_known {
-            costs.insert(node, total_cost);
-        } else {
-            queue.push_back(node);
-        }
-    }
-    costs.get(goal).cloned().unwrap()
+fn ids<const N: usize>(graph: &Graph, labels: [&str; N]) -> [NodeId; N] {
+    labels.map(|l| graph.id(l).unwrap())
 }
 
 fn parse(input: &str) -> <Day11 as AocDay>::Input {
     input
         .lines()
-        .map(|line| {
-            let mut parts = line.split(": ");
-            let key = parts.next().unwrap().to_string();
-            let values = parts
-                .next()
-                .unwrap()
-                .split_whitespace()
-                .map(|s| s.to_string())
-                .collect();
-            (key, values)
+        .flat_map(|line| {
+            let (key, values) = line.split_once(": ").unwrap();
+            values.split_whitespace().map(move |v| (key, v))
         })
         .collect()
 }
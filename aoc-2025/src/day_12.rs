@@ -1,4 +1,4 @@
-use aoc_common::aoc_day::AocDay;
+use aoc_common::aoc_day::{AocDay, NoPuzzle};
 use aoc_common::files::Res;
 use std::fs::read_to_string;
 use tinyvec::ArrayVec;
@@ -13,7 +13,7 @@ type Map = TV;
 impl AocDay for Day12 {
     type Input = (Vec<Map>, Vec<((i64, i64), MapCounts)>);
     type Result1 = i64;
-    type Result2 = i64;
+    type Result2 = NoPuzzle;
 
     fn day() -> usize {
         12
@@ -24,27 +24,311 @@ impl AocDay for Day12 {
     }
 
     fn part_1(input: &Self::Input) -> Self::Result1 {
-        // Turns out the problem doesn't need to be solved fully.
-        // Just filter out all the arrangements that are impossible based on total occupied space alone.
         let (maps, regions) = input;
-        let population: ArrayVec<[i64; 6]> = maps
-            .iter()
-            .map(|m| m.iter().filter(|&v| *v).count() as i64)
-            .collect();
+        let shapes: Vec<Vec<Orientation>> = maps.iter().map(orientations).collect();
         regions
             .iter()
-            .filter(|((width, height), map_indices)| {
-                let mut area = width * height;
-                for (idx, &count) in map_indices.iter().enumerate() {
-                    area -= count as i64 * population[idx];
-                }
-                area > 0
+            .filter(|((width, height), counts)| {
+                pack(*width as usize, *height as usize, &shapes, counts).is_some()
             })
             .count() as i64
     }
 
+    /// The last day has no second puzzle.
     fn part_2(_input: &Self::Input) -> Self::Result2 {
-        12
+        NoPuzzle
+    }
+}
+
+/// A present in one orientation, as a mask per row. Columns are relative to the first filled
+/// cell of the top row, so a present is placed by that cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Orientation {
+    /// `(dy, left, mask)`, bit `i` of `mask` being column `left + i`.
+    rows: ArrayVec<[(usize, i64, u64); 3]>,
+    cells: u32,
+}
+
+/// Every distinct rotation and reflection of a present.
+fn orientations(map: &Map) -> Vec<Orientation> {
+    let mut cells: Vec<(i64, i64)> = (0..9)
+        .filter(|&i| map[i])
+        .map(|i| ((i % 3) as i64, (i / 3) as i64))
+        .collect();
+
+    let mut found: Vec<Orientation> = vec![];
+    for _flip in 0..2 {
+        for _rotation in 0..4 {
+            let orientation = to_orientation(&cells);
+            if !found.contains(&orientation) {
+                found.push(orientation);
+            }
+            cells.iter_mut().for_each(|(x, y)| (*x, *y) = (-*y, *x));
+        }
+        cells.iter_mut().for_each(|(x, _)| *x = -*x);
+    }
+    found
+}
+
+fn to_orientation(cells: &[(i64, i64)]) -> Orientation {
+    let top = cells.iter().map(|&(_, y)| y).min().unwrap();
+    let anchor = cells
+        .iter()
+        .filter(|&&(_, y)| y == top)
+        .map(|&(x, _)| x)
+        .min()
+        .unwrap();
+
+    let mut rows = ArrayVec::new();
+    for dy in 0..3 {
+        let row: Vec<i64> = cells
+            .iter()
+            .filter(|&&(_, y)| y == top + dy)
+            .map(|&(x, _)| x - anchor)
+            .collect();
+        if let Some(&left) = row.iter().min() {
+            let mask = row.iter().fold(0, |m, x| m | 1 << (x - left));
+            rows.push((dy as usize, left, mask));
+        }
+    }
+    Orientation {
+        rows,
+        cells: cells.len() as u32,
+    }
+}
+
+/// Where every present went, by the order they were placed.
+#[derive(Debug, PartialEq, Eq)]
+struct Layout {
+    width: usize,
+    cells: Vec<Option<usize>>,
+}
+
+impl Layout {
+    fn transposed(&self, rows: usize) -> Layout {
+        let cells = (0..self.cells.len())
+            .map(|i| self.cells[(i % rows) * self.width + i / rows])
+            .collect();
+        Layout { width: rows, cells }
+    }
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.cells.chunks(self.width) {
+            let line: String = row
+                .iter()
+                .map(|c| match c {
+                    Some(n) => (b'A' + (n % 26) as u8) as char,
+                    None => '.',
+                })
+                .collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fit `counts[i]` of each present into a `width` by `height` region, if it can be done.
+fn pack(
+    width: usize,
+    height: usize,
+    shapes: &[Vec<Orientation>],
+    counts: &[usize],
+) -> Option<Layout> {
+    let needed: u32 = counts
+        .iter()
+        .zip(shapes.iter())
+        .map(|(&c, s)| c as u32 * s[0].cells)
+        .sum();
+    let area = (width * height) as u32;
+    if needed > area {
+        return None;
+    }
+
+    let presents: usize = counts.iter().sum();
+    if (width / 3) * (height / 3) >= presents {
+        // every present gets a 3x3 block to itself
+        return Some(blocks(width, height, shapes, counts));
+    }
+
+    // Every orientation is included, so a transposed region packs the same way. Searching
+    // across the narrow side keeps the rows short and dead ends are found sooner.
+    let transpose = width > height;
+    let (across, down) = if transpose {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let mut packer = Packer {
+        width: across,
+        height: down,
+        shapes,
+        counts: counts.to_vec(),
+        grid: vec![0; across.div_ceil(64) * down],
+        words: across.div_ceil(64),
+        placed: vec![],
+        slack: area - needed,
+    };
+    if !packer.search(0) {
+        return None;
+    }
+    let layout = packer.layout();
+    Some(if transpose {
+        layout.transposed(down)
+    } else {
+        layout
+    })
+}
+
+fn blocks(width: usize, height: usize, shapes: &[Vec<Orientation>], counts: &[usize]) -> Layout {
+    let mut cells = vec![None; width * height];
+    let across = width / 3;
+    let shape_ids = counts
+        .iter()
+        .enumerate()
+        .flat_map(|(s, &c)| std::iter::repeat_n(s, c));
+    for (n, s) in shape_ids.enumerate() {
+        let (x, y) = ((n % across) * 3, (n / across) * 3);
+        let orientation = &shapes[s][0];
+        // shift right so the leftmost column lands in the block
+        let shift = -orientation.rows.iter().map(|r| r.1).min().unwrap();
+        for_each_cell(orientation, |dy, dx| {
+            cells[(y + dy) * width + x + (dx + shift) as usize] = Some(n);
+        });
+    }
+    Layout { width, cells }
+}
+
+fn for_each_cell(orientation: &Orientation, mut f: impl FnMut(usize, i64)) {
+    for &(dy, left, mask) in orientation.rows.iter() {
+        for bit in 0..3 {
+            if mask & (1 << bit) != 0 {
+                f(dy, left + bit);
+            }
+        }
+    }
+}
+
+struct Packer<'a> {
+    width: usize,
+    height: usize,
+    shapes: &'a [Vec<Orientation>],
+    counts: Vec<usize>,
+    /// The filled cells, `words` per row, with column `x` as bit `x % 64` of word `x / 64`.
+    grid: Vec<u64>,
+    words: usize,
+    /// `(shape, orientation, x, y)` of each present so far.
+    placed: Vec<(usize, usize, usize, usize)>,
+    /// How many cells can still be left empty.
+    slack: u32,
+}
+
+impl Packer<'_> {
+    /// Fill the region in reading order from `position`. The first free cell must either be the
+    /// top-left of a present or be left empty, which keeps each layout from being found twice.
+    /// Presents of the same shape are interchangeable, so only their count matters.
+    fn search(&mut self, mut position: usize) -> bool {
+        if self.counts.iter().all(|&c| c == 0) {
+            return true;
+        }
+        while position < self.width * self.height && self.is_filled(position) {
+            position += 1;
+        }
+        if position == self.width * self.height {
+            return false;
+        }
+        let (x, y) = (position % self.width, position / self.width);
+        // nothing left can start this low down
+        let shortest = (0..self.shapes.len())
+            .filter(|&s| self.counts[s] > 0)
+            .flat_map(|s| self.shapes[s].iter().map(|o| o.rows.last().unwrap().0 + 1))
+            .min()
+            .unwrap();
+        if y + shortest > self.height {
+            return false;
+        }
+
+        for s in 0..self.shapes.len() {
+            if self.counts[s] == 0 {
+                continue;
+            }
+            for o in 0..self.shapes[s].len() {
+                if !self.fits(&self.shapes[s][o], x, y) {
+                    continue;
+                }
+                self.toggle(s, o, x, y);
+                self.counts[s] -= 1;
+                self.placed.push((s, o, x, y));
+                if self.search(position + 1) {
+                    return true;
+                }
+                self.placed.pop();
+                self.counts[s] += 1;
+                self.toggle(s, o, x, y);
+            }
+        }
+
+        if self.slack > 0 {
+            self.slack -= 1;
+            if self.search(position + 1) {
+                return true;
+            }
+            self.slack += 1;
+        }
+        false
+    }
+
+    fn is_filled(&self, position: usize) -> bool {
+        let (x, y) = (position % self.width, position / self.width);
+        self.grid[y * self.words + x / 64] & 1 << (x % 64) != 0
+    }
+
+    /// The words of row `y` that a row `mask` starting at column `x` lands in, and its bits in
+    /// each. It spills into the second word when it starts near the end of the first.
+    fn spans(&self, y: usize, x: usize, mask: u64) -> [(usize, u64); 2] {
+        let word = y * self.words + x / 64;
+        let shift = x % 64;
+        let spill = if shift == 0 { 0 } else { mask >> (64 - shift) };
+        [(word, mask << shift), (word + 1, spill)]
+    }
+
+    fn fits(&self, orientation: &Orientation, x: usize, y: usize) -> bool {
+        orientation.rows.iter().all(|&(dy, left, mask)| {
+            let column = x as i64 + left;
+            let right = column + 64 - mask.leading_zeros() as i64;
+            y + dy < self.height
+                && column >= 0
+                && right <= self.width as i64
+                && self
+                    .spans(y + dy, column as usize, mask)
+                    .iter()
+                    .all(|&(word, bits)| bits == 0 || self.grid[word] & bits == 0)
+        })
+    }
+
+    fn toggle(&mut self, s: usize, o: usize, x: usize, y: usize) {
+        for &(dy, left, mask) in self.shapes[s][o].rows.iter() {
+            let column = (x as i64 + left) as usize;
+            for (word, bits) in self.spans(y + dy, column, mask) {
+                if bits != 0 {
+                    self.grid[word] ^= bits;
+                }
+            }
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        let mut cells = vec![None; self.width * self.height];
+        for (n, &(s, o, x, y)) in self.placed.iter().enumerate() {
+            for_each_cell(&self.shapes[s][o], |dy, dx| {
+                cells[(y + dy) * self.width + (x as i64 + dx) as usize] = Some(n);
+            });
+        }
+        Layout {
+            width: self.width,
+            cells,
+        }
     }
 }
 
@@ -134,8 +418,7 @@ mod tests {
 12x5: 1 0 1 0 3 2";
 
     #[test]
-
-    fn part_1() {
+    fn parse_input() {
         let input = parse(INPUT);
         assert_eq!(
             input,
@@ -156,4 +439,79 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn part_1() {
+        let input = parse(INPUT);
+        assert_eq!(Day12::part_1(&input), 2);
+    }
+
+    #[test]
+    fn all_orientations() {
+        let (maps, _) = parse(INPUT);
+        let counts: Vec<usize> = maps.iter().map(|m| orientations(m).len()).collect();
+        // symmetric shapes have fewer, e.g. shape 5 looks the same flipped either way
+        assert_eq!(counts, vec![8, 8, 2, 4, 4, 2]);
+    }
+
+    #[test]
+    fn witness_layout() {
+        let (maps, _) = parse(INPUT);
+        let shapes: Vec<Vec<Orientation>> = maps.iter().map(orientations).collect();
+
+        let layout = pack(4, 4, &shapes, &[0, 0, 0, 0, 2, 0]).unwrap();
+        let rendered = layout.to_string();
+        assert_eq!(rendered.lines().count(), 4);
+        assert_eq!(rendered.matches('A').count(), 7);
+        assert_eq!(rendered.matches('B').count(), 7);
+
+        let wide = pack(12, 5, &shapes, &[1, 0, 1, 0, 2, 2])
+            .unwrap()
+            .to_string();
+        assert!(wide.lines().all(|l| l.len() == 12));
+        assert_eq!(wide.lines().count(), 5);
+        assert_eq!(wide.matches(|c: char| c != '.' && c != '\n').count(), 6 * 7);
+        assert!(pack(12, 5, &shapes, &[1, 0, 1, 0, 3, 2]).is_none());
+        // plenty of room is packed without searching
+        let roomy = pack(9, 6, &shapes, &[1, 1, 1, 1, 1, 1]).unwrap();
+        assert_eq!(roomy.cells.iter().filter(|c| c.is_some()).count(), 7 * 6);
+    }
+
+    #[test]
+    fn wide_regions() {
+        // too many for a 3x3 block each, so they're searched for across 66 columns
+        let bar =
+            array_vec!([bool; 9] => true, true, true, false, false, false, false, false, false);
+        let shapes = vec![orientations(&bar)];
+        let layout = pack(66, 66, &shapes, &[66 * 22]).unwrap();
+        assert!(layout.cells.iter().all(|c| c.is_some()));
+        assert!(pack(66, 66, &shapes, &[66 * 22 + 1]).is_none());
+    }
+
+    #[test]
+    fn agrees_with_exact_cover() {
+        use aoc_common::exact_cover::{self, GridCover};
+
+        let (maps, regions) = parse(INPUT);
+        let shapes: Vec<Vec<Orientation>> = maps.iter().map(orientations).collect();
+        for ((w, h), counts) in regions {
+            let mut grid = GridCover::new(w as usize, h as usize).with_optional_cells();
+            for (map, &count) in maps.iter().zip(counts.iter()) {
+                let cells: Vec<(i64, i64)> = (0..9)
+                    .filter(|&i| map[i])
+                    .map(|i| ((i % 3) as i64, (i / 3) as i64))
+                    .collect();
+                assert_eq!(
+                    exact_cover::orientations(&cells).len(),
+                    orientations(map).len()
+                );
+                grid = grid.pieces(&cells, count);
+            }
+            let fits = grid.build().0.first_solution().is_some();
+            assert_eq!(
+                pack(w as usize, h as usize, &shapes, &counts).is_some(),
+                fits
+            );
+        }
+    }
 }
@@ -1,6 +1,16 @@
 use crate::files::Res;
 use crate::time;
-use std::fmt::Display;
+use std::fmt::{self, Display};
+
+/// The answer to a part that has no puzzle, like the second half of the last day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoPuzzle;
+
+impl Display for NoPuzzle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no puzzle")
+    }
+}
 
 pub trait AocDay {
     type Input;
@@ -0,0 +1,634 @@
+//! Cellular automata: a neighbourhood, a rule and somewhere to keep the cells.
+//!
+//! [`Dense`] is a bounded grid of any kind of cell, [`Sparse`] a set of live points on an
+//! unbounded lattice, and [`Growing`] a box of live and dead cells that grows as they spread.
+
+use std::collections::{HashMap, HashSet};
+
+pub type Point<const D: usize> = [i64; D];
+
+/// The offsets from a cell to each of its neighbours.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Neighbourhood<const D: usize> {
+    offsets: Vec<Point<D>>,
+}
+
+impl<const D: usize> Neighbourhood<D> {
+    pub fn from_offsets(offsets: Vec<Point<D>>) -> Self {
+        Neighbourhood { offsets }
+    }
+
+    /// Every cell within one step along any number of axes, so 8 in 2D and 80 in 4D.
+    pub fn moore() -> Self {
+        let mut offsets = vec![[0; D]];
+        for d in 0..D {
+            offsets = offsets
+                .into_iter()
+                .flat_map(|o| {
+                    (-1..=1).map(move |delta| {
+                        let mut o = o;
+                        o[d] = delta;
+                        o
+                    })
+                })
+                .collect();
+        }
+        offsets.retain(|o| o.iter().any(|&x| x != 0));
+        Neighbourhood { offsets }
+    }
+
+    /// Only the cells one step along a single axis.
+    pub fn von_neumann() -> Self {
+        let mut offsets = vec![];
+        for d in 0..D {
+            for &delta in [-1, 1].iter() {
+                let mut o = [0; D];
+                o[d] = delta;
+                offsets.push(o);
+            }
+        }
+        Neighbourhood { offsets }
+    }
+
+    pub fn offsets(&self) -> &[Point<D>] {
+        &self.offsets
+    }
+
+    /// The furthest any neighbour is along a single axis.
+    pub fn reach(&self) -> i64 {
+        self.offsets
+            .iter()
+            .flat_map(|o| o.iter())
+            .map(|x| x.abs())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Neighbourhood<2> {
+    /// The six neighbours of a hexagon in axial coordinates.
+    pub fn hex() -> Self {
+        Neighbourhood::from_offsets(vec![[0, 1], [-1, 1], [1, -1], [0, -1], [1, 0], [-1, 0]])
+    }
+}
+
+/// Decides whether a cell is alive next generation.
+pub trait Rule {
+    fn alive(&self, alive: bool, neighbours: usize) -> bool;
+}
+
+impl<F: Fn(bool, usize) -> bool> Rule for F {
+    fn alive(&self, alive: bool, neighbours: usize) -> bool {
+        self(alive, neighbours)
+    }
+}
+
+/// A rule given as the neighbour counts that bring a dead cell to life, and keep a live one so.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LifeRule {
+    birth: u128,
+    survival: u128,
+}
+
+impl LifeRule {
+    /// Counts of 128 or more are ignored, as [`Rule::alive`] never matches them.
+    pub fn new(birth: &[usize], survival: &[usize]) -> Self {
+        let mask = |counts: &[usize]| {
+            counts
+                .iter()
+                .filter(|&&c| c < 128)
+                .fold(0, |m, &c| m | 1 << c)
+        };
+        LifeRule {
+            birth: mask(birth),
+            survival: mask(survival),
+        }
+    }
+
+    /// B3/S23
+    pub fn conway() -> Self {
+        LifeRule::new(&[3], &[2, 3])
+    }
+}
+
+impl Rule for LifeRule {
+    fn alive(&self, alive: bool, neighbours: usize) -> bool {
+        let set = if alive { self.survival } else { self.birth };
+        neighbours < 128 && set & 1 << neighbours != 0
+    }
+}
+
+/// Common to every backend.
+pub trait Automaton {
+    /// Advance one generation, returning whether anything changed.
+    fn step(&mut self) -> bool;
+
+    /// How many cells are alive.
+    fn population(&self) -> usize;
+
+    fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// Step until nothing changes, returning how many generations that took.
+    fn run_to_fixed_point(&mut self) -> usize {
+        let mut generations = 0;
+        while self.step() {
+            generations += 1;
+        }
+        generations
+    }
+}
+
+/// Set each of `out` to `f` of its index, spread over up to `threads` threads.
+fn fill<T: Send>(out: &mut [T], threads: usize, f: impl Fn(usize) -> T + Sync) {
+    if threads <= 1 || out.len() < 2 {
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = f(i);
+        }
+        return;
+    }
+    let chunk = out.len().div_ceil(threads);
+    let f = &f;
+    std::thread::scope(|s| {
+        for (c, part) in out.chunks_mut(chunk).enumerate() {
+            s.spawn(move || {
+                for (i, o) in part.iter_mut().enumerate() {
+                    *o = f(c * chunk + i);
+                }
+            });
+        }
+    });
+}
+
+/// A bounded 2D grid of any kind of cell.
+/// Each cell's neighbours are worked out once, so they can be cells seen past see-through ones.
+pub struct Dense<T, A, F> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+    next: Vec<T>,
+    offsets: Vec<Point<2>>,
+    /// The neighbours of cell `i` are `targets[starts[i]..starts[i + 1]]`.
+    starts: Vec<usize>,
+    targets: Vec<usize>,
+    alive: A,
+    rule: F,
+    threads: usize,
+}
+
+impl<T, A, F> Dense<T, A, F>
+where
+    T: Clone + PartialEq + Send + Sync,
+    A: Fn(&T) -> bool + Sync,
+    F: Fn(&T, usize) -> T + Sync,
+{
+    /// `cells` go row by row. `rule` gets a cell and how many of its neighbours are `alive`.
+    pub fn new(
+        width: usize,
+        height: usize,
+        cells: Vec<T>,
+        neighbourhood: &Neighbourhood<2>,
+        alive: A,
+        rule: F,
+    ) -> Self {
+        assert_eq!(cells.len(), width * height);
+        let mut dense = Dense {
+            width,
+            height,
+            next: cells.clone(),
+            cells,
+            offsets: neighbourhood.offsets().to_vec(),
+            starts: vec![],
+            targets: vec![],
+            alive,
+            rule,
+            threads: 1,
+        };
+        dense.link(|_| false);
+        dense
+    }
+
+    /// Look past cells that are `see_through` to the first one that isn't.
+    /// They must stay see-through, as this is worked out from the cells as they are now.
+    #[must_use]
+    pub fn with_sight(mut self, see_through: impl Fn(&T) -> bool) -> Self {
+        self.link(see_through);
+        self
+    }
+
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    fn link(&mut self, see_through: impl Fn(&T) -> bool) {
+        let (w, h) = (self.width as i64, self.height as i64);
+        self.starts = vec![0];
+        self.targets = vec![];
+        for y in 0..h {
+            for x in 0..w {
+                for &[dx, dy] in self.offsets.iter() {
+                    let (mut nx, mut ny) = (x + dx, y + dy);
+                    while (0..w).contains(&nx) && (0..h).contains(&ny) {
+                        let i = (ny * w + nx) as usize;
+                        if !see_through(&self.cells[i]) {
+                            self.targets.push(i);
+                            break;
+                        }
+                        nx += dx;
+                        ny += dy;
+                    }
+                }
+                self.starts.push(self.targets.len());
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The cells row by row.
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> &T {
+        &self.cells[y * self.width + x]
+    }
+}
+
+impl<T, A, F> Automaton for Dense<T, A, F>
+where
+    T: Clone + PartialEq + Send + Sync,
+    A: Fn(&T) -> bool + Sync,
+    F: Fn(&T, usize) -> T + Sync,
+{
+    fn step(&mut self) -> bool {
+        let Dense {
+            cells,
+            next,
+            starts,
+            targets,
+            alive,
+            rule,
+            threads,
+            ..
+        } = self;
+        fill(next, *threads, |i| {
+            let live = targets[starts[i]..starts[i + 1]]
+                .iter()
+                .filter(|&&n| alive(&cells[n]))
+                .count();
+            rule(&cells[i], live)
+        });
+        std::mem::swap(cells, next);
+        cells != next
+    }
+
+    fn population(&self) -> usize {
+        self.cells.iter().filter(|c| (self.alive)(c)).count()
+    }
+}
+
+fn add<const D: usize>(a: &Point<D>, b: &Point<D>) -> Point<D> {
+    let mut sum = *a;
+    for (s, b) in sum.iter_mut().zip(b.iter()) {
+        *s += b;
+    }
+    sum
+}
+
+/// The live cells of an unbounded lattice, for when they are few and far between.
+/// A cell with no live neighbours is never born, however the rule is set.
+pub struct Sparse<const D: usize, R> {
+    cells: HashSet<Point<D>>,
+    neighbourhood: Neighbourhood<D>,
+    rule: R,
+    threads: usize,
+}
+
+impl<const D: usize, R: Rule + Sync> Sparse<D, R> {
+    pub fn new(
+        cells: impl IntoIterator<Item = Point<D>>,
+        neighbourhood: Neighbourhood<D>,
+        rule: R,
+    ) -> Self {
+        Sparse {
+            cells: cells.into_iter().collect(),
+            neighbourhood,
+            rule,
+            threads: 1,
+        }
+    }
+
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn cells(&self) -> &HashSet<Point<D>> {
+        &self.cells
+    }
+
+    pub fn contains(&self, p: &Point<D>) -> bool {
+        self.cells.contains(p)
+    }
+
+    fn count(&self, live: &[&Point<D>]) -> HashMap<Point<D>, usize> {
+        let mut counts = HashMap::new();
+        for p in live {
+            for o in self.neighbourhood.offsets() {
+                *counts.entry(add(p, o)).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+}
+
+impl<const D: usize, R: Rule + Sync> Automaton for Sparse<D, R> {
+    fn step(&mut self) -> bool {
+        let live: Vec<&Point<D>> = self.cells.iter().collect();
+        let counts = if self.threads <= 1 || live.len() < 2 {
+            self.count(&live)
+        } else {
+            let chunk = live.len().div_ceil(self.threads);
+            let this = &*self;
+            let parts: Vec<HashMap<Point<D>, usize>> = std::thread::scope(|s| {
+                let handles: Vec<_> = live
+                    .chunks(chunk)
+                    .map(|part| s.spawn(move || this.count(part)))
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+            let mut parts = parts.into_iter();
+            let mut counts = parts.next().unwrap_or_default();
+            for part in parts {
+                for (p, n) in part {
+                    *counts.entry(p).or_insert(0) += n;
+                }
+            }
+            counts
+        };
+
+        let mut next: HashSet<Point<D>> = counts
+            .iter()
+            .filter(|(p, &n)| self.rule.alive(self.cells.contains(*p), n))
+            .map(|(p, _)| *p)
+            .collect();
+        // live cells without any live neighbours never made it into the counts
+        if self.rule.alive(true, 0) {
+            next.extend(self.cells.iter().filter(|p| !counts.contains_key(*p)));
+        }
+        let changed = next != self.cells;
+        self.cells = next;
+        changed
+    }
+
+    fn population(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// Live and dead cells in a box that grows to fit the neighbourhood of every live cell,
+/// then shrinks back to the live ones, each generation.
+pub struct Growing<const D: usize, R> {
+    origin: Point<D>,
+    extent: [usize; D],
+    cells: Vec<bool>,
+    neighbourhood: Neighbourhood<D>,
+    rule: R,
+    threads: usize,
+}
+
+impl<const D: usize, R: Rule + Sync> Growing<D, R> {
+    pub fn new(
+        cells: impl IntoIterator<Item = Point<D>>,
+        neighbourhood: Neighbourhood<D>,
+        rule: R,
+    ) -> Self {
+        let mut growing = Growing {
+            origin: [0; D],
+            extent: [0; D],
+            cells: vec![],
+            neighbourhood,
+            rule,
+            threads: 1,
+        };
+        growing.fit(cells.into_iter().collect());
+        growing
+    }
+
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn contains(&self, p: &Point<D>) -> bool {
+        self.index(p).is_some_and(|i| self.cells[i])
+    }
+
+    /// The lowest corner of the box and its size along each axis.
+    pub fn bounds(&self) -> (Point<D>, [usize; D]) {
+        (self.origin, self.extent)
+    }
+
+    pub fn live_cells(&self) -> Vec<Point<D>> {
+        (0..self.cells.len())
+            .filter(|&i| self.cells[i])
+            .map(|i| point_at(&self.origin, &self.extent, i))
+            .collect()
+    }
+
+    fn index(&self, p: &Point<D>) -> Option<usize> {
+        let mut index = 0;
+        for d in (0..D).rev() {
+            let rel = p[d] - self.origin[d];
+            if rel < 0 || rel >= self.extent[d] as i64 {
+                return None;
+            }
+            index = index * self.extent[d] + rel as usize;
+        }
+        Some(index)
+    }
+
+    /// Make the box the smallest around these cells.
+    fn fit(&mut self, live: Vec<Point<D>>) {
+        if live.is_empty() {
+            self.extent = [0; D];
+            self.cells = vec![];
+            return;
+        }
+        for d in 0..D {
+            let low = live.iter().map(|p| p[d]).min().unwrap();
+            let high = live.iter().map(|p| p[d]).max().unwrap();
+            self.origin[d] = low;
+            self.extent[d] = (high - low + 1) as usize;
+        }
+        self.cells = vec![false; self.extent.iter().product()];
+        for p in live {
+            let i = self.index(&p).unwrap();
+            self.cells[i] = true;
+        }
+    }
+}
+
+/// The point at index `i` of a box, with the first axis varying fastest.
+fn point_at<const D: usize>(origin: &Point<D>, extent: &[usize; D], mut i: usize) -> Point<D> {
+    let mut p = *origin;
+    for d in 0..D {
+        p[d] += (i % extent[d]) as i64;
+        i /= extent[d];
+    }
+    p
+}
+
+impl<const D: usize, R: Rule + Sync> Automaton for Growing<D, R> {
+    fn step(&mut self) -> bool {
+        let reach = self.neighbourhood.reach();
+        let mut origin = self.origin;
+        let mut extent = self.extent;
+        for d in 0..D {
+            origin[d] -= reach;
+            extent[d] += 2 * reach as usize;
+        }
+
+        // the padding keeps every neighbour of a live cell inside the new box, so each offset
+        // is a fixed distance along it
+        let size = extent.iter().product();
+        let mut strides = [1; D];
+        for d in 1..D {
+            strides[d] = strides[d - 1] * extent[d - 1] as i64;
+        }
+        let deltas: Vec<i64> = self
+            .neighbourhood
+            .offsets()
+            .iter()
+            .map(|o| o.iter().zip(strides.iter()).map(|(o, s)| o * s).sum())
+            .collect();
+        let mut was = vec![false; size];
+        let mut counts = vec![0; size];
+        for p in self.live_cells() {
+            let i: i64 = (0..D).map(|d| (p[d] - origin[d]) * strides[d]).sum();
+            was[i as usize] = true;
+            for delta in deltas.iter() {
+                counts[(i + delta) as usize] += 1;
+            }
+        }
+
+        let mut next = vec![false; size];
+        let rule = &self.rule;
+        fill(&mut next, self.threads, |i| rule.alive(was[i], counts[i]));
+
+        let live: Vec<Point<D>> = (0..next.len())
+            .filter(|&i| next[i])
+            .map(|i| point_at(&origin, &extent, i))
+            .collect();
+        let (before_origin, before_extent) = self.bounds();
+        let before = std::mem::take(&mut self.cells);
+        self.fit(live);
+        before != self.cells || before_origin != self.origin || before_extent != self.extent
+    }
+
+    fn population(&self) -> usize {
+        self.cells.iter().filter(|&&c| c).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider() -> Vec<Point<2>> {
+        vec![[1, 0], [2, 1], [0, 2], [1, 2], [2, 2]]
+    }
+
+    #[test]
+    fn neighbourhoods() {
+        assert_eq!(Neighbourhood::<2>::moore().offsets().len(), 8);
+        assert_eq!(Neighbourhood::<4>::moore().offsets().len(), 80);
+        assert_eq!(Neighbourhood::<3>::von_neumann().offsets().len(), 6);
+        assert_eq!(Neighbourhood::hex().reach(), 1);
+
+        let rule = LifeRule::new(&[2], &[1, 2]);
+        assert!(rule.alive(false, 2));
+        assert!(!rule.alive(false, 1));
+        assert!(rule.alive(true, 1));
+        assert!(!rule.alive(true, 80));
+
+        let rule = LifeRule::new(&[3, 128, 500], &[]);
+        assert_eq!(rule, LifeRule::new(&[3], &[]));
+        assert!(!rule.alive(false, 128));
+    }
+
+    #[test]
+    fn glider_glides() {
+        // after four generations a glider is the same shape, one step down and to the right
+        let mut moved: Vec<Point<2>> = glider().iter().map(|&[x, y]| [x + 1, y + 1]).collect();
+        moved.sort_unstable();
+
+        let mut sparse = Sparse::new(glider(), Neighbourhood::moore(), LifeRule::conway());
+        sparse.run(4);
+        let mut cells: Vec<Point<2>> = sparse.cells().iter().copied().collect();
+        cells.sort_unstable();
+        assert_eq!(cells, moved);
+
+        let mut growing =
+            Growing::new(glider(), Neighbourhood::moore(), LifeRule::conway()).with_threads(3);
+        growing.run(4);
+        let mut cells = growing.live_cells();
+        cells.sort_unstable();
+        assert_eq!(cells, moved);
+        assert_eq!(growing.bounds(), ([1, 1], [3, 3]));
+
+        let mut threaded =
+            Sparse::new(glider(), Neighbourhood::moore(), LifeRule::conway()).with_threads(2);
+        threaded.run(4);
+        assert_eq!(threaded.cells(), sparse.cells());
+    }
+
+    #[test]
+    fn dense_fixed_point() {
+        // a block is still life, and a blinker keeps changing
+        let block = "....\n.##.\n.##.\n....";
+        let cells: Vec<bool> = block
+            .chars()
+            .filter(|&c| c != '\n')
+            .map(|c| c == '#')
+            .collect();
+        let conway = |&alive: &bool, n: usize| LifeRule::conway().alive(alive, n);
+        let mut dense = Dense::new(4, 4, cells, &Neighbourhood::moore(), |&c| c, conway);
+        assert_eq!(dense.run_to_fixed_point(), 0);
+        assert_eq!(dense.population(), 4);
+
+        let blinker = vec![false, false, false, true, true, true, false, false, false];
+        let mut dense =
+            Dense::new(3, 3, blinker, &Neighbourhood::moore(), |&c| c, conway).with_threads(2);
+        assert!(dense.step());
+        assert!(*dense.get(1, 0) && *dense.get(1, 2) && !dense.get(0, 1));
+
+        // seeing past the gaps, the ends of a row count as neighbours
+        let row = vec![true, false, false, true];
+        let mut dense = Dense::new(
+            4,
+            1,
+            row,
+            &Neighbourhood::moore(),
+            |&c| c,
+            |&c, n| c && n > 0,
+        )
+        .with_sight(|&c| !c);
+        assert!(!dense.step());
+        assert_eq!(dense.population(), 2);
+    }
+}
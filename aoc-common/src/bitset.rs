@@ -1,53 +1,403 @@
+//! Sets of small integers stored as bits.
+//!
+//! [`Bitset`] is a fixed number of `u64` words and is `Copy`, so it can be used as search state.
+//! A plain `Bitset` is one word, positions `0..64`. [`BitVec`] grows on the heap as needed.
+
+use std::fmt;
+use std::iter::FromIterator;
+
 #[derive(PartialEq, Eq, Copy, Clone, std::fmt::Debug, Hash)]
-pub struct Bitset(pub u64);
+pub struct Bitset<const WORDS: usize = 1>(pub [u64; WORDS]);
 
-#[allow(unused)]
 impl Bitset {
-    pub fn empty() -> Self {
-        Bitset(0)
+    pub fn from(raw: u64) -> Self {
+        Bitset([raw])
     }
 
-    pub fn from(raw: u64) -> Self {
-        Bitset(raw)
+    /// The raw bits of a one word set.
+    pub fn bits(&self) -> u64 {
+        self.0[0]
+    }
+}
+
+impl<const WORDS: usize> Default for Bitset<WORDS> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<const WORDS: usize> Bitset<WORDS> {
+    /// The number of positions this set can hold.
+    pub const BITS: usize = WORDS * 64;
+
+    pub fn empty() -> Self {
+        Bitset([0; WORDS])
     }
 
+    /// Panics if `position` is out of range, rather than silently dropping the bit.
     #[must_use]
     pub fn set(&self, position: usize) -> Self {
-        Self(self.0 | (1 << position))
+        let mut new = *self;
+        new.0[position / 64] |= 1 << (position % 64);
+        new
     }
 
     #[must_use]
     pub fn clear(&self, position: usize) -> Self {
-        Self(self.0 & (!0 ^ (1 << position)))
+        let mut new = *self;
+        new.0[position / 64] &= !(1 << (position % 64));
+        new
+    }
+
+    #[must_use]
+    pub fn toggle(&self, position: usize) -> Self {
+        let mut new = *self;
+        new.0[position / 64] ^= 1 << (position % 64);
+        new
     }
 
     pub fn count(&self) -> u64 {
-        self.0.count_ones() as u64
+        self.0.iter().map(|w| w.count_ones() as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|w| *w == 0)
     }
 
     pub fn contains(&self, position: usize) -> bool {
-        (self.0 & (1 << position)) != 0
+        position < Self::BITS && self.0[position / 64] & (1 << (position % 64)) != 0
+    }
+
+    /// Is `other` a subset of this set.
+    pub fn contains_all(&self, other: Self) -> bool {
+        self.zip(other, |a, b| a & b) == other
     }
 
-    pub fn contains_all(&self, Bitset(other): Bitset) -> bool {
-        (self.0 & other) == other
+    pub fn is_disjoint(&self, other: Self) -> bool {
+        self.intersection(other).is_empty()
     }
 
+    /// Reverse the order of the first `len` positions, dropping any beyond them.
     #[must_use]
     pub fn flip_start(&self, len: usize) -> Self {
-        let mut new = Bitset::empty();
-        for i in 0..len {
-            if self.contains(i) {
-                new = new.set(len - i - 1);
-            }
+        (0..len)
+            .filter(|&i| self.contains(i))
+            .map(|i| len - i - 1)
+            .collect()
+    }
+
+    #[must_use]
+    pub fn union(&self, other: Self) -> Self {
+        self.zip(other, |a, b| a | b)
+    }
+
+    #[must_use]
+    pub fn intersection(&self, other: Self) -> Self {
+        self.zip(other, |a, b| a & b)
+    }
+
+    /// Everything in this set but not in `other`.
+    #[must_use]
+    pub fn difference(&self, other: Self) -> Self {
+        self.zip(other, |a, b| a & !b)
+    }
+
+    /// Everything in exactly one of the sets.
+    #[must_use]
+    pub fn symmetric_difference(&self, other: Self) -> Self {
+        self.zip(other, |a, b| a ^ b)
+    }
+
+    fn zip(&self, other: Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        let mut new = *self;
+        for (a, b) in new.0.iter_mut().zip(other.0.iter()) {
+            *a = f(*a, *b);
         }
         new
     }
 
-    pub fn disjoint(&self, Bitset(other): Bitset) -> Self {
-        Bitset(self.0 ^ other)
+    /// The set positions, lowest first.
+    pub fn iter(&self) -> Ones<'_> {
+        Ones::new(&self.0)
+    }
+
+    pub fn lowest(&self) -> Option<usize> {
+        lowest(&self.0)
+    }
+
+    pub fn highest(&self) -> Option<usize> {
+        highest(&self.0)
+    }
+
+    /// The number of set positions below `position`.
+    pub fn rank(&self, position: usize) -> usize {
+        rank(&self.0, position)
+    }
+}
+
+impl<const WORDS: usize> FromIterator<usize> for Bitset<WORDS> {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut set = Self::empty();
+        for i in iter {
+            set.0[i / 64] |= 1 << (i % 64);
+        }
+        set
+    }
+}
+
+/// Written like `{:b}` of a number, highest position first.
+impl<const WORDS: usize> fmt::Display for Bitset<WORDS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&bit_string(&self.0))
+    }
+}
+
+/// A set of positions that grows to fit whatever is inserted.
+#[derive(PartialEq, Eq, Clone, Default, std::fmt::Debug, Hash)]
+pub struct BitVec {
+    // never has trailing zero words, so the derived equality and hash work
+    words: Vec<u64>,
+}
+
+impl BitVec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if `position` wasn't already set.
+    pub fn insert(&mut self, position: usize) -> bool {
+        let word = position / 64;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let was_set = self.words[word] & (1 << (position % 64)) != 0;
+        self.words[word] |= 1 << (position % 64);
+        !was_set
+    }
+
+    /// Returns `true` if `position` was set.
+    pub fn remove(&mut self, position: usize) -> bool {
+        let was_set = self.contains(position);
+        if was_set {
+            self.words[position / 64] &= !(1 << (position % 64));
+            self.trim();
+        }
+        was_set
+    }
+
+    pub fn contains(&self, position: usize) -> bool {
+        self.words
+            .get(position / 64)
+            .is_some_and(|w| w & (1 << (position % 64)) != 0)
+    }
+
+    pub fn count(&self) -> u64 {
+        self.words.iter().map(|w| w.count_ones() as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    /// Is `other` a subset of this set.
+    pub fn contains_all(&self, other: &BitVec) -> bool {
+        other.words.len() <= self.words.len()
+            && other
+                .words
+                .iter()
+                .zip(self.words.iter())
+                .all(|(b, a)| a & b == *b)
+    }
+
+    pub fn is_disjoint(&self, other: &BitVec) -> bool {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .all(|(a, b)| a & b == 0)
+    }
+
+    pub fn union_with(&mut self, other: &BitVec) {
+        self.zip_with(other, |a, b| a | b);
+    }
+
+    pub fn intersect_with(&mut self, other: &BitVec) {
+        self.zip_with(other, |a, b| a & b);
+    }
+
+    pub fn difference_with(&mut self, other: &BitVec) {
+        self.zip_with(other, |a, b| a & !b);
+    }
+
+    pub fn symmetric_difference_with(&mut self, other: &BitVec) {
+        self.zip_with(other, |a, b| a ^ b);
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &BitVec) -> BitVec {
+        let mut new = self.clone();
+        new.union_with(other);
+        new
+    }
+
+    #[must_use]
+    pub fn intersection(&self, other: &BitVec) -> BitVec {
+        let mut new = self.clone();
+        new.intersect_with(other);
+        new
+    }
+
+    #[must_use]
+    pub fn difference(&self, other: &BitVec) -> BitVec {
+        let mut new = self.clone();
+        new.difference_with(other);
+        new
+    }
+
+    #[must_use]
+    pub fn symmetric_difference(&self, other: &BitVec) -> BitVec {
+        let mut new = self.clone();
+        new.symmetric_difference_with(other);
+        new
+    }
+
+    fn zip_with(&mut self, other: &BitVec, f: impl Fn(u64, u64) -> u64) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (i, a) in self.words.iter_mut().enumerate() {
+            *a = f(*a, other.words.get(i).copied().unwrap_or(0));
+        }
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+
+    /// The set positions, lowest first.
+    pub fn iter(&self) -> Ones<'_> {
+        Ones::new(&self.words)
+    }
+
+    pub fn lowest(&self) -> Option<usize> {
+        lowest(&self.words)
+    }
+
+    pub fn highest(&self) -> Option<usize> {
+        highest(&self.words)
+    }
+
+    /// The number of set positions below `position`.
+    pub fn rank(&self, position: usize) -> usize {
+        rank(&self.words, position)
     }
+}
 
+impl FromIterator<usize> for BitVec {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut set = BitVec::new();
+        for i in iter {
+            set.insert(i);
+        }
+        set
+    }
+}
+
+impl<const WORDS: usize> From<Bitset<WORDS>> for BitVec {
+    fn from(set: Bitset<WORDS>) -> Self {
+        let mut set = BitVec {
+            words: set.0.to_vec(),
+        };
+        set.trim();
+        set
+    }
+}
+
+/// Written like `{:b}` of a number, highest position first.
+impl fmt::Display for BitVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&bit_string(&self.words))
+    }
+}
+
+/// Iterator over the set positions of a [`Bitset`] or [`BitVec`].
+pub struct Ones<'a> {
+    words: &'a [u64],
+    index: usize,
+    current: u64,
+}
+
+impl<'a> Ones<'a> {
+    fn new(words: &'a [u64]) -> Self {
+        Ones {
+            words,
+            index: 0,
+            current: words.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+impl Iterator for Ones<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.index += 1;
+            self.current = *self.words.get(self.index)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        // clear the lowest set bit
+        self.current &= self.current - 1;
+        Some(self.index * 64 + bit)
+    }
+}
+
+fn lowest(words: &[u64]) -> Option<usize> {
+    words
+        .iter()
+        .enumerate()
+        .find(|(_, w)| **w != 0)
+        .map(|(i, w)| i * 64 + w.trailing_zeros() as usize)
+}
+
+fn highest(words: &[u64]) -> Option<usize> {
+    words
+        .iter()
+        .enumerate()
+        .rfind(|(_, w)| **w != 0)
+        .map(|(i, w)| i * 64 + 63 - w.leading_zeros() as usize)
+}
+
+fn rank(words: &[u64], position: usize) -> usize {
+    let (whole, part) = (position / 64, position % 64);
+    let below: u32 = words.iter().take(whole).map(|w| w.count_ones()).sum();
+    let partial = match words.get(whole) {
+        Some(w) if part > 0 => (w & (u64::MAX >> (64 - part))).count_ones(),
+        _ => 0,
+    };
+    (below + partial) as usize
+}
+
+fn bit_string(words: &[u64]) -> String {
+    match highest(words) {
+        None => "0".to_string(),
+        Some(top) => (0..=top)
+            .rev()
+            .map(|i| {
+                if words[i / 64] & (1 << (i % 64)) != 0 {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect(),
+    }
 }
 
 #[cfg(test)]
@@ -56,20 +406,78 @@ mod tests {
 
     #[test]
     fn flip_start() {
-        assert_eq!(0b0, Bitset::from(0).flip_start(1).0);
-        assert_eq!(0b1, Bitset::from(0b1).flip_start(1).0);
-        assert_eq!(0b01, Bitset::from(0b10).flip_start(2).0);
-        assert_eq!(0b01, Bitset::from(0b10).flip_start(2).0);
+        assert_eq!(0b0, Bitset::from(0).flip_start(1).bits());
+        assert_eq!(0b1, Bitset::from(0b1).flip_start(1).bits());
+        assert_eq!(0b01, Bitset::from(0b10).flip_start(2).bits());
+        assert_eq!(0b01, Bitset::from(0b10).flip_start(2).bits());
     }
 
-        #[test]
-    fn disjoint() {
-        fn helper (a: u64, b: u64) -> u64 {
-            Bitset::from(a).disjoint(Bitset::from(b)).0
+    #[test]
+    fn symmetric_difference() {
+        fn helper(a: u64, b: u64) -> u64 {
+            Bitset::from(a).symmetric_difference(Bitset::from(b)).bits()
         }
-        assert_eq!(0, helper(0,0));
-        assert_eq!(0, helper(1,1));
-        assert_eq!(3, helper(1,2));
-        assert_eq!(6, helper(5,3));
+        assert_eq!(0, helper(0, 0));
+        assert_eq!(0, helper(1, 1));
+        assert_eq!(3, helper(1, 2));
+        assert_eq!(6, helper(5, 3));
+    }
+
+    #[test]
+    fn set_algebra() {
+        let a: Bitset<2> = vec![1, 3, 70, 100].into_iter().collect();
+        let b: Bitset<2> = vec![3, 4, 100].into_iter().collect();
+        let positions = |s: Bitset<2>| s.iter().collect::<Vec<_>>();
+
+        assert_eq!(positions(a.union(b)), vec![1, 3, 4, 70, 100]);
+        assert_eq!(positions(a.intersection(b)), vec![3, 100]);
+        assert_eq!(positions(a.difference(b)), vec![1, 70]);
+        assert_eq!(positions(a.symmetric_difference(b)), vec![1, 4, 70]);
+        assert!(a.contains_all(a.intersection(b)));
+        assert!(!a.contains_all(b));
+        assert!(a.difference(b).is_disjoint(b));
+        assert_eq!(a.count(), 4);
+        assert!(a.clear(70).toggle(64).contains(64));
+        assert!(!a.contains(128));
+    }
+
+    #[test]
+    fn positions() {
+        let a: Bitset<2> = vec![5, 64, 90].into_iter().collect();
+        assert_eq!(a.lowest(), Some(5));
+        assert_eq!(a.highest(), Some(90));
+        assert_eq!(a.rank(5), 0);
+        assert_eq!(a.rank(6), 1);
+        assert_eq!(a.rank(64), 1);
+        assert_eq!(a.rank(128), 3);
+        assert_eq!(Bitset::<2>::empty().lowest(), None);
+        assert_eq!(Bitset::from(0b0110).to_string(), "110");
+        assert_eq!(format!("{:>5}", Bitset::from(0)), "    0");
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_out_of_range() {
+        let _ = Bitset::from(0).set(64);
+    }
+
+    #[test]
+    fn bit_vec() {
+        let mut v: BitVec = vec![2, 200].into_iter().collect();
+        assert!(v.insert(1000));
+        assert!(!v.insert(2));
+        assert_eq!(v.count(), 3);
+        assert_eq!(v.highest(), Some(1000));
+        assert_eq!(v.rank(1000), 2);
+
+        let w: BitVec = vec![2, 1000].into_iter().collect();
+        assert!(v.contains_all(&w));
+        assert_eq!(v.difference(&w).iter().collect::<Vec<_>>(), vec![200]);
+        // no trailing zero words, so sets compare by contents
+        assert_eq!(v.intersection(&w), w);
+        assert!(v.remove(1000) && v.remove(200));
+        assert_eq!(v, BitVec::from(Bitset::from(0b100)));
+        assert_eq!(v.to_string(), "100");
+        assert!(v.symmetric_difference(&v).is_empty());
     }
 }
@@ -0,0 +1,225 @@
+//! Finding where a repeatedly stepped state starts going round in circles.
+
+use crate::numtheory::lcm_all;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// After `prefix` steps the states repeat every `length` steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub prefix: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    /// The earliest step that has the same state as step `n`.
+    pub fn earliest_equivalent(&self, n: usize) -> usize {
+        if n < self.prefix {
+            n
+        } else {
+            self.prefix + (n - self.prefix) % self.length
+        }
+    }
+}
+
+/// Floyd's tortoise and hare, which only ever holds two states.
+pub fn floyd<T: Clone + PartialEq>(start: &T, step: impl Fn(&T) -> T) -> Cycle {
+    let mut tortoise = step(start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    // the hare is now a multiple of the cycle length ahead, so they meet where it starts
+    let mut prefix = 0;
+    tortoise = start.clone();
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix += 1;
+    }
+
+    let mut length = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        length += 1;
+    }
+    Cycle { prefix, length }
+}
+
+/// Brent's algorithm, which needs fewer steps than Floyd's.
+pub fn brent<T: Clone + PartialEq>(start: &T, step: impl Fn(&T) -> T) -> Cycle {
+    // the tortoise teleports to the hare every power of two, until the hare laps it
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(start);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = step(&hare);
+        length += 1;
+    }
+
+    let mut prefix = 0;
+    tortoise = start.clone();
+    hare = start.clone();
+    for _ in 0..length {
+        hare = step(&hare);
+    }
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix += 1;
+    }
+    Cycle { prefix, length }
+}
+
+/// Remember every state until one comes round again.
+/// Also returns the states seen, one per step up to the end of the first cycle.
+pub fn find_cycle<T: Clone + Eq + Hash>(
+    start: T,
+    mut step: impl FnMut(&T) -> T,
+) -> (Cycle, Vec<T>) {
+    let mut seen = HashMap::new();
+    let mut history = vec![];
+    let mut state = start;
+    loop {
+        if let Some(&prefix) = seen.get(&state) {
+            let length = history.len() - prefix;
+            return (Cycle { prefix, length }, history);
+        }
+        seen.insert(state.clone(), history.len());
+        let next = step(&state);
+        history.push(state);
+        state = next;
+    }
+}
+
+/// The state after `n` steps, skipping over whole cycles once it starts repeating.
+pub fn state_after<T: Clone + Eq + Hash>(start: T, n: usize, mut step: impl FnMut(&T) -> T) -> T {
+    let mut seen = HashMap::new();
+    let mut history = vec![];
+    let mut state = start;
+    for i in 0..n {
+        if let Some(&prefix) = seen.get(&state) {
+            let cycle = Cycle {
+                prefix,
+                length: i - prefix,
+            };
+            return history.swap_remove(cycle.earliest_equivalent(n));
+        }
+        seen.insert(state.clone(), i);
+        let next = step(&state);
+        history.push(state);
+        state = next;
+    }
+    state
+}
+
+/// Step until nothing changes, returning the settled state and how many steps that took.
+pub fn fixed_point<T: PartialEq>(start: T, mut step: impl FnMut(&T) -> T) -> (T, usize) {
+    let mut state = start;
+    let mut steps = 0;
+    loop {
+        let next = step(&state);
+        if next == state {
+            return (state, steps);
+        }
+        state = next;
+        steps += 1;
+    }
+}
+
+/// For a step that can be undone, so the start is on the cycle, and whose `components` each
+/// move independently: how many steps each takes to first come back to where it started.
+pub fn component_periods<T: Clone, C: PartialEq>(
+    start: &T,
+    mut step: impl FnMut(&mut T),
+    components: impl Fn(&T) -> Vec<C>,
+) -> Vec<usize> {
+    let initial = components(start);
+    let mut periods = vec![None; initial.len()];
+    let mut state = start.clone();
+    let mut steps = 0;
+    while periods.iter().any(Option::is_none) {
+        step(&mut state);
+        steps += 1;
+        for ((period, now), then) in periods
+            .iter_mut()
+            .zip(components(&state))
+            .zip(initial.iter())
+        {
+            if period.is_none() && now == *then {
+                *period = Some(steps);
+            }
+        }
+    }
+    periods.into_iter().flatten().collect()
+}
+
+/// The period of the whole state, the lcm of the [`component_periods`].
+pub fn combined_period<T: Clone, C: PartialEq>(
+    start: &T,
+    step: impl FnMut(&mut T),
+    components: impl Fn(&T) -> Vec<C>,
+) -> usize {
+    lcm_all(component_periods(start, step, components))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 0, 1, 2, then 3, 4, 5, 6 over and over.
+    fn rho(x: &usize) -> usize {
+        if *x < 3 {
+            x + 1
+        } else {
+            3 + (x - 2) % 4
+        }
+    }
+
+    #[test]
+    fn detectors_agree() {
+        let expected = Cycle {
+            prefix: 3,
+            length: 4,
+        };
+        assert_eq!(floyd(&0, rho), expected);
+        assert_eq!(brent(&0, rho), expected);
+        let (cycle, history) = find_cycle(0, rho);
+        assert_eq!(cycle, expected);
+        assert_eq!(history, vec![0, 1, 2, 3, 4, 5, 6]);
+
+        // already on the cycle
+        let on_cycle = Cycle {
+            prefix: 0,
+            length: 4,
+        };
+        assert_eq!(brent(&5, rho), on_cycle);
+        assert_eq!(floyd(&5, rho), on_cycle);
+    }
+
+    #[test]
+    fn skipping_ahead() {
+        assert_eq!(state_after(0, 2, rho), 2);
+        assert_eq!(state_after(0, 9, rho), 5);
+        assert_eq!(state_after(0, 1_000_000_000_000, rho), 4);
+        assert_eq!(fixed_point(100, |x| x / 2), (0, 7));
+    }
+
+    #[test]
+    fn periods() {
+        // two hands, one going round 4 positions and the other 6
+        let step = |s: &mut (u8, u8)| *s = ((s.0 + 1) % 4, (s.1 + 1) % 6);
+        let components = |s: &(u8, u8)| vec![s.0, s.1];
+        assert_eq!(component_periods(&(1, 2), step, components), vec![4, 6]);
+        assert_eq!(combined_period(&(1, 2), step, components), 12);
+    }
+}
@@ -0,0 +1,341 @@
+//! Distances from a source to everything reachable, and between every pair of nodes.
+//!
+//! Where [`crate::search`] stops at the first goal, these explore the whole graph.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+/// The distance to every node reached, along with the node it was reached from.
+#[derive(Clone, Debug)]
+pub struct DistanceMap<N> {
+    map: HashMap<N, (u64, Option<N>)>,
+}
+
+impl<N: Hash + Eq + Clone> DistanceMap<N> {
+    pub fn get(&self, node: &N) -> Option<u64> {
+        self.map.get(node).map(|(d, _)| *d)
+    }
+
+    pub fn contains(&self, node: &N) -> bool {
+        self.map.contains_key(node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&N, u64)> {
+        self.map.iter().map(|(n, (d, _))| (n, *d))
+    }
+
+    /// The distance to the furthest node, i.e. the depth of the search tree.
+    pub fn max_distance(&self) -> u64 {
+        self.map.values().map(|(d, _)| *d).max().unwrap_or(0)
+    }
+
+    /// The route taken from a source to `node`, inclusive of both.
+    pub fn path_to(&self, node: &N) -> Option<Vec<N>> {
+        let mut path = vec![node.clone()];
+        let mut current = self.map.get(node)?;
+        while let Some(parent) = &current.1 {
+            path.push(parent.clone());
+            current = &self.map[parent];
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Flood fill out from `starts`, going no further than `max_depth` moves.
+pub fn flood_fill<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    max_depth: u64,
+) -> DistanceMap<N>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = N>,
+{
+    let mut map = HashMap::new();
+    let mut queue = VecDeque::new();
+    for s in starts {
+        if let Entry::Vacant(e) = map.entry(s.clone()) {
+            e.insert((0, None));
+            queue.push_back((s, 0));
+        }
+    }
+
+    while let Some((node, depth)) = queue.pop_front() {
+        if depth == max_depth {
+            continue;
+        }
+        for next in successors(&node) {
+            if let Entry::Vacant(e) = map.entry(next.clone()) {
+                e.insert((depth + 1, Some(node.clone())));
+                queue.push_back((next, depth + 1));
+            }
+        }
+    }
+
+    DistanceMap { map }
+}
+
+/// Breadth first distances from `starts` to every reachable node.
+pub fn bfs_distances<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    successors: impl FnMut(&N) -> I,
+) -> DistanceMap<N>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = N>,
+{
+    flood_fill(starts, successors, u64::MAX)
+}
+
+/// Dijkstra distances from `starts` to every reachable node.
+pub fn dijkstra_distances<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+) -> DistanceMap<N>
+where
+    N: Hash + Eq + Clone + Ord,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    let mut map: HashMap<N, (u64, Option<N>)> = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    for s in starts {
+        map.insert(s.clone(), (0, None));
+        queue.push(Reverse((0, s)));
+    }
+
+    while let Some(Reverse((cost, node))) = queue.pop() {
+        if cost > map[&node].0 {
+            continue;
+        }
+        for (next, step) in successors(&node) {
+            let next_cost = cost + step;
+            let better = map.get(&next).is_none_or(|(d, _)| next_cost < *d);
+            if better {
+                map.insert(next.clone(), (next_cost, Some(node.clone())));
+                queue.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+
+    DistanceMap { map }
+}
+
+/// Compress a grid (or any graph) into a weighted graph between points of interest.
+/// A search is run from each POI, recording the distance to every other POI it can reach
+/// without passing through a third one.
+pub fn poi_graph<N, I>(
+    pois: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    is_poi: impl Fn(&N) -> bool,
+) -> HashMap<N, Vec<(N, u64)>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = N>,
+{
+    let mut graph = HashMap::new();
+    for poi in pois {
+        let distances = bfs_distances(vec![poi.clone()], |n| {
+            if *n != poi && is_poi(n) {
+                // don't walk through other points of interest
+                vec![]
+            } else {
+                successors(n).into_iter().collect()
+            }
+        });
+        let edges = distances
+            .iter()
+            .filter(|(n, _)| **n != poi && is_poi(n))
+            .map(|(n, d)| (n.clone(), d))
+            .collect();
+        graph.insert(poi, edges);
+    }
+    graph
+}
+
+/// Shortest distances between every pair of nodes.
+#[derive(Clone, Debug)]
+pub struct AllPairs<N> {
+    index: HashMap<N, usize>,
+    distances: Vec<Vec<Option<u64>>>,
+}
+
+impl<N: Hash + Eq + Clone> AllPairs<N> {
+    pub fn get(&self, from: &N, to: &N) -> Option<u64> {
+        self.distances[*self.index.get(from)?][*self.index.get(to)?]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &N> {
+        self.index.keys()
+    }
+}
+
+/// Floyd–Warshall over an explicit weighted graph, best for small dense graphs.
+pub fn floyd_warshall<N: Hash + Eq + Clone>(graph: &HashMap<N, Vec<(N, u64)>>) -> AllPairs<N> {
+    let mut index: HashMap<N, usize> = HashMap::new();
+    for (n, edges) in graph {
+        for m in std::iter::once(n).chain(edges.iter().map(|(m, _)| m)) {
+            let len = index.len();
+            index.entry(m.clone()).or_insert(len);
+        }
+    }
+
+    let size = index.len();
+    let mut dist: Vec<Vec<Option<u64>>> = vec![vec![None; size]; size];
+    for (i, row) in dist.iter_mut().enumerate() {
+        row[i] = Some(0);
+    }
+    for (n, edges) in graph {
+        let i = index[n];
+        for (m, d) in edges {
+            let j = index[m];
+            if dist[i][j].is_none_or(|old| *d < old) {
+                dist[i][j] = Some(*d);
+            }
+        }
+    }
+
+    for k in 0..size {
+        let via = dist[k].clone();
+        for row in dist.iter_mut() {
+            let ik = match row[k] {
+                Some(d) => d,
+                None => continue,
+            };
+            for (ij, kj) in row.iter_mut().zip(via.iter()) {
+                if let Some(kj) = kj {
+                    if ij.is_none_or(|ij| ik + kj < ij) {
+                        *ij = Some(ik + kj);
+                    }
+                }
+            }
+        }
+    }
+
+    AllPairs {
+        index,
+        distances: dist,
+    }
+}
+
+/// All pairs distances between `nodes` by running Dijkstra from each, best for large sparse
+/// graphs. Nodes reached that aren't in `nodes` are ignored.
+pub fn all_pairs_dijkstra<N, I>(
+    nodes: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+) -> AllPairs<N>
+where
+    N: Hash + Eq + Clone + Ord,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    let mut index: HashMap<N, usize> = HashMap::new();
+    for n in nodes {
+        let len = index.len();
+        index.entry(n).or_insert(len);
+    }
+
+    let mut distances = vec![vec![None; index.len()]; index.len()];
+    for (n, &i) in index.iter() {
+        let from_n = dijkstra_distances(vec![n.clone()], &mut successors);
+        for (m, &j) in index.iter() {
+            distances[i][j] = from_n.get(m);
+        }
+    }
+
+    AllPairs { index, distances }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "\
+#########
+#a..#..b#
+#.#.#.#.#
+#...c...#
+#########";
+
+    type Point = (usize, usize);
+
+    fn at((x, y): Point) -> u8 {
+        GRID.lines().nth(y).unwrap().as_bytes()[x]
+    }
+
+    fn neighbours(&(x, y): &Point) -> Vec<Point> {
+        vec![(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .into_iter()
+            .filter(|&p| at(p) != b'#')
+            .collect()
+    }
+
+    #[test]
+    fn bfs_distance_map() {
+        let d = bfs_distances(vec![(1, 1)], neighbours);
+        assert_eq!(d.len(), 17);
+        assert_eq!(d.get(&(4, 3)), Some(5));
+        assert_eq!(d.get(&(7, 1)), Some(10));
+        assert_eq!(d.get(&(0, 0)), None);
+        assert_eq!(d.max_distance(), 10);
+
+        let path = d.path_to(&(4, 3)).unwrap();
+        assert_eq!(path.len(), 6);
+        assert_eq!(path[0], (1, 1));
+        assert_eq!(path[5], (4, 3));
+    }
+
+    #[test]
+    fn flood_fill_stops_at_depth() {
+        let d = flood_fill(vec![(1, 1)], neighbours, 2);
+        assert_eq!(d.len(), 5);
+        assert!(d.iter().all(|(_, d)| d <= 2));
+    }
+
+    #[test]
+    fn dijkstra_matches_bfs_on_unit_weights() {
+        let bfs = bfs_distances(vec![(1, 1)], neighbours);
+        let dijkstra = dijkstra_distances(vec![(1, 1)], |p| {
+            neighbours(p)
+                .into_iter()
+                .map(|n| (n, 1))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(bfs.len(), dijkstra.len());
+        assert!(bfs.iter().all(|(n, d)| dijkstra.get(n) == Some(d)));
+    }
+
+    #[test]
+    fn compress_and_all_pairs() {
+        let is_poi = |p: &Point| at(*p).is_ascii_lowercase();
+        let pois = vec![(1, 1), (7, 1), (4, 3)];
+        let graph = poi_graph(pois.clone(), neighbours, is_poi);
+
+        let mut from_a = graph[&(1, 1)].clone();
+        from_a.sort();
+        // b can only be reached from a by passing c
+        assert_eq!(from_a, vec![((4, 3), 5)]);
+        assert_eq!(graph[&(4, 3)].len(), 2);
+
+        let fw = floyd_warshall(&graph);
+        assert_eq!(fw.get(&(1, 1), &(7, 1)), Some(10));
+        assert_eq!(fw.get(&(7, 1), &(7, 1)), Some(0));
+        assert_eq!(fw.nodes().count(), 3);
+
+        let dj = all_pairs_dijkstra(pois.clone(), |p| graph[p].clone());
+        for a in pois.iter() {
+            for b in pois.iter() {
+                assert_eq!(fw.get(a, b), dj.get(a, b));
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Disjoint sets (union-find) over `0..n`, with path compression and union by size.
+
+/// A forest of disjoint sets, each named by its root.
+#[derive(Clone, Debug)]
+pub struct Dsu {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    components: usize,
+}
+
+impl Dsu {
+    /// `n` singleton sets.
+    pub fn new(n: usize) -> Self {
+        Dsu {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            components: n,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// The root of the set containing `x`.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // point everything on the way straight at the root
+        let mut x = x;
+        while self.parent[x] != root {
+            let next = self.parent[x];
+            self.parent[x] = root;
+            x = next;
+        }
+        root
+    }
+
+    /// Join the sets containing `a` and `b`, returning the new root if they were separate.
+    pub fn union(&mut self, a: usize, b: usize) -> Option<usize> {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return None;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.components -= 1;
+        Some(a)
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The size of the set containing `x`.
+    pub fn size_of(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).filter(move |&x| self.parent[x] == x)
+    }
+
+    /// `(root, size)` for every set.
+    pub fn sizes(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.roots().map(move |r| (r, self.size[r]))
+    }
+
+    /// The members of every set, in ascending order within each.
+    pub fn components(&mut self) -> Vec<Vec<usize>> {
+        let mut index = vec![usize::MAX; self.len()];
+        let mut components: Vec<Vec<usize>> = vec![];
+        for x in 0..self.len() {
+            let root = self.find(x);
+            if index[root] == usize::MAX {
+                index[root] = components.len();
+                components.push(vec![]);
+            }
+            components[index[root]].push(x);
+        }
+        components
+    }
+}
+
+/// An edge from Kruskal's algorithm that joined two sets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Merge<W> {
+    pub weight: W,
+    pub a: usize,
+    pub b: usize,
+    /// The size of the newly joined set.
+    pub size: usize,
+    /// How many sets remain after joining.
+    pub components: usize,
+}
+
+/// Kruskal's algorithm run one merge at a time, see [`kruskal`].
+pub struct Kruskal<I> {
+    dsu: Dsu,
+    edges: I,
+}
+
+impl<I> Kruskal<I> {
+    /// The sets as they are after the merges taken so far.
+    pub fn dsu(&mut self) -> &mut Dsu {
+        &mut self.dsu
+    }
+}
+
+impl<W, I: Iterator<Item = (W, usize, usize)>> Iterator for Kruskal<I> {
+    type Item = Merge<W>;
+
+    fn next(&mut self) -> Option<Merge<W>> {
+        if self.dsu.component_count() <= 1 {
+            return None;
+        }
+        for (weight, a, b) in &mut self.edges {
+            if let Some(root) = self.dsu.union(a, b) {
+                return Some(Merge {
+                    weight,
+                    a,
+                    b,
+                    size: self.dsu.size[root],
+                    components: self.dsu.component_count(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// Join `n` nodes using `(weight, a, b)` edges, which must already be sorted by weight.
+/// Yields each edge that connects two sets, so the whole lot is a minimum spanning forest.
+pub fn kruskal<W, I>(n: usize, edges: I) -> Kruskal<I::IntoIter>
+where
+    I: IntoIterator<Item = (W, usize, usize)>,
+{
+    Kruskal {
+        dsu: Dsu::new(n),
+        edges: edges.into_iter(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_and_find() {
+        let mut dsu = Dsu::new(6);
+        assert_eq!(dsu.component_count(), 6);
+        assert!(dsu.union(0, 1).is_some());
+        assert!(dsu.union(2, 3).is_some());
+        assert!(dsu.union(1, 3).is_some());
+        assert!(dsu.union(0, 2).is_none());
+
+        assert!(dsu.same(0, 3));
+        assert!(!dsu.same(0, 4));
+        assert_eq!(dsu.size_of(2), 4);
+        assert_eq!(dsu.component_count(), 3);
+
+        let mut sizes: Vec<usize> = dsu.sizes().map(|(_, s)| s).collect();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 1, 4]);
+        assert_eq!(dsu.components(), vec![vec![0, 1, 2, 3], vec![4], vec![5]]);
+    }
+
+    #[test]
+    fn minimum_spanning_tree() {
+        let edges = vec![(1, 0, 1), (2, 1, 2), (3, 0, 2), (4, 2, 3), (5, 3, 0)];
+        let merges: Vec<Merge<i32>> = kruskal(4, edges).collect();
+        assert_eq!(merges.iter().map(|m| m.weight).sum::<i32>(), 7);
+        let last = merges.last().unwrap();
+        assert_eq!((last.a, last.b, last.size, last.components), (2, 3, 4, 1));
+    }
+}
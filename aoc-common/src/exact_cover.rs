@@ -0,0 +1,516 @@
+//! Exact cover by Knuth's Algorithm X, using dancing links.
+//!
+//! Every primary column must be covered by exactly one chosen row, or by its count of them,
+//! and every secondary column by at most one. [`GridCover`] builds the columns and rows for
+//! fitting pieces onto a grid.
+
+/// The links of a sparse 0/1 matrix. Node 0 is the root, nodes `1..=columns` the headers.
+#[derive(Clone, Debug)]
+pub struct ExactCover {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    row: Vec<usize>,
+    size: Vec<usize>,
+    /// How many more rows each column needs.
+    need: Vec<usize>,
+    rows: usize,
+}
+
+impl ExactCover {
+    /// Columns `0..primary` must be covered, `primary..primary + secondary` may be.
+    pub fn new(primary: usize, secondary: usize) -> Self {
+        let columns = primary + secondary;
+        let mut cover = ExactCover {
+            left: vec![0; columns + 1],
+            right: vec![0; columns + 1],
+            up: (0..=columns).collect(),
+            down: (0..=columns).collect(),
+            column: (0..=columns).collect(),
+            row: vec![usize::MAX; columns + 1],
+            size: vec![0; columns + 1],
+            need: vec![1; columns + 1],
+            rows: 0,
+        };
+        // only the primary headers are linked to the root, so the search never picks a secondary
+        for h in 0..=columns {
+            let (l, r) = if h == 0 || h > primary {
+                (h, h)
+            } else {
+                (h - 1, if h == primary { 0 } else { h + 1 })
+            };
+            cover.left[h] = l;
+            cover.right[h] = r;
+        }
+        if primary > 0 {
+            cover.left[0] = primary;
+            cover.right[0] = 1;
+        }
+        cover
+    }
+
+    /// Add a row covering `columns`, returning its index.
+    pub fn add_row(&mut self, columns: &[usize]) -> usize {
+        let row = self.rows;
+        self.rows += 1;
+        let first = self.left.len();
+        for (i, &c) in columns.iter().enumerate() {
+            let header = c + 1;
+            let node = self.left.len();
+            // linked into the bottom of the column, and between the previous node and the first
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            self.down[self.up[header]] = node;
+            self.up[header] = node;
+            self.left.push(if i == 0 { node } else { node - 1 });
+            self.right.push(first);
+            if i > 0 {
+                self.right[node - 1] = node;
+                self.left[first] = node;
+            }
+            self.column.push(header);
+            self.row.push(row);
+            self.size[header] += 1;
+        }
+        row
+    }
+
+    /// Make a primary column need `count` rows rather than one. The rows it's covered by are
+    /// a set, so each solution is still found once.
+    pub fn set_count(&mut self, column: usize, count: usize) {
+        assert!(count > 0, "a primary column must be covered");
+        self.need[column + 1] = count;
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows
+    }
+
+    /// Call `f` with the rows of each solution until it returns `false`.
+    pub fn for_each_solution(&mut self, mut f: impl FnMut(&[usize]) -> bool) {
+        self.search(&mut vec![], &mut f);
+    }
+
+    pub fn first_solution(&mut self) -> Option<Vec<usize>> {
+        let mut first = None;
+        self.for_each_solution(|rows| {
+            first = Some(rows.to_vec());
+            false
+        });
+        first
+    }
+
+    pub fn solutions(&mut self) -> Vec<Vec<usize>> {
+        let mut all = vec![];
+        self.for_each_solution(|rows| {
+            all.push(rows.to_vec());
+            true
+        });
+        all
+    }
+
+    pub fn count_solutions(&mut self) -> usize {
+        let mut count = 0;
+        self.for_each_solution(|_| {
+            count += 1;
+            true
+        });
+        count
+    }
+
+    /// Returns `false` once `f` asks to stop.
+    fn search(&mut self, chosen: &mut Vec<usize>, f: &mut impl FnMut(&[usize]) -> bool) -> bool {
+        if self.right[0] == 0 {
+            return f(chosen);
+        }
+
+        // the column with the fewest choices of its next row fails fastest
+        let choices = |cover: &Self, h: usize| (cover.size[h] + 1).saturating_sub(cover.need[h]);
+        let mut c = self.right[0];
+        let mut h = self.right[c];
+        while h != 0 {
+            if choices(self, h) < choices(self, c) {
+                c = h;
+            }
+            h = self.right[h];
+        }
+
+        // each row tried is hidden afterwards, so the rows of a column are chosen in order
+        let mut hidden = vec![];
+        let mut keep_going = true;
+        while keep_going && self.size[c] >= self.need[c] {
+            let r = self.down[c];
+            self.hide(r);
+            hidden.push(r);
+            chosen.push(self.row[r]);
+            // a column is only covered once it has all the rows it needs
+            let mut j = r;
+            loop {
+                let column = self.column[j];
+                self.need[column] -= 1;
+                if self.need[column] == 0 {
+                    self.cover(column);
+                }
+                j = self.right[j];
+                if j == r {
+                    break;
+                }
+            }
+
+            keep_going = self.search(chosen, f);
+
+            loop {
+                j = self.left[j];
+                let column = self.column[j];
+                if self.need[column] == 0 {
+                    self.uncover(column);
+                }
+                self.need[column] += 1;
+                if j == r {
+                    break;
+                }
+            }
+            chosen.pop();
+        }
+        for &r in hidden.iter().rev() {
+            self.unhide(r);
+        }
+        keep_going
+    }
+
+    /// Take every node of row `r` out of its column.
+    fn hide(&mut self, r: usize) {
+        let mut j = r;
+        loop {
+            self.down[self.up[j]] = self.down[j];
+            self.up[self.down[j]] = self.up[j];
+            self.size[self.column[j]] -= 1;
+            j = self.right[j];
+            if j == r {
+                break;
+            }
+        }
+    }
+
+    fn unhide(&mut self, r: usize) {
+        let mut j = r;
+        loop {
+            j = self.left[j];
+            self.size[self.column[j]] += 1;
+            self.down[self.up[j]] = j;
+            self.up[self.down[j]] = j;
+            if j == r {
+                break;
+            }
+        }
+    }
+
+    fn cover(&mut self, c: usize) {
+        self.right[self.left[c]] = self.right[c];
+        self.left[self.right[c]] = self.left[c];
+        let mut i = self.down[c];
+        while i != c {
+            let mut j = self.right[i];
+            while j != i {
+                self.down[self.up[j]] = self.down[j];
+                self.up[self.down[j]] = self.up[j];
+                self.size[self.column[j]] -= 1;
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, c: usize) {
+        let mut i = self.up[c];
+        while i != c {
+            let mut j = self.left[i];
+            while j != i {
+                self.size[self.column[j]] += 1;
+                self.down[self.up[j]] = j;
+                self.up[self.down[j]] = j;
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        self.right[self.left[c]] = c;
+        self.left[self.right[c]] = c;
+    }
+}
+
+/// Every distinct rotation and reflection of a shape, each moved so its cells start at `(0, 0)`.
+pub fn orientations(cells: &[(i64, i64)]) -> Vec<Vec<(usize, usize)>> {
+    let mut cells = cells.to_vec();
+    let mut found: Vec<Vec<(usize, usize)>> = vec![];
+    for _flip in 0..2 {
+        for _rotation in 0..4 {
+            let min_x = cells.iter().map(|c| c.0).min().unwrap_or(0);
+            let min_y = cells.iter().map(|c| c.1).min().unwrap_or(0);
+            let mut shape: Vec<(usize, usize)> = cells
+                .iter()
+                .map(|&(x, y)| ((x - min_x) as usize, (y - min_y) as usize))
+                .collect();
+            shape.sort_unstable();
+            if !found.contains(&shape) {
+                found.push(shape);
+            }
+            cells.iter_mut().for_each(|c| *c = (-c.1, c.0));
+        }
+        cells.iter_mut().for_each(|c| c.0 = -c.0);
+    }
+    found
+}
+
+/// A piece put down on the grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub piece: usize,
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// Builds the exact cover problem of fitting pieces onto a grid.
+/// There's a column per cell and a column per piece, so every piece is used exactly as many
+/// times as it was given. Copies of a piece aren't told apart, so swapping them isn't a new
+/// solution.
+#[derive(Clone, Debug)]
+pub struct GridCover {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    pieces: Vec<Vec<Vec<(usize, usize)>>>,
+    counts: Vec<usize>,
+    optional_cells: bool,
+}
+
+impl GridCover {
+    pub fn new(width: usize, height: usize) -> Self {
+        GridCover {
+            width,
+            height,
+            blocked: vec![false; width * height],
+            pieces: vec![],
+            counts: vec![],
+            optional_cells: false,
+        }
+    }
+
+    /// Allow cells to be left empty, for packing rather than tiling.
+    #[must_use]
+    pub fn with_optional_cells(mut self) -> Self {
+        self.optional_cells = true;
+        self
+    }
+
+    /// A cell that no piece may cover.
+    #[must_use]
+    pub fn blocked(mut self, x: usize, y: usize) -> Self {
+        self.blocked[y * self.width + x] = true;
+        self
+    }
+
+    /// A piece that may be rotated and flipped.
+    #[must_use]
+    pub fn piece(self, cells: &[(i64, i64)]) -> Self {
+        self.pieces(cells, 1)
+    }
+
+    /// `count` copies of a piece that may be rotated and flipped. None at all is fine.
+    #[must_use]
+    pub fn pieces(self, cells: &[(i64, i64)], count: usize) -> Self {
+        let all = orientations(cells);
+        self.pieces_with_orientations(all, count)
+    }
+
+    /// A piece that may only be placed in these orientations.
+    #[must_use]
+    pub fn piece_with_orientations(self, orientations: Vec<Vec<(usize, usize)>>) -> Self {
+        self.pieces_with_orientations(orientations, 1)
+    }
+
+    #[must_use]
+    pub fn pieces_with_orientations(
+        mut self,
+        orientations: Vec<Vec<(usize, usize)>>,
+        count: usize,
+    ) -> Self {
+        if count > 0 {
+            self.pieces.push(orientations);
+            self.counts.push(count);
+        }
+        self
+    }
+
+    /// The problem, and the placement each of its rows stands for.
+    pub fn build(&self) -> (ExactCover, Vec<Placement>) {
+        let cell_columns = self.width * self.height;
+        let mut cover = if self.optional_cells {
+            ExactCover::new(self.pieces.len(), cell_columns)
+        } else {
+            ExactCover::new(cell_columns + self.pieces.len(), 0)
+        };
+        // primary columns come first, so which group that is depends on the mode
+        let (cell_base, piece_base) = if self.optional_cells {
+            (self.pieces.len(), 0)
+        } else {
+            (0, cell_columns)
+        };
+        for (p, &count) in self.counts.iter().enumerate() {
+            cover.set_count(piece_base + p, count);
+        }
+
+        // a blocked cell that must be covered can't be, so fill it with a row of its own
+        let mut placements = vec![];
+        if !self.optional_cells {
+            for i in (0..cell_columns).filter(|&i| self.blocked[i]) {
+                cover.add_row(&[cell_base + i]);
+                placements.push(Placement {
+                    piece: usize::MAX,
+                    cells: vec![(i % self.width, i / self.width)],
+                });
+            }
+        }
+
+        for (p, orientations) in self.pieces.iter().enumerate() {
+            for shape in orientations {
+                let w = shape.iter().map(|c| c.0).max().map_or(0, |m| m + 1);
+                let h = shape.iter().map(|c| c.1).max().map_or(0, |m| m + 1);
+                for y in 0..(self.height + 1).saturating_sub(h) {
+                    for x in 0..(self.width + 1).saturating_sub(w) {
+                        let cells: Vec<(usize, usize)> =
+                            shape.iter().map(|&(dx, dy)| (x + dx, y + dy)).collect();
+                        if cells.iter().any(|&(x, y)| self.blocked[y * self.width + x]) {
+                            continue;
+                        }
+                        let mut columns = vec![piece_base + p];
+                        columns.extend(cells.iter().map(|&(x, y)| cell_base + y * self.width + x));
+                        cover.add_row(&columns);
+                        placements.push(Placement { piece: p, cells });
+                    }
+                }
+            }
+        }
+        (cover, placements)
+    }
+
+    /// Draw a solution with a letter per piece, `#` for blocked and `.` for empty cells.
+    pub fn render(&self, placements: &[&Placement]) -> String {
+        let mut grid: Vec<char> = self
+            .blocked
+            .iter()
+            .map(|&b| if b { '#' } else { '.' })
+            .collect();
+        for placement in placements.iter().filter(|p| p.piece != usize::MAX) {
+            let letter = (b'A' + (placement.piece % 26) as u8) as char;
+            for &(x, y) in placement.cells.iter() {
+                grid[y * self.width + x] = letter;
+            }
+        }
+        grid.chunks(self.width)
+            .map(|row| row.iter().collect::<String>() + "\n")
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knuths_example() {
+        // from "Dancing Links", the only cover is rows 0, 3 and 4
+        let mut cover = ExactCover::new(7, 0);
+        let rows: [&[usize]; 6] = [
+            &[2, 4, 5],
+            &[0, 3, 6],
+            &[1, 2, 5],
+            &[0, 3],
+            &[1, 6],
+            &[3, 4, 6],
+        ];
+        for row in rows.iter() {
+            cover.add_row(row);
+        }
+        let mut solution = cover.first_solution().unwrap();
+        solution.sort_unstable();
+        assert_eq!(solution, vec![0, 3, 4]);
+        assert_eq!(cover.count_solutions(), 1);
+        // the links are all restored after a search
+        assert_eq!(cover.solutions().len(), 1);
+    }
+
+    #[test]
+    fn queens_with_secondary_diagonals() {
+        fn queens(n: usize) -> ExactCover {
+            // ranks and files must be covered, diagonals may be
+            let mut cover = ExactCover::new(2 * n, 2 * (2 * n - 1));
+            for r in 0..n {
+                for f in 0..n {
+                    let diagonal = 2 * n + r + f;
+                    let anti_diagonal = 2 * n + (2 * n - 1) + (n - 1 + r - f);
+                    cover.add_row(&[r, n + f, diagonal, anti_diagonal]);
+                }
+            }
+            cover
+        }
+        assert_eq!(queens(6).count_solutions(), 4);
+        assert_eq!(queens(8).count_solutions(), 92);
+        assert_eq!(queens(3).first_solution(), None);
+    }
+
+    #[test]
+    fn counted_columns() {
+        // choosing 2 of 4 rows that each cover the one column
+        let mut cover = ExactCover::new(1, 0);
+        for _ in 0..4 {
+            cover.add_row(&[0]);
+        }
+        cover.set_count(0, 2);
+        assert_eq!(cover.count_solutions(), 6);
+        assert_eq!(cover.first_solution(), Some(vec![0, 1]));
+
+        cover.set_count(0, 5);
+        assert_eq!(cover.first_solution(), None);
+    }
+
+    #[test]
+    fn tiling_a_grid() {
+        let domino = [(0, 0), (1, 0)];
+        assert_eq!(orientations(&domino).len(), 2);
+
+        let (mut cover, _) = GridCover::new(4, 3).pieces(&domino, 6).build();
+        // 11 domino tilings of a 4x3 rectangle
+        assert_eq!(cover.count_solutions(), 11);
+        // one too many can't fit, and one too few leaves a cell that must be covered
+        assert_eq!(
+            GridCover::new(4, 3)
+                .pieces(&domino, 7)
+                .build()
+                .0
+                .first_solution(),
+            None
+        );
+        assert_eq!(
+            GridCover::new(4, 3)
+                .pieces(&domino, 5)
+                .build()
+                .0
+                .first_solution(),
+            None
+        );
+
+        let l_tromino = [(0, 0), (0, 1), (1, 1)];
+        let grid = GridCover::new(3, 3)
+            .blocked(1, 1)
+            .with_optional_cells()
+            .pieces(&l_tromino, 2);
+        let (mut cover, placements) = grid.build();
+        let rows = cover.first_solution().unwrap();
+        let chosen: Vec<&Placement> = rows.iter().map(|&r| &placements[r]).collect();
+        let drawn = grid.render(&chosen);
+        assert_eq!(drawn.lines().count(), 3);
+        assert_eq!(drawn.matches('A').count(), 6);
+        assert_eq!(drawn.matches('#').count(), 1);
+    }
+}
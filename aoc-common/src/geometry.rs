@@ -190,18 +190,27 @@ where
 }
 
 pub fn output_map<K, V: MapFmt, T>(map: &HashMap<K, V>)
+where
+    T: num::Integer + Copy + std::iter::Step,
+    K: PointLike<T> + Eq + core::hash::Hash,
+{
+    println!("{}", format_map(map));
+}
+
+/// Draw a map as lines of text, as printed by `output_map`.
+pub fn format_map<K, V: MapFmt, T>(map: &HashMap<K, V>) -> String
 where
     T: num::Integer + Copy + std::iter::Step,
     K: PointLike<T> + Eq + core::hash::Hash,
 {
     let (x_min, x_max, y_min, y_max) = map_bounds(map);
 
-    for r in y_min..=y_max {
-        println!(
-            "{}",
+    (y_min..=y_max)
+        .map(|r| {
             (x_min..=x_max)
                 .map(|c| map.get(&K::from_xy(c, r)).map_or(' ', V::out))
                 .collect::<String>()
-        );
-    }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
@@ -0,0 +1,437 @@
+//! A directed graph over string labelled nodes, stored by index.
+//!
+//! Labels are interned as they're first seen, so the algorithms work on `NodeId`s and
+//! `label` turns them back into names for output.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Write};
+use std::iter::FromIterator;
+
+pub type NodeId = usize;
+
+#[derive(Clone, Debug)]
+pub struct Graph<W = ()> {
+    labels: Vec<String>,
+    index: HashMap<String, NodeId>,
+    edges: Vec<Vec<(NodeId, W)>>,
+}
+
+impl<W> Default for Graph<W> {
+    fn default() -> Self {
+        Graph {
+            labels: vec![],
+            index: HashMap::new(),
+            edges: vec![],
+        }
+    }
+}
+
+impl<W> Graph<W> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of the node with this label, adding it if it's new.
+    pub fn node(&mut self, label: &str) -> NodeId {
+        if let Some(&id) = self.index.get(label) {
+            return id;
+        }
+        let id = self.labels.len();
+        self.labels.push(label.to_string());
+        self.index.insert(label.to_string(), id);
+        self.edges.push(vec![]);
+        id
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str, weight: W) {
+        let from = self.node(from);
+        let to = self.node(to);
+        self.edges[from].push((to, weight));
+    }
+
+    pub fn id(&self, label: &str) -> Option<NodeId> {
+        self.index.get(label).copied()
+    }
+
+    pub fn label(&self, id: NodeId) -> &str {
+        &self.labels[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn nodes(&self) -> std::ops::Range<NodeId> {
+        0..self.len()
+    }
+
+    /// Outgoing edges from `id`, with their weights.
+    pub fn edges(&self, id: NodeId) -> impl Iterator<Item = (NodeId, &W)> {
+        self.edges[id].iter().map(|(to, w)| (*to, w))
+    }
+
+    pub fn successors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.edges[id].iter().map(|(to, _)| *to)
+    }
+
+    /// The same graph with every edge pointing the other way. Node ids are unchanged.
+    pub fn reversed(&self) -> Graph<W>
+    where
+        W: Clone,
+    {
+        let mut edges = vec![vec![]; self.len()];
+        for (from, out) in self.edges.iter().enumerate() {
+            for (to, w) in out {
+                edges[*to].push((from, w.clone()));
+            }
+        }
+        Graph {
+            labels: self.labels.clone(),
+            index: self.index.clone(),
+            edges,
+        }
+    }
+
+    /// Every node, ordered so that edges only point forwards. `None` if there's a cycle.
+    pub fn topo_order(&self) -> Option<Vec<NodeId>> {
+        self.topo_order_of(&vec![true; self.len()])
+    }
+
+    pub fn has_cycle(&self) -> bool {
+        self.topo_order().is_none()
+    }
+
+    /// Kahn's algorithm over the subgraph of nodes where `include` is set.
+    fn topo_order_of(&self, include: &[bool]) -> Option<Vec<NodeId>> {
+        let mut in_degree = vec![0; self.len()];
+        for from in self.nodes().filter(|&n| include[n]) {
+            for to in self.successors(from).filter(|&n| include[n]) {
+                in_degree[to] += 1;
+            }
+        }
+
+        let mut queue: VecDeque<NodeId> = self
+            .nodes()
+            .filter(|&n| include[n] && in_degree[n] == 0)
+            .collect();
+        let mut order = vec![];
+        while let Some(n) = queue.pop_front() {
+            order.push(n);
+            for to in self.successors(n).filter(|&n| include[n]) {
+                in_degree[to] -= 1;
+                if in_degree[to] == 0 {
+                    queue.push_back(to);
+                }
+            }
+        }
+
+        if order.len() == include.iter().filter(|i| **i).count() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    /// Strongly connected components, by Tarjan's algorithm.
+    /// Components come out in reverse topological order, sinks first.
+    pub fn sccs(&self) -> Vec<Vec<NodeId>> {
+        const UNSEEN: usize = usize::MAX;
+        let mut index = vec![UNSEEN; self.len()];
+        let mut low = vec![0; self.len()];
+        let mut on_stack = vec![false; self.len()];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut next_index = 0;
+
+        for root in self.nodes() {
+            if index[root] != UNSEEN {
+                continue;
+            }
+            // (node, next edge to look at), to save recursing
+            let mut work = vec![(root, 0)];
+            while let Some(&mut (n, ref mut edge)) = work.last_mut() {
+                if *edge == 0 && index[n] == UNSEEN {
+                    index[n] = next_index;
+                    low[n] = next_index;
+                    next_index += 1;
+                    stack.push(n);
+                    on_stack[n] = true;
+                }
+
+                if let Some(&(to, _)) = self.edges[n].get(*edge) {
+                    *edge += 1;
+                    if index[to] == UNSEEN {
+                        work.push((to, 0));
+                    } else if on_stack[to] {
+                        low[n] = low[n].min(index[to]);
+                    }
+                    continue;
+                }
+
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    low[parent] = low[parent].min(low[n]);
+                }
+                if low[n] == index[n] {
+                    let mut component = vec![];
+                    loop {
+                        let m = stack.pop().unwrap();
+                        on_stack[m] = false;
+                        component.push(m);
+                        if m == n {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    /// Every node that can be reached from `start`, including itself.
+    pub fn reachable_from(&self, start: NodeId) -> HashSet<NodeId> {
+        let mut seen = HashSet::new();
+        seen.insert(start);
+        let mut stack = vec![start];
+        while let Some(n) = stack.pop() {
+            for to in self.successors(n) {
+                if seen.insert(to) {
+                    stack.push(to);
+                }
+            }
+        }
+        seen
+    }
+
+    /// The number of distinct paths from `from` to `to`.
+    /// `None` if a cycle lies on one of those paths, as there are then infinitely many.
+    pub fn count_paths(&self, from: NodeId, to: NodeId) -> Option<u64> {
+        let forwards = self.reachable_from(from);
+        if !forwards.contains(&to) {
+            return Some(0);
+        }
+        // only the nodes between the two matter, cycles elsewhere are fine
+        let mut between = vec![false; self.len()];
+        let mut stack = vec![to];
+        between[to] = true;
+        let predecessors = self.predecessors();
+        while let Some(n) = stack.pop() {
+            for &p in &predecessors[n] {
+                if !between[p] && forwards.contains(&p) {
+                    between[p] = true;
+                    stack.push(p);
+                }
+            }
+        }
+
+        let order = self.topo_order_of(&between)?;
+        let mut counts = vec![0_u64; self.len()];
+        counts[from] = 1;
+        for n in order {
+            for s in self.successors(n).filter(|&s| between[s]) {
+                counts[s] += counts[n];
+            }
+        }
+        Some(counts[to])
+    }
+
+    /// The number of paths from `from` to `to` that pass through every one of `via`, in any order.
+    pub fn count_paths_via(&self, from: NodeId, to: NodeId, via: &[NodeId]) -> Option<u64> {
+        // Without cycles a node reaches strictly more than anything after it, so this puts the
+        // waypoints in the only order a path could visit them. If they can't all be on one path
+        // then one of the legs has no paths.
+        let mut waypoints: Vec<(usize, NodeId)> = via
+            .iter()
+            .map(|&v| (self.reachable_from(v).len(), v))
+            .collect();
+        waypoints.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut route = vec![from];
+        route.extend(waypoints.into_iter().map(|(_, v)| v));
+        route.push(to);
+        route
+            .windows(2)
+            .map(|leg| self.count_paths(leg[0], leg[1]))
+            .product()
+    }
+
+    fn predecessors(&self) -> Vec<Vec<NodeId>> {
+        let mut predecessors = vec![vec![]; self.len()];
+        for from in self.nodes() {
+            for to in self.successors(from) {
+                predecessors[to].push(from);
+            }
+        }
+        predecessors
+    }
+
+    /// Graphviz source for the graph, e.g. for `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        self.dot(|_| None)
+    }
+
+    /// Graphviz source with each edge labelled by its weight.
+    pub fn to_dot_weighted(&self) -> String
+    where
+        W: Display,
+    {
+        self.dot(|w| Some(w.to_string()))
+    }
+
+    fn dot(&self, edge_label: impl Fn(&W) -> Option<String>) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut out = "digraph {\n".to_string();
+        for n in self.nodes() {
+            if self.edges[n].is_empty() {
+                writeln!(out, "    {};", quote(self.label(n))).unwrap();
+            }
+            for (to, w) in self.edges(n) {
+                write!(
+                    out,
+                    "    {} -> {}",
+                    quote(self.label(n)),
+                    quote(self.label(to))
+                )
+                .unwrap();
+                if let Some(label) = edge_label(w) {
+                    write!(out, " [label={}]", quote(&label)).unwrap();
+                }
+                out.push_str(";\n");
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl<'a> FromIterator<(&'a str, &'a str)> for Graph {
+    fn from_iter<T: IntoIterator<Item = (&'a str, &'a str)>>(iter: T) -> Self {
+        let mut graph = Graph::new();
+        for (from, to) in iter {
+            graph.add_edge(from, to, ());
+        }
+        graph
+    }
+}
+
+impl<'a, W> FromIterator<(&'a str, &'a str, W)> for Graph<W> {
+    fn from_iter<T: IntoIterator<Item = (&'a str, &'a str, W)>>(iter: T) -> Self {
+        let mut graph = Graph::new();
+        for (from, to, w) in iter {
+            graph.add_edge(from, to, w);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diamond() -> Graph {
+        vec![
+            ("a", "b"),
+            ("a", "c"),
+            ("b", "d"),
+            ("c", "d"),
+            ("d", "e"),
+            ("b", "e"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn ids(g: &Graph, labels: &str) -> Vec<NodeId> {
+        labels
+            .chars()
+            .map(|c| g.id(&c.to_string()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn topo_order_and_cycles() {
+        let mut g = diamond();
+        let order = g.topo_order().unwrap();
+        let position = |n| order.iter().position(|&m| m == n).unwrap();
+        for n in g.nodes() {
+            assert!(g.successors(n).all(|s| position(n) < position(s)));
+        }
+        assert!(!g.has_cycle());
+
+        g.add_edge("e", "b", ());
+        assert!(g.has_cycle());
+        assert_eq!(g.topo_order(), None);
+    }
+
+    #[test]
+    fn strongly_connected() {
+        let g: Graph = vec![
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "a"),
+            ("c", "d"),
+            ("d", "e"),
+            ("e", "d"),
+        ]
+        .into_iter()
+        .collect();
+        let mut sccs: Vec<Vec<NodeId>> = g
+            .sccs()
+            .into_iter()
+            .map(|mut c| {
+                c.sort_unstable();
+                c
+            })
+            .collect();
+        // sinks come first
+        assert_eq!(sccs[0], ids(&g, "de"));
+        sccs.sort();
+        assert_eq!(sccs, vec![ids(&g, "abc"), ids(&g, "de")]);
+    }
+
+    #[test]
+    fn reverse_and_reach() {
+        let g = diamond();
+        let d = g.id("d").unwrap();
+        let mut down: Vec<_> = g.reachable_from(d).into_iter().collect();
+        down.sort_unstable();
+        assert_eq!(down, ids(&g, "de"));
+
+        let mut up: Vec<_> = g.reversed().reachable_from(d).into_iter().collect();
+        up.sort_unstable();
+        assert_eq!(up, ids(&g, "abcd"));
+    }
+
+    #[test]
+    fn path_counting() {
+        let mut g = diamond();
+        let n = ids(&g, "abcde");
+        let (a, b, c, d, e) = (n[0], n[1], n[2], n[3], n[4]);
+        assert_eq!(g.count_paths(a, e), Some(3));
+        assert_eq!(g.count_paths(e, a), Some(0));
+        assert_eq!(g.count_paths(a, a), Some(1));
+        assert_eq!(g.count_paths_via(a, e, &[d]), Some(2));
+        assert_eq!(g.count_paths_via(a, e, &[d, b]), Some(1));
+        assert_eq!(g.count_paths_via(a, e, &[b, c]), Some(0));
+
+        // a cycle away from the paths doesn't matter, one on them does
+        g.add_edge("e", "f", ());
+        g.add_edge("f", "e", ());
+        assert_eq!(g.count_paths(a, d), Some(2));
+        assert_eq!(g.count_paths(a, e), None);
+    }
+
+    #[test]
+    fn dot_export() {
+        let g: Graph<u32> = vec![("a", "b", 2), ("x\"y", "a", 1)].into_iter().collect();
+        assert_eq!(
+            g.to_dot_weighted(),
+            "digraph {\n    \"a\" -> \"b\" [label=\"2\"];\n    \"b\";\n    \"x\\\"y\" -> \"a\" [label=\"1\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Sets of integers kept as sorted ranges, and sets of points kept as disjoint boxes.
+//!
+//! Ranges are half open, `start..end`, like the standard library's. Inclusive ones such as
+//! `"3-7"` in puzzle inputs become `3..8` when parsed.
+
+use num::PrimInt;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// A set of integers as the fewest sorted, non-overlapping, non-touching ranges.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RangeSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T: PrimInt> RangeSet<T> {
+    pub fn new() -> Self {
+        RangeSet { ranges: vec![] }
+    }
+
+    /// Add every number in `range`, merging with whatever it overlaps or touches.
+    pub fn insert(&mut self, range: Range<T>) {
+        if range.start >= range.end {
+            return;
+        }
+        let i = self.ranges.partition_point(|r| r.end < range.start);
+        let j = self.ranges.partition_point(|r| r.start <= range.end);
+        let merged = if i < j {
+            range.start.min(self.ranges[i].start)..range.end.max(self.ranges[j - 1].end)
+        } else {
+            range
+        };
+        self.ranges.splice(i..j, std::iter::once(merged));
+    }
+
+    /// Take out every number in `range`, splitting any range it lands in the middle of.
+    pub fn remove(&mut self, range: Range<T>) {
+        if range.start >= range.end {
+            return;
+        }
+        let i = self.ranges.partition_point(|r| r.end <= range.start);
+        let j = self.ranges.partition_point(|r| r.start < range.end);
+        if i >= j {
+            return;
+        }
+        let mut pieces = vec![];
+        if self.ranges[i].start < range.start {
+            pieces.push(self.ranges[i].start..range.start);
+        }
+        if self.ranges[j - 1].end > range.end {
+            pieces.push(range.end..self.ranges[j - 1].end);
+        }
+        self.ranges.splice(i..j, pieces);
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        let i = self.ranges.partition_point(|r| r.end <= x);
+        self.ranges.get(i).is_some_and(|r| r.start <= x)
+    }
+
+    /// Whether every number in `range` is in the set.
+    pub fn contains_range(&self, range: &Range<T>) -> bool {
+        if range.start >= range.end {
+            return true;
+        }
+        let i = self.ranges.partition_point(|r| r.end <= range.start);
+        self.ranges
+            .get(i)
+            .is_some_and(|r| r.start <= range.start && range.end <= r.end)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        for r in other.ranges() {
+            union.insert(r.clone());
+        }
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let (start, end) = (a.start.max(b.start), a.end.min(b.end));
+            if start < end {
+                ranges.push(start..end);
+            }
+            // whichever finishes first can't overlap anything further on
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        RangeSet { ranges }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut difference = self.clone();
+        for r in other.ranges() {
+            difference.remove(r.clone());
+        }
+        difference
+    }
+
+    /// How many numbers are in the set.
+    pub fn len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::zero(), |total, r| total + (r.end - r.start))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The ranges in ascending order.
+    pub fn ranges(&self) -> impl Iterator<Item = &Range<T>> + '_ {
+        self.ranges.iter()
+    }
+
+    /// From the lowest number in the set to just past the highest.
+    pub fn span(&self) -> Option<Range<T>> {
+        Some(self.ranges.first()?.start..self.ranges.last()?.end)
+    }
+
+    /// The ranges missing between the lowest and highest numbers in the set.
+    pub fn gaps(&self) -> impl Iterator<Item = Range<T>> + '_ {
+        self.ranges.windows(2).map(|w| w[0].end..w[1].start)
+    }
+}
+
+impl<T: PrimInt> Default for RangeSet<T> {
+    fn default() -> Self {
+        RangeSet::new()
+    }
+}
+
+impl<T: PrimInt> From<Range<T>> for RangeSet<T> {
+    fn from(range: Range<T>) -> Self {
+        let mut set = RangeSet::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl<T: PrimInt> std::iter::FromIterator<Range<T>> for RangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = RangeSet::new();
+        for r in iter {
+            set.insert(r);
+        }
+        set
+    }
+}
+
+impl<T: fmt::Display> fmt::Debug for RangeSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set()
+            .entries(
+                self.ranges
+                    .iter()
+                    .map(|r| format!("{}..{}", r.start, r.end)),
+            )
+            .finish()
+    }
+}
+
+/// An inclusive range written `"a-b"`, as the half open `a..b + 1`. `None` if `b` comes
+/// before `a`, or `b + 1` doesn't fit in `T`.
+pub fn parse_inclusive<T: PrimInt + FromStr>(s: &str) -> Option<Range<T>> {
+    let s = s.trim();
+    // skip the first character so a negative start isn't taken for the separator
+    let split = s.char_indices().skip(1).find(|&(_, c)| c == '-')?.0;
+    let start: T = s[..split].trim().parse().ok()?;
+    let end: T = s[split + 1..].trim().parse().ok()?;
+    if end < start {
+        return None;
+    }
+    Some(start..end.checked_add(&T::one())?)
+}
+
+/// The input couldn't be read as ranges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRangeError(pub String);
+
+impl fmt::Display for ParseRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not a range: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRangeError {}
+
+/// One or more inclusive `"a-b"` ranges, separated by commas or `" or "`.
+impl<T: PrimInt + FromStr> FromStr for RangeSet<T> {
+    type Err = ParseRangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(" or ")
+            .flat_map(|part| part.split(','))
+            .map(|part| parse_inclusive(part).ok_or_else(|| ParseRangeError(part.to_owned())))
+            .collect()
+    }
+}
+
+/// The points `min[d] <= p[d] < max[d]` on every axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cuboid<T, const D: usize> {
+    pub min: [T; D],
+    pub max: [T; D],
+}
+
+impl<T: PrimInt, const D: usize> Cuboid<T, D> {
+    pub fn new(min: [T; D], max: [T; D]) -> Self {
+        Cuboid { min, max }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..D).any(|d| self.min[d] >= self.max[d])
+    }
+
+    pub fn volume(&self) -> T {
+        if self.is_empty() {
+            return T::zero();
+        }
+        (0..D).fold(T::one(), |v, d| v * (self.max[d] - self.min[d]))
+    }
+
+    pub fn contains(&self, p: &[T; D]) -> bool {
+        (0..D).all(|d| self.min[d] <= p[d] && p[d] < self.max[d])
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let mut overlap = *self;
+        for d in 0..D {
+            overlap.min[d] = self.min[d].max(other.min[d]);
+            overlap.max[d] = self.max[d].min(other.max[d]);
+        }
+        if overlap.is_empty() {
+            None
+        } else {
+            Some(overlap)
+        }
+    }
+
+    /// What's left of this after cutting out `other`, as at most `2 * D` disjoint pieces.
+    pub fn difference(&self, other: &Self) -> Vec<Self> {
+        let overlap = match self.intersection(other) {
+            Some(overlap) => overlap,
+            None => return vec![*self],
+        };
+        // slice off the parts below and above the overlap one axis at a time
+        let mut pieces = vec![];
+        let mut rest = *self;
+        for d in 0..D {
+            if rest.min[d] < overlap.min[d] {
+                let mut below = rest;
+                below.max[d] = overlap.min[d];
+                pieces.push(below);
+            }
+            if overlap.max[d] < rest.max[d] {
+                let mut above = rest;
+                above.min[d] = overlap.max[d];
+                pieces.push(above);
+            }
+            rest.min[d] = overlap.min[d];
+            rest.max[d] = overlap.max[d];
+        }
+        pieces
+    }
+}
+
+/// A set of points as disjoint cuboids.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BoxSet<T, const D: usize> {
+    boxes: Vec<Cuboid<T, D>>,
+}
+
+impl<T: PrimInt, const D: usize> BoxSet<T, D> {
+    pub fn new() -> Self {
+        BoxSet { boxes: vec![] }
+    }
+
+    pub fn insert(&mut self, cuboid: Cuboid<T, D>) {
+        self.remove(cuboid);
+        if !cuboid.is_empty() {
+            self.boxes.push(cuboid);
+        }
+    }
+
+    pub fn remove(&mut self, cuboid: Cuboid<T, D>) {
+        self.boxes = self
+            .boxes
+            .iter()
+            .flat_map(|b| b.difference(&cuboid))
+            .collect();
+    }
+
+    pub fn contains(&self, p: &[T; D]) -> bool {
+        self.boxes.iter().any(|b| b.contains(p))
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        for b in other.boxes() {
+            union.insert(*b);
+        }
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let boxes = self
+            .boxes
+            .iter()
+            .flat_map(|a| other.boxes.iter().filter_map(move |b| a.intersection(b)))
+            .collect();
+        BoxSet { boxes }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut difference = self.clone();
+        for b in other.boxes() {
+            difference.remove(*b);
+        }
+        difference
+    }
+
+    /// How many points are in the set.
+    pub fn volume(&self) -> T {
+        self.boxes.iter().fold(T::zero(), |v, b| v + b.volume())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// The disjoint cuboids making up the set, in no particular order.
+    pub fn boxes(&self) -> impl Iterator<Item = &Cuboid<T, D>> + '_ {
+        self.boxes.iter()
+    }
+}
+
+impl<T: PrimInt, const D: usize> Default for BoxSet<T, D> {
+    fn default() -> Self {
+        BoxSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_and_splitting() {
+        let mut set: RangeSet<i64> = vec![1..3, 10..12, 5..7].into_iter().collect();
+        assert_eq!(set.ranges().count(), 3);
+        set.insert(3..5);
+        assert_eq!(
+            set.ranges().cloned().collect::<Vec<_>>(),
+            vec![1..7, 10..12]
+        );
+        set.insert(0..20);
+        assert_eq!(set.ranges().cloned().collect::<Vec<_>>(), vec![0..20]);
+
+        set.remove(5..8);
+        set.remove(19..30);
+        assert_eq!(set.ranges().cloned().collect::<Vec<_>>(), vec![0..5, 8..19]);
+        assert!(set.contains(4) && !set.contains(5) && set.contains(8) && !set.contains(19));
+        assert!(set.contains_range(&(8..19)) && !set.contains_range(&(4..9)));
+        assert_eq!(set.len(), 16);
+        assert_eq!(set.span(), Some(0..19));
+        assert_eq!(set.gaps().collect::<Vec<_>>(), vec![5..8]);
+    }
+
+    #[test]
+    fn set_operations() {
+        let a: RangeSet<u32> = "1-5, 10-15".parse().unwrap();
+        let b: RangeSet<u32> = "4-11 or 20-20".parse().unwrap();
+        assert_eq!(format!("{:?}", a), "{\"1..6\", \"10..16\"}");
+        assert_eq!(a.union(&b).ranges().count(), 2);
+        assert_eq!(a.union(&b).len(), 16);
+        assert_eq!(
+            a.intersection(&b).ranges().cloned().collect::<Vec<_>>(),
+            vec![4..6, 10..12]
+        );
+        assert_eq!(
+            a.difference(&b).ranges().cloned().collect::<Vec<_>>(),
+            vec![1..4, 12..16]
+        );
+        assert_eq!(parse_inclusive::<i32>("-5--2"), Some(-5..-1));
+        assert_eq!(parse_inclusive::<u8>("3-3"), Some(3..4));
+        assert_eq!(parse_inclusive::<u8>("5-3"), None);
+        assert_eq!(parse_inclusive::<u8>("0-255"), None);
+        assert!("1-x".parse::<RangeSet<u32>>().is_err());
+    }
+
+    #[test]
+    fn cuboids() {
+        // switching cubes on and off, as in 2021 day 22's reactor
+        let cube = |min: i64, max: i64| Cuboid::new([min; 3], [max + 1; 3]);
+        let mut reactor = BoxSet::new();
+        reactor.insert(cube(10, 12));
+        reactor.insert(cube(11, 13));
+        reactor.remove(cube(9, 11));
+        reactor.insert(cube(10, 10));
+        assert_eq!(reactor.volume(), 39);
+        assert!(reactor.contains(&[10, 10, 10]) && !reactor.contains(&[11, 11, 11]));
+
+        let mut a = BoxSet::new();
+        a.insert(Cuboid::new([0, 0], [4, 4]));
+        let mut b = BoxSet::new();
+        b.insert(Cuboid::new([2, 2], [6, 6]));
+        assert_eq!(a.union(&b).volume(), 28);
+        assert_eq!(a.intersection(&b).volume(), 4);
+        assert_eq!(a.difference(&b).volume(), 12);
+        assert_eq!(
+            Cuboid::new([0, 0], [4, 4])
+                .difference(&Cuboid::new([1, 1], [2, 2]))
+                .len(),
+            4
+        );
+    }
+}
@@ -1,9 +1,21 @@
 #![feature(step_trait)]
 
+pub mod automaton;
 pub mod bitset;
+pub mod cycle;
+pub mod distances;
+pub mod dsu;
+pub mod exact_cover;
 pub mod geometry;
+pub mod graph;
+pub mod intervals;
+pub mod linalg;
+pub mod memo;
+pub mod numtheory;
+pub mod polygon;
 pub mod search;
 mod time;
 pub use time::*;
 pub mod aoc_day;
 pub mod files;
+pub mod viz;
@@ -0,0 +1,347 @@
+//! Exact linear algebra over the rationals, and small integer programs built on it.
+
+use num::rational::Ratio;
+use num::Zero;
+
+pub type Rational = Ratio<i128>;
+
+/// The reduced row echelon form of an augmented system `A x = b`.
+#[derive(Clone, Debug)]
+pub struct Rref {
+    /// One row per equation, `A` then `b` in the last column. Zero rows come last.
+    pub rows: Vec<Vec<Rational>>,
+    /// The column of each leading one, for the first `rank` rows.
+    pub pivots: Vec<usize>,
+    /// Columns without a pivot, which may take any value.
+    pub free: Vec<usize>,
+    variables: usize,
+}
+
+/// Gauss–Jordan elimination of `A x = b`, with exact arithmetic.
+pub fn rref(a: &[Vec<i64>], b: &[i64]) -> Rref {
+    let variables = a.first().map_or(0, |row| row.len());
+    let mut rows: Vec<Vec<Rational>> = a
+        .iter()
+        .zip(b.iter())
+        .map(|(row, &b)| {
+            row.iter()
+                .chain(std::iter::once(&b))
+                .map(|&x| Rational::from_integer(x as i128))
+                .collect()
+        })
+        .collect();
+
+    let mut pivots = vec![];
+    let mut free = vec![];
+    for col in 0..variables {
+        let r = pivots.len();
+        let found = (r..rows.len()).find(|&i| !rows[i][col].is_zero());
+        let p = match found {
+            Some(p) => p,
+            None => {
+                free.push(col);
+                continue;
+            }
+        };
+        rows.swap(r, p);
+        let lead = rows[r][col];
+        for x in rows[r].iter_mut() {
+            *x /= lead;
+        }
+        let pivot_row = rows[r].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i != r && !row[col].is_zero() {
+                let factor = row[col];
+                for (x, p) in row.iter_mut().zip(pivot_row.iter()) {
+                    *x -= factor * p;
+                }
+            }
+        }
+        pivots.push(col);
+    }
+
+    Rref {
+        rows,
+        pivots,
+        free,
+        variables,
+    }
+}
+
+impl Rref {
+    pub fn rank(&self) -> usize {
+        self.pivots.len()
+    }
+
+    /// False if elimination left a row saying `0 = c` for some non-zero `c`.
+    pub fn is_consistent(&self) -> bool {
+        self.rows[self.rank()..]
+            .iter()
+            .all(|row| row[self.variables].is_zero())
+    }
+
+    /// The solution with the free variables given these values, in the order of `free`.
+    pub fn solve_with(&self, free_values: &[Rational]) -> Vec<Rational> {
+        let mut x = vec![Rational::zero(); self.variables];
+        for (&f, &v) in self.free.iter().zip(free_values) {
+            x[f] = v;
+        }
+        for (row, &p) in self.rows.iter().zip(self.pivots.iter()) {
+            x[p] = self.pivot_value(row, &x);
+        }
+        x
+    }
+
+    /// The unique solution, if there is one.
+    pub fn solution(&self) -> Option<Vec<Rational>> {
+        if self.is_consistent() && self.free.is_empty() {
+            Some(self.solve_with(&[]))
+        } else {
+            None
+        }
+    }
+
+    fn pivot_value(&self, row: &[Rational], x: &[Rational]) -> Rational {
+        self.free
+            .iter()
+            .fold(row[self.variables], |acc, &f| acc - row[f] * x[f])
+    }
+}
+
+/// Minimise `cost · x` over integers `0 <= x <= upper` with `A x = b`.
+/// Costs must not be negative. Branches over the free variables of the row reduced system,
+/// narrowing each one's range to what keeps every pivot variable within its bounds, and
+/// cutting off branches that can't beat the best found so far.
+pub fn minimise_integer(
+    a: &[Vec<i64>],
+    b: &[i64],
+    upper: &[i64],
+    cost: &[i64],
+) -> Option<(i64, Vec<i64>)> {
+    let system = rref(a, b);
+    if !system.is_consistent() {
+        return None;
+    }
+    let mut search = Search::new(&system, upper, cost);
+    let mut partial: Vec<i64> = search.rows.iter().map(|r| r.rhs).collect();
+    search.branch(0, &mut partial, search.objective.0);
+
+    let (total, free_values) = search.best?;
+    let mut x = vec![0; upper.len()];
+    for (&f, &v) in system.free.iter().zip(free_values.iter()) {
+        x[f] = v;
+    }
+    for row in search.rows.iter() {
+        let sum: i64 = row
+            .coefs
+            .iter()
+            .zip(free_values.iter())
+            .map(|(c, v)| c * v)
+            .sum();
+        x[row.pivot] = (row.rhs - sum) / row.scale;
+    }
+    Some((total, x))
+}
+
+/// A pivot row scaled up to integers: `scale * x[pivot] = rhs - coefs · free values`.
+struct Row {
+    pivot: usize,
+    scale: i64,
+    rhs: i64,
+    coefs: Vec<i64>,
+    /// The most `scale * x[pivot]` may be.
+    limit: i64,
+    /// The range of `coefs · free values` over the free variables from each depth on.
+    rest: Vec<(i64, i64)>,
+}
+
+struct Search {
+    rows: Vec<Row>,
+    upper: Vec<i64>,
+    /// The objective times `objective_scale`, as a constant plus a cost per free variable.
+    objective: (i64, Vec<i64>),
+    objective_scale: i64,
+    /// The lowest the objective can go from the free variables from each depth on.
+    objective_rest: Vec<i64>,
+    values: Vec<i64>,
+    best: Option<(i64, Vec<i64>)>,
+}
+
+impl Search {
+    fn new(system: &Rref, upper: &[i64], cost: &[i64]) -> Self {
+        let free_upper: Vec<i64> = system.free.iter().map(|&f| upper[f]).collect();
+        let n = system.free.len();
+
+        let rows: Vec<Row> = system
+            .rows
+            .iter()
+            .zip(system.pivots.iter())
+            .map(|(row, &pivot)| {
+                let scale = row.iter().map(|x| *x.denom()).fold(1, num::integer::lcm);
+                let to_int = |x: &Rational| (x * scale).to_integer() as i64;
+                let coefs: Vec<i64> = system.free.iter().map(|&f| to_int(&row[f])).collect();
+                let mut rest = vec![(0, 0); n + 1];
+                for k in (0..n).rev() {
+                    let swing = coefs[k] * free_upper[k];
+                    rest[k] = (rest[k + 1].0 + swing.min(0), rest[k + 1].1 + swing.max(0));
+                }
+                Row {
+                    pivot,
+                    scale: scale as i64,
+                    rhs: to_int(&row[system.variables]),
+                    coefs,
+                    limit: scale as i64 * upper[pivot],
+                    rest,
+                }
+            })
+            .collect();
+
+        // substitute each pivot into the cost so it's in terms of the free variables alone
+        let objective_scale = rows.iter().map(|r| r.scale).fold(1, num::integer::lcm);
+        let mut constant = 0;
+        let mut per_free: Vec<i64> = system
+            .free
+            .iter()
+            .map(|&f| cost[f] * objective_scale)
+            .collect();
+        for row in rows.iter() {
+            let weight = cost[row.pivot] * (objective_scale / row.scale);
+            constant += weight * row.rhs;
+            for (c, k) in per_free.iter_mut().zip(row.coefs.iter()) {
+                *c -= weight * k;
+            }
+        }
+        let mut objective_rest = vec![0; n + 1];
+        for k in (0..n).rev() {
+            objective_rest[k] = objective_rest[k + 1] + (per_free[k] * free_upper[k]).min(0);
+        }
+
+        Search {
+            rows,
+            upper: free_upper,
+            objective: (constant, per_free),
+            objective_scale,
+            objective_rest,
+            values: vec![0; n],
+            best: None,
+        }
+    }
+
+    /// `partial` is each row's `rhs` less the free variables already chosen, and `objective`
+    /// the scaled objective so far.
+    fn branch(&mut self, depth: usize, partial: &mut [i64], objective: i64) {
+        if depth == self.values.len() {
+            self.leaf(partial, objective);
+            return;
+        }
+
+        // the values of this variable that leave every pivot a chance of being in bounds
+        let (mut low, mut high) = (0, self.upper[depth]);
+        for (row, &p) in self.rows.iter().zip(partial.iter()) {
+            let c = row.coefs[depth];
+            let (rest_low, rest_high) = row.rest[depth + 1];
+            // need 0 <= p - c * v - rest <= limit for some rest in its range
+            let (most, least) = (p - rest_low, p - rest_high - row.limit);
+            if c == 0 {
+                if most < 0 || least > 0 {
+                    return;
+                }
+            } else if c > 0 {
+                high = high.min(most.div_euclid(c));
+                low = low.max(-((-least).div_euclid(c)));
+            } else {
+                high = high.min(least.div_euclid(c));
+                low = low.max(-((-most).div_euclid(c)));
+            }
+        }
+
+        let step = self.objective.1[depth];
+        for v in low..=high {
+            let bound = objective + step * v + self.objective_rest[depth + 1];
+            if self.cannot_beat(bound) {
+                if step >= 0 {
+                    break;
+                }
+                continue;
+            }
+            self.values[depth] = v;
+            for (row, p) in self.rows.iter().zip(partial.iter_mut()) {
+                *p -= row.coefs[depth] * v;
+            }
+            self.branch(depth + 1, partial, objective + step * v);
+            for (row, p) in self.rows.iter().zip(partial.iter_mut()) {
+                *p += row.coefs[depth] * v;
+            }
+        }
+    }
+
+    fn cannot_beat(&self, scaled_bound: i64) -> bool {
+        let bound = -((-scaled_bound).div_euclid(self.objective_scale));
+        self.best.as_ref().is_some_and(|(best, _)| bound >= *best)
+    }
+
+    fn leaf(&mut self, partial: &[i64], objective: i64) {
+        let integral = self
+            .rows
+            .iter()
+            .zip(partial.iter())
+            .all(|(row, p)| p % row.scale == 0 && (0..=row.limit).contains(p));
+        if integral && !self.cannot_beat(objective) {
+            self.best = Some((objective / self.objective_scale, self.values.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(n: i128, d: i128) -> Rational {
+        Rational::new(n, d)
+    }
+
+    #[test]
+    fn unique_solution() {
+        // 2x + y - z = 8, -3x - y + 2z = -11, -2x + y + 2z = -3
+        let a = vec![vec![2, 1, -1], vec![-3, -1, 2], vec![-2, 1, 2]];
+        let system = rref(&a, &[8, -11, -3]);
+        assert_eq!(system.rank(), 3);
+        assert_eq!(system.solution(), Some(vec![r(2, 1), r(3, 1), r(-1, 1)]));
+    }
+
+    #[test]
+    fn fractions_and_free_variables() {
+        // 2x + 4y + z = 3, and the same again
+        let a = vec![vec![2, 4, 1], vec![2, 4, 1]];
+        let system = rref(&a, &[3, 3]);
+        assert_eq!(system.rank(), 1);
+        assert_eq!(system.free, vec![1, 2]);
+        assert!(system.is_consistent());
+        assert_eq!(system.solution(), None);
+        assert_eq!(
+            system.solve_with(&[r(0, 1), r(0, 1)]),
+            vec![r(3, 2), r(0, 1), r(0, 1)]
+        );
+
+        assert!(!rref(&a, &[3, 4]).is_consistent());
+    }
+
+    #[test]
+    fn integer_minimum() {
+        // the first machine from 2025 day 10, six buttons over four counters
+        let a = vec![
+            vec![0, 0, 0, 0, 1, 1],
+            vec![0, 1, 0, 0, 0, 1],
+            vec![0, 0, 1, 1, 1, 0],
+            vec![1, 1, 0, 1, 0, 0],
+        ];
+        let (presses, x) = minimise_integer(&a, &[3, 5, 4, 7], &[7; 6], &[1; 6]).unwrap();
+        assert_eq!(presses, 10);
+        for (row, b) in a.iter().zip(vec![3, 5, 4, 7]) {
+            assert_eq!(row.iter().zip(x.iter()).map(|(a, x)| a * x).sum::<i64>(), b);
+        }
+
+        // 2x = 3 has rational but no integer solutions
+        assert_eq!(minimise_integer(&[vec![2]], &[3], &[10], &[1]), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! Caching the results of recursive functions, for top-down dynamic programming.
+//!
+//! A recursive function takes the memo as an argument, wraps its body in
+//! [`Memo::get_or_insert_with`], and makes its recursive calls with the memo it's handed.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// Computed values by key, and how often they were looked up.
+#[derive(Clone, Debug)]
+pub struct Memo<K, V> {
+    cache: HashMap<K, V>,
+    hits: usize,
+    misses: usize,
+}
+
+/// How well a [`Memo`] has been doing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+impl MemoStats {
+    /// The fraction of lookups that were already cached.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+impl fmt::Display for MemoStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses ({:.1}% hit rate), {} entries",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0,
+            self.entries
+        )
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        Memo {
+            cache: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The value cached for `key`, otherwise `compute` it, cache it and return it.
+    /// `compute` is handed the memo back for its recursive calls.
+    /// The key can be borrowed, so a `Memo<String, _>` can be looked up with a `&str`.
+    pub fn get_or_insert_with<Q>(&mut self, key: &Q, compute: impl FnOnce(&mut Self) -> V) -> V
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        if let Some(value) = self.cache.get(key) {
+            self.hits += 1;
+            return value.clone();
+        }
+        self.misses += 1;
+        let value = compute(self);
+        self.cache.insert(key.to_owned(), value.clone());
+        value
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.cache.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Forget everything, ready for another input.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        MemoStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.cache.len(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Default for Memo<K, V> {
+    fn default() -> Self {
+        Memo::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fib(n: u64, memo: &mut Memo<u64, u64>) -> u64 {
+        memo.get_or_insert_with(&n, |memo| {
+            if n < 2 {
+                n
+            } else {
+                fib(n - 1, memo) + fib(n - 2, memo)
+            }
+        })
+    }
+
+    /// The ways to make `target` from `towels` one after another.
+    fn arrangements(target: &str, towels: &[&str], memo: &mut Memo<String, u64>) -> u64 {
+        memo.get_or_insert_with(target, |memo| {
+            if target.is_empty() {
+                return 1;
+            }
+            towels
+                .iter()
+                .filter_map(|t| target.strip_prefix(t))
+                .map(|rest| arrangements(rest, towels, memo))
+                .sum()
+        })
+    }
+
+    #[test]
+    fn recursion() {
+        let mut memo = Memo::new();
+        assert_eq!(fib(90, &mut memo), 2880067194370816120);
+        assert_eq!(memo.stats().misses, 91);
+        assert_eq!(memo.stats().hits, 88);
+    }
+
+    #[test]
+    fn borrowed_keys_and_stats() {
+        let towels = ["r", "wr", "b", "g", "bwu", "rb", "gb", "br"];
+        let mut memo = Memo::new();
+        assert_eq!(arrangements("rrbgbr", &towels, &mut memo), 6);
+        let stats = memo.stats();
+        assert_eq!(stats.misses, stats.entries);
+        assert!(stats.hits > 0);
+        assert_eq!(memo.get("br"), Some(&2));
+
+        memo.clear();
+        assert!(memo.is_empty());
+        assert_eq!(memo.stats(), MemoStats::default());
+        assert_eq!(arrangements("bbrgwb", &towels, &mut memo), 0);
+        assert_eq!(memo.stats().to_string().split(',').count(), 3);
+    }
+}
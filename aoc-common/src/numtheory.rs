@@ -0,0 +1,236 @@
+//! Number theory: gcd and lcm, the Chinese remainder theorem, modular arithmetic and primes.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Greatest common divisor, never negative.
+pub fn gcd<T: num::Integer + Copy>(a: T, b: T) -> T {
+    a.gcd(&b)
+}
+
+/// Lowest common multiple, never negative.
+pub fn lcm<T: num::Integer + Copy>(a: T, b: T) -> T {
+    a.lcm(&b)
+}
+
+/// The lowest common multiple of everything, 1 if there's nothing.
+pub fn lcm_all<T: num::Integer + Copy>(values: impl IntoIterator<Item = T>) -> T {
+    values.into_iter().fold(T::one(), lcm)
+}
+
+/// Returns `(g, x, y)` where `g = gcd(a, b) = a * x + b * y`.
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_x, x) = (x, old_x - q * x);
+        (old_y, y) = (y, old_y - q * y);
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+/// `x` in `0..m` with `a * x ≡ 1 (mod m)`, if `a` and `m` are coprime.
+pub fn mod_inverse(a: i64, m: i64) -> Option<i64> {
+    let (g, x, _) = extended_gcd(a.rem_euclid(m), m);
+    if g == 1 {
+        Some(x.rem_euclid(m))
+    } else {
+        None
+    }
+}
+
+/// `a * b % m` without overflowing.
+pub fn mulmod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+/// `base ^ exp % m` by repeated squaring.
+pub fn powmod(base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    let mut base = base % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mulmod(result, base, m);
+        }
+        base = mulmod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Solve `x ≡ r (mod m)` for every `(r, m)`, returning `(x, lcm of the moduli)` with `x` the
+/// smallest non-negative solution. The moduli needn't be coprime; `None` if they conflict, if
+/// one isn't positive, or if their lcm doesn't fit in an `i64`.
+pub fn crt(congruences: impl IntoIterator<Item = (i64, i64)>) -> Option<(i64, i64)> {
+    let mut x: i128 = 0;
+    let mut modulus: i128 = 1;
+    for (r, m) in congruences {
+        if m <= 0 {
+            return None;
+        }
+        let (g, p, _) = extended_gcd(i64::try_from(modulus).ok()?, m);
+        let (r, m) = (r as i128, m as i128);
+        let g = g as i128;
+        let diff = r - x;
+        if diff % g != 0 {
+            return None;
+        }
+        // step x along by multiples of the current modulus until it also fits this congruence
+        let step = m / g;
+        let k = (diff / g % step * p as i128).rem_euclid(step);
+        x += modulus * k;
+        modulus *= step;
+        x = x.rem_euclid(modulus);
+    }
+    Some((x as i64, i64::try_from(modulus).ok()?))
+}
+
+/// The smallest `x` with `base ^ x ≡ target (mod m)`, by baby-step giant-step.
+/// `base` must be coprime to `m`, otherwise this gives `None`.
+pub fn discrete_log(base: u64, target: u64, m: u64) -> Option<u64> {
+    if m == 1 {
+        return Some(0);
+    }
+    let n = (m as f64).sqrt().ceil() as u64;
+
+    // the baby steps, base^j for j < n, keeping the smallest j for each value
+    let mut baby_steps = HashMap::with_capacity(n as usize);
+    let mut value = 1;
+    for j in 0..n {
+        baby_steps.entry(value).or_insert(j);
+        value = mulmod(value, base, m);
+    }
+
+    let inverse = mod_inverse(base as i64, m as i64)? as u64;
+    let giant_step = powmod(inverse, n, m);
+    let mut gamma = target % m;
+    for i in 0..n {
+        if let Some(j) = baby_steps.get(&gamma) {
+            return Some(i * n + j);
+        }
+        gamma = mulmod(gamma, giant_step, m);
+    }
+    None
+}
+
+/// Every prime up to and including `limit`, by the sieve of Eratosthenes.
+pub fn sieve(limit: usize) -> Vec<usize> {
+    let mut composite = vec![false; limit + 1];
+    let mut primes = vec![];
+    for n in 2..=limit {
+        if composite[n] {
+            continue;
+        }
+        primes.push(n);
+        for multiple in (n * n..=limit).step_by(n) {
+            composite[multiple] = true;
+        }
+    }
+    primes
+}
+
+/// The prime factors of `n` with their powers, smallest first.
+pub fn prime_factors(mut n: u64) -> Vec<(u64, u32)> {
+    let mut factors = vec![];
+    let mut d = 2;
+    while d <= n / d {
+        let mut power = 0;
+        while n.is_multiple_of(d) {
+            n /= d;
+            power += 1;
+        }
+        if power > 0 {
+            factors.push((d, power));
+        }
+        d += if d == 2 { 1 } else { 2 };
+    }
+    if n > 1 {
+        factors.push((n, 1));
+    }
+    factors
+}
+
+pub fn is_prime(n: u64) -> bool {
+    n > 1 && prime_factors(n) == vec![(n, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcd_and_lcm() {
+        assert_eq!(gcd(12, -18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(lcm(4_u64, 6), 12);
+        assert_eq!(lcm_all(vec![2, 3, 4, 5]), 60);
+        assert_eq!(lcm_all(Vec::<i64>::new()), 1);
+
+        let (g, x, y) = extended_gcd(240, 46);
+        assert_eq!(g, 2);
+        assert_eq!(240 * x + 46 * y, 2);
+    }
+
+    #[test]
+    fn modular() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(6, 9), None);
+        assert_eq!(mulmod(u64::MAX, u64::MAX, 1_000_000_007), 114944269);
+        assert_eq!(powmod(7, 8, 20201227), 5764801);
+        assert_eq!(powmod(2, 0, 1), 0);
+    }
+
+    #[test]
+    fn chinese_remainder() {
+        // the bus example from 2020 day 13
+        let busses = vec![(0, 7), (-1, 13), (-4, 59), (-6, 31), (-7, 19)];
+        assert_eq!(crt(busses), Some((1068781, 3162341)));
+        // moduli sharing a factor
+        assert_eq!(crt(vec![(2, 4), (4, 6)]), Some((10, 12)));
+        assert_eq!(crt(vec![(1, 4), (2, 6)]), None);
+        assert_eq!(crt(vec![]), Some((0, 1)));
+        assert_eq!(crt(vec![(1, 4), (0, 0)]), None);
+        // the lcm is too big for an i64
+        assert_eq!(crt(vec![(0, i64::MAX), (0, i64::MAX - 1)]), None);
+    }
+
+    #[test]
+    fn discrete_logarithm() {
+        assert_eq!(discrete_log(7, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(7, 17807724, 20201227), Some(11));
+        assert_eq!(discrete_log(2, 1, 7), Some(0));
+        // powers of 2 mod 7 are only ever 1, 2 and 4
+        assert_eq!(discrete_log(2, 3, 7), None);
+    }
+
+    #[test]
+    fn primes() {
+        assert_eq!(sieve(30), vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert_eq!(sieve(1), Vec::<usize>::new());
+        assert_eq!(prime_factors(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(prime_factors(97), vec![(97, 1)]);
+        assert_eq!(prime_factors(1), vec![]);
+        assert_eq!(
+            prime_factors(u64::MAX),
+            vec![
+                (3, 1),
+                (5, 1),
+                (17, 1),
+                (257, 1),
+                (641, 1),
+                (65537, 1),
+                (6700417, 1)
+            ]
+        );
+        assert!(is_prime(20201227));
+        assert!(!is_prime(1));
+    }
+}
@@ -0,0 +1,377 @@
+//! Simple polygons with integer vertices: area, lattice points, which points are inside,
+//! and for rectilinear polygons a compressed grid that answers rectangle queries quickly.
+
+use crate::numtheory::gcd;
+
+pub type Vertex = (i64, i64);
+
+/// Where a point is relative to a polygon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+/// A simple polygon, the edges running between consecutive vertices and back to the first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polygon {
+    vertices: Vec<Vertex>,
+}
+
+impl Polygon {
+    /// A repeat of the first vertex at the end is dropped.
+    pub fn new(mut vertices: Vec<Vertex>) -> Self {
+        if vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        Polygon { vertices }
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = (Vertex, Vertex)> + '_ {
+        let n = self.vertices.len();
+        (0..n).map(move |i| (self.vertices[i], self.vertices[(i + 1) % n]))
+    }
+
+    /// Whether every edge is horizontal or vertical.
+    pub fn is_rectilinear(&self) -> bool {
+        self.edges().all(|(a, b)| a.0 == b.0 || a.1 == b.1)
+    }
+
+    /// Twice the area by the shoelace formula, positive if the vertices go anticlockwise
+    /// (with y pointing up).
+    pub fn signed_double_area(&self) -> i64 {
+        self.edges().map(|(a, b)| a.0 * b.1 - b.0 * a.1).sum()
+    }
+
+    /// Twice the area, so it's always a whole number.
+    pub fn double_area(&self) -> i64 {
+        self.signed_double_area().abs()
+    }
+
+    /// How many lattice points lie on the edges.
+    pub fn boundary_points(&self) -> i64 {
+        self.edges().map(|(a, b)| gcd(b.0 - a.0, b.1 - a.1)).sum()
+    }
+
+    /// How many lattice points lie strictly inside, by Pick's theorem.
+    pub fn interior_points(&self) -> i64 {
+        (self.double_area() - self.boundary_points() + 2) / 2
+    }
+
+    /// How many lattice points are inside or on the edges, such as the tiles a path encloses.
+    pub fn enclosed_points(&self) -> i64 {
+        self.interior_points() + self.boundary_points()
+    }
+
+    /// Inside by the even-odd rule, counting how many edges a ray from the point crosses.
+    pub fn locate(&self, p: Vertex) -> Location {
+        let mut inside = false;
+        for (a, b) in self.edges() {
+            if on_segment(a, b, p) {
+                return Location::Boundary;
+            }
+            if (a.1 > p.1) != (b.1 > p.1) {
+                // the ray heads off along +x, so it crosses if the point is left of the edge
+                // as it goes upwards
+                let cross = side(a, b, p);
+                if (cross > 0) == (b.1 > a.1) {
+                    inside = !inside;
+                }
+            }
+        }
+        if inside {
+            Location::Inside
+        } else {
+            Location::Outside
+        }
+    }
+
+    /// How many times the edges wind anticlockwise around the point, which is on none of them.
+    /// Only differs from the even-odd rule for polygons that cross themselves.
+    pub fn winding_number(&self, p: Vertex) -> i64 {
+        let mut winding = 0;
+        for (a, b) in self.edges() {
+            if a.1 <= p.1 {
+                if b.1 > p.1 && side(a, b, p) > 0 {
+                    winding += 1;
+                }
+            } else if b.1 <= p.1 && side(a, b, p) < 0 {
+                winding -= 1;
+            }
+        }
+        winding
+    }
+
+    /// Whether the rectangle with these opposite corners, taken as a region of the plane, lies
+    /// within the polygon or on its edges. The polygon must be rectilinear.
+    /// Checks every edge. [`Compressed::contains_tiles`] asks about the rectangle's lattice
+    /// points instead, which isn't the same across a one wide notch, but answers quickly.
+    pub fn contains_rectangle(&self, a: Vertex, b: Vertex) -> bool {
+        let (min_x, max_x) = (a.0.min(b.0), a.0.max(b.0));
+        let (min_y, max_y) = (a.1.min(b.1), a.1.max(b.1));
+        let corners = [
+            (min_x, min_y),
+            (min_x, max_y),
+            (max_x, min_y),
+            (max_x, max_y),
+        ];
+        if corners.iter().any(|&c| self.locate(c) == Location::Outside) {
+            return false;
+        }
+        // with the corners in, the only way out is an edge passing through the middle
+        !self.edges().any(|(a, b)| {
+            let (lo_x, hi_x) = (a.0.min(b.0), a.0.max(b.0));
+            let (lo_y, hi_y) = (a.1.min(b.1), a.1.max(b.1));
+            lo_x < max_x && hi_x > min_x && lo_y < max_y && hi_y > min_y
+        })
+    }
+
+    /// The compressed grid of a rectilinear polygon.
+    pub fn compress(&self) -> Compressed {
+        assert!(self.is_rectilinear(), "only rectilinear polygons compress");
+        Compressed::new(self)
+    }
+}
+
+/// Positive if `p` is left of the line from `a` to `b`, zero if on it.
+fn side(a: Vertex, b: Vertex, p: Vertex) -> i128 {
+    (b.0 - a.0) as i128 * (p.1 - a.1) as i128 - (p.0 - a.0) as i128 * (b.1 - a.1) as i128
+}
+
+fn on_segment(a: Vertex, b: Vertex, p: Vertex) -> bool {
+    side(a, b, p) == 0
+        && (a.0.min(b.0)..=a.0.max(b.0)).contains(&p.0)
+        && (a.1.min(b.1)..=a.1.max(b.1)).contains(&p.1)
+}
+
+/// The lattice points around a rectilinear polygon, squashed so each vertex's x and y, and
+/// each run of coordinates between neighbouring ones, is a single column or row of cells.
+/// Every cell is then wholly inside the polygon (edges included) or wholly outside.
+#[derive(Clone, Debug)]
+pub struct Compressed {
+    /// The first x of each column, then one past the last column.
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    inside: Vec<bool>,
+    /// Outside cells above and left of each corner, a row and column bigger than the grid.
+    outside_before: Vec<u32>,
+}
+
+impl Compressed {
+    fn new(polygon: &Polygon) -> Self {
+        let starts = |coordinate: fn(&Vertex) -> i64| {
+            let mut values: Vec<i64> = polygon.vertices().iter().map(coordinate).collect();
+            values.sort_unstable();
+            values.dedup();
+            // a column of padding either side, so the outside is all connected
+            let mut starts = vec![values[0] - 1];
+            for (i, &v) in values.iter().enumerate() {
+                starts.push(v);
+                if values.get(i + 1).is_some_and(|&next| next > v + 1) {
+                    starts.push(v + 1);
+                }
+            }
+            let last = *values.last().unwrap();
+            starts.push(last + 1);
+            starts.push(last + 2);
+            starts
+        };
+        let xs = starts(|v| v.0);
+        let ys = starts(|v| v.1);
+        let (width, height) = (xs.len() - 1, ys.len() - 1);
+        let column = |x: i64| xs.partition_point(|&s| s <= x) - 1;
+        let row = |y: i64| ys.partition_point(|&s| s <= y) - 1;
+
+        // a vertical edge flips inside and outside for the rows starting within its span
+        let mut edge = vec![false; width * height];
+        let mut crossing = vec![false; width * height];
+        for (a, b) in polygon.edges() {
+            let (c0, c1) = (column(a.0.min(b.0)), column(a.0.max(b.0)));
+            let (r0, r1) = (row(a.1.min(b.1)), row(a.1.max(b.1)));
+            for r in r0..=r1 {
+                for c in c0..=c1 {
+                    edge[r * width + c] = true;
+                }
+            }
+            if c0 == c1 {
+                for r in r0..r1 {
+                    crossing[r * width + c0] = true;
+                }
+            }
+        }
+
+        let mut inside = vec![false; width * height];
+        for r in 0..height {
+            let mut within = false;
+            for i in r * width..(r + 1) * width {
+                inside[i] = edge[i] || within;
+                within ^= crossing[i];
+            }
+        }
+
+        let mut outside_before = vec![0; (width + 1) * (height + 1)];
+        for r in 0..height {
+            for c in 0..width {
+                outside_before[(r + 1) * (width + 1) + c + 1] = outside_before
+                    [r * (width + 1) + c + 1]
+                    + outside_before[(r + 1) * (width + 1) + c]
+                    - outside_before[r * (width + 1) + c]
+                    + u32::from(!inside[r * width + c]);
+            }
+        }
+
+        Compressed {
+            xs,
+            ys,
+            inside,
+            outside_before,
+        }
+    }
+
+    /// The column and row of the cell holding a point, if it's within the grid.
+    pub fn cell(&self, (x, y): Vertex) -> Option<(usize, usize)> {
+        let in_range = |starts: &[i64], v: i64| starts[0] <= v && v < *starts.last().unwrap();
+        if !in_range(&self.xs, x) || !in_range(&self.ys, y) {
+            return None;
+        }
+        Some((
+            self.xs.partition_point(|&s| s <= x) - 1,
+            self.ys.partition_point(|&s| s <= y) - 1,
+        ))
+    }
+
+    /// Whether a lattice point is inside the polygon or on an edge.
+    pub fn contains(&self, p: Vertex) -> bool {
+        let width = self.xs.len() - 1;
+        self.cell(p)
+            .is_some_and(|(c, r)| self.inside[r * width + c])
+    }
+
+    /// Whether every lattice point of the rectangle with these opposite corners is inside the
+    /// polygon or on an edge, from the prefix sums in constant time. Unlike
+    /// [`Polygon::contains_rectangle`], the plane between the points isn't checked.
+    pub fn contains_tiles(&self, a: Vertex, b: Vertex) -> bool {
+        match (self.cell(a), self.cell(b)) {
+            (Some(a), Some(b)) => self.contains_cells(a, b),
+            _ => false,
+        }
+    }
+
+    /// As [`Compressed::contains_tiles`], with the corners already looked up by
+    /// [`Compressed::cell`]. Saves the searches when the same corners come up again and again.
+    pub fn contains_cells(&self, (c0, r0): (usize, usize), (c1, r1): (usize, usize)) -> bool {
+        let (c0, c1) = (c0.min(c1), c0.max(c1));
+        let (r0, r1) = (r0.min(r1), r0.max(r1));
+        let stride = self.xs.len();
+        let at = |r: usize, c: usize| self.outside_before[r * stride + c];
+        at(r1 + 1, c1 + 1) + at(r0, c0) == at(r0, c1 + 1) + at(r1 + 1, c0)
+    }
+
+    /// How many lattice points are inside the polygon or on an edge.
+    pub fn enclosed_points(&self) -> i64 {
+        let width = self.xs.len() - 1;
+        let size = |starts: &[i64], i: usize| starts[i + 1] - starts[i];
+        (0..self.inside.len())
+            .filter(|&i| self.inside[i])
+            .map(|i| size(&self.xs, i % width) * size(&self.ys, i / width))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The red tiles from 2025 day 9.
+    fn tiles() -> Polygon {
+        Polygon::new(vec![
+            (7, 1),
+            (11, 1),
+            (11, 7),
+            (9, 7),
+            (9, 5),
+            (2, 5),
+            (2, 3),
+            (7, 3),
+        ])
+    }
+
+    #[test]
+    fn area_and_picks_theorem() {
+        let square = Polygon::new(vec![(0, 0), (4, 0), (4, 4), (0, 4), (0, 0)]);
+        assert_eq!(square.vertices().len(), 4);
+        assert_eq!(square.signed_double_area(), 32);
+        assert_eq!(square.boundary_points(), 16);
+        assert_eq!(square.interior_points(), 9);
+        assert_eq!(square.enclosed_points(), 25);
+
+        let triangle = Polygon::new(vec![(0, 0), (0, 3), (3, 0)]);
+        assert_eq!(triangle.signed_double_area(), -9);
+        assert_eq!(triangle.interior_points(), 1);
+        assert!(!triangle.is_rectilinear());
+
+        assert_eq!(tiles().enclosed_points(), 46);
+        assert_eq!(tiles().compress().enclosed_points(), 46);
+    }
+
+    #[test]
+    fn point_in_polygon() {
+        let triangle = Polygon::new(vec![(0, 0), (6, 0), (0, 6)]);
+        assert_eq!(triangle.locate((1, 1)), Location::Inside);
+        assert_eq!(triangle.locate((3, 3)), Location::Boundary);
+        assert_eq!(triangle.locate((0, 2)), Location::Boundary);
+        assert_eq!(triangle.locate((4, 4)), Location::Outside);
+        assert_eq!(triangle.locate((-1, 0)), Location::Outside);
+        assert_eq!(triangle.winding_number((1, 1)), 1);
+        assert_eq!(triangle.winding_number((4, 4)), 0);
+
+        // a pentagram goes round its middle twice, which even-odd counts as outside
+        let star = Polygon::new(vec![(0, 10), (6, -8), (-10, 3), (10, 3), (-6, -8)]);
+        assert_eq!(star.winding_number((0, 0)).abs(), 2);
+        assert_eq!(star.locate((0, 0)), Location::Outside);
+        assert_eq!(star.locate((0, 7)), Location::Inside);
+    }
+
+    #[test]
+    fn rectangles_inside() {
+        let tiles = tiles();
+        let grid = tiles.compress();
+        for (a, b, inside) in [
+            ((2, 3), (9, 5), true),
+            ((9, 1), (11, 7), true),
+            ((2, 3), (9, 7), false),
+            ((7, 1), (11, 7), false),
+            ((1, 3), (7, 3), false),
+            ((11, 1), (11, 7), true),
+        ]
+        .iter()
+        {
+            assert_eq!(tiles.contains_rectangle(*a, *b), *inside, "{:?} {:?}", a, b);
+            assert_eq!(grid.contains_tiles(*a, *b), *inside, "{:?} {:?}", a, b);
+        }
+        assert!(grid.contains((8, 2)) && grid.contains((2, 4)) && !grid.contains((3, 2)));
+        assert!(!grid.contains((100, 100)));
+        let (a, b) = (grid.cell((2, 3)).unwrap(), grid.cell((9, 5)).unwrap());
+        assert!(grid.contains_cells(b, a));
+        assert_eq!(grid.cell((100, 100)), None);
+
+        // tiles either side of a one wide notch are all inside, but the plane between isn't
+        let notched = Polygon::new(vec![
+            (0, 0),
+            (3, 0),
+            (3, 2),
+            (4, 2),
+            (4, 0),
+            (7, 0),
+            (7, 5),
+            (0, 5),
+        ]);
+        assert!(notched.compress().contains_tiles((0, 0), (7, 5)));
+        assert!(!notched.contains_rectangle((0, 0), (7, 5)));
+    }
+}
@@ -1,62 +1,615 @@
-use std::cmp::{Eq, Ordering, PartialEq};
-use std::collections::{BinaryHeap, HashSet};
-use std::fmt::Debug;
+//! Shortest path searches over implicit graphs.
+//!
+//! Every search takes any number of start states and a goal predicate, and returns `None`
+//! if no goal can be reached. Nodes are cloned into an arena as they're discovered, so the
+//! path to the goal can be rebuilt from parent links.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 use std::hash::Hash;
 
-#[derive(PartialEq, Copy, Clone, Eq, Debug)]
-pub struct HeapElem<E> {
-    pub elem: E,
-    pub distance: u64,
-    pub heuristic: u64,
+/// A path from one of the start states to a goal, inclusive of both.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path<N> {
+    pub nodes: Vec<N>,
+    pub cost: u64,
+}
+
+impl<N> Path<N> {
+    pub fn start(&self) -> &N {
+        &self.nodes[0]
+    }
+
+    pub fn goal(&self) -> &N {
+        &self.nodes[self.nodes.len() - 1]
+    }
+
+    /// The number of moves made, which is the cost for unweighted searches.
+    pub fn steps(&self) -> usize {
+        self.nodes.len() - 1
+    }
+}
+
+/// Discovered nodes, their best known cost, and the parent they were reached from.
+struct Arena<N> {
+    index: HashMap<N, usize>,
+    nodes: Vec<(N, u64, Option<usize>)>,
+}
+
+impl<N: Hash + Eq + Clone> Arena<N> {
+    fn new() -> Self {
+        Arena {
+            index: HashMap::new(),
+            nodes: vec![],
+        }
+    }
+
+    /// Record `node` at `cost`. Returns its index if this is the first or a cheaper route to it.
+    fn relax(&mut self, node: N, cost: u64, parent: Option<usize>) -> Option<usize> {
+        match self.index.entry(node) {
+            Entry::Occupied(e) => {
+                let i = *e.get();
+                if cost < self.nodes[i].1 {
+                    self.nodes[i].1 = cost;
+                    self.nodes[i].2 = parent;
+                    Some(i)
+                } else {
+                    None
+                }
+            }
+            Entry::Vacant(e) => {
+                let i = self.nodes.len();
+                self.nodes.push((e.key().clone(), cost, parent));
+                e.insert(i);
+                Some(i)
+            }
+        }
+    }
+
+    fn path(&self, mut i: usize) -> Path<N> {
+        let cost = self.nodes[i].1;
+        let mut nodes = vec![self.nodes[i].0.clone()];
+        while let Some(p) = self.nodes[i].2 {
+            nodes.push(self.nodes[p].0.clone());
+            i = p;
+        }
+        nodes.reverse();
+        Path { nodes, cost }
+    }
+}
+
+/// Breadth first search, where every move costs 1.
+pub fn bfs<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    mut is_goal: impl FnMut(&N) -> bool,
+) -> Option<Path<N>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = N>,
+{
+    let mut arena = Arena::new();
+    let mut queue = VecDeque::new();
+    for s in starts {
+        if let Some(i) = arena.relax(s, 0, None) {
+            queue.push_back(i);
+        }
+    }
+
+    while let Some(i) = queue.pop_front() {
+        let (node, cost, _) = &arena.nodes[i];
+        if is_goal(node) {
+            return Some(arena.path(i));
+        }
+        let cost = *cost;
+        for next in successors(&arena.nodes[i].0) {
+            if let Entry::Vacant(_) = arena.index.entry(next.clone()) {
+                let n = arena.relax(next, cost + 1, Some(i)).unwrap();
+                queue.push_back(n);
+            }
+        }
+    }
+
+    None
 }
 
-impl<E: Eq> Ord for HeapElem<E> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        (self.distance + self.heuristic)
-            .cmp(&(other.distance + other.heuristic))
-            .reverse()
+/// Dijkstra's algorithm, `successors` yields each neighbour along with the cost of moving to it.
+pub fn dijkstra<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    successors: impl FnMut(&N) -> I,
+    is_goal: impl FnMut(&N) -> bool,
+) -> Option<Path<N>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    astar(starts, successors, |_| 0, is_goal)
+}
+
+/// A* search. The `heuristic` must never overestimate the remaining cost for the path to be
+/// the shortest, nodes are re-expanded if a cheaper route to them is found later.
+pub fn astar<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    mut heuristic: impl FnMut(&N) -> u64,
+    mut is_goal: impl FnMut(&N) -> bool,
+) -> Option<Path<N>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    let mut arena = Arena::new();
+    // (estimated total, cost so far, node index), lowest estimate first
+    let mut queue = BinaryHeap::new();
+    for s in starts {
+        let h = heuristic(&s);
+        if let Some(i) = arena.relax(s, 0, None) {
+            queue.push(Reverse((h, 0, i)));
+        }
+    }
+
+    while let Some(Reverse((_, cost, i))) = queue.pop() {
+        if cost > arena.nodes[i].1 {
+            // a cheaper route to this node has already been expanded
+            continue;
+        }
+        if is_goal(&arena.nodes[i].0) {
+            return Some(arena.path(i));
+        }
+        for (next, step) in successors(&arena.nodes[i].0) {
+            let next_cost = cost + step;
+            let h = heuristic(&next);
+            if let Some(n) = arena.relax(next, next_cost, Some(i)) {
+                queue.push(Reverse((next_cost + h, next_cost, n)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Breadth first search where every move costs either 0 or 1.
+pub fn bfs_0_1<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    mut is_goal: impl FnMut(&N) -> bool,
+) -> Option<Path<N>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    let mut arena = Arena::new();
+    let mut queue = VecDeque::new();
+    for s in starts {
+        if let Some(i) = arena.relax(s, 0, None) {
+            queue.push_back((0, i));
+        }
+    }
+
+    while let Some((cost, i)) = queue.pop_front() {
+        if cost > arena.nodes[i].1 {
+            continue;
+        }
+        if is_goal(&arena.nodes[i].0) {
+            return Some(arena.path(i));
+        }
+        for (next, step) in successors(&arena.nodes[i].0) {
+            assert!(step <= 1, "bfs_0_1 only supports moves costing 0 or 1");
+            if let Some(n) = arena.relax(next, cost + step, Some(i)) {
+                if step == 0 {
+                    queue.push_front((cost, n));
+                } else {
+                    queue.push_back((cost + 1, n));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Search forwards from `start` and backwards from `goal` at the same time, always growing
+/// the smaller frontier. `predecessors` gives the nodes that can move to a node, for an
+/// undirected graph it's the same as `successors`.
+pub fn bidirectional_bfs<N, I, J>(
+    start: N,
+    goal: N,
+    mut successors: impl FnMut(&N) -> I,
+    mut predecessors: impl FnMut(&N) -> J,
+) -> Option<Path<N>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = N>,
+    J: IntoIterator<Item = N>,
+{
+    if start == goal {
+        return Some(Path {
+            nodes: vec![start],
+            cost: 0,
+        });
+    }
+
+    let mut forward = Arena::new();
+    let mut backward = Arena::new();
+    let mut f_frontier = vec![forward.relax(start, 0, None).unwrap()];
+    let mut b_frontier = vec![backward.relax(goal, 0, None).unwrap()];
+
+    while !f_frontier.is_empty() && !b_frontier.is_empty() {
+        let grow_forward = f_frontier.len() <= b_frontier.len();
+        let (arena, other, frontier) = if grow_forward {
+            (&mut forward, &backward, &mut f_frontier)
+        } else {
+            (&mut backward, &forward, &mut b_frontier)
+        };
+
+        // Expand a whole layer, then take the cheapest meeting point found in it.
+        let mut best: Option<(u64, usize, usize)> = None;
+        let mut next_frontier = vec![];
+        for &i in frontier.iter() {
+            let cost = arena.nodes[i].1;
+            let nexts: Vec<N> = if grow_forward {
+                successors(&arena.nodes[i].0).into_iter().collect()
+            } else {
+                predecessors(&arena.nodes[i].0).into_iter().collect()
+            };
+            for next in nexts {
+                if arena.index.contains_key(&next) {
+                    continue;
+                }
+                let met = other.index.get(&next).copied();
+                let n = arena.relax(next, cost + 1, Some(i)).unwrap();
+                next_frontier.push(n);
+                if let Some(m) = met {
+                    let total = cost + 1 + other.nodes[m].1;
+                    if best.is_none_or(|(b, _, _)| total < b) {
+                        best = Some((total, n, m));
+                    }
+                }
+            }
+        }
+        *frontier = next_frontier;
+
+        if let Some((cost, n, m)) = best {
+            let (f, b) = if grow_forward { (n, m) } else { (m, n) };
+            let mut nodes = forward.path(f).nodes;
+            let mut back = backward.path(b).nodes;
+            back.reverse();
+            nodes.extend(back.into_iter().skip(1));
+            return Some(Path { nodes, cost });
+        }
     }
+
+    None
 }
 
-impl<E: Eq> PartialOrd for HeapElem<E> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// Every node that lies on a shortest path to the cheapest goals, as found by
+/// [`dijkstra_all`] or [`bfs_all`].
+pub struct ShortestPaths<N> {
+    pub cost: u64,
+    nodes: Vec<N>,
+    parents: Vec<Vec<usize>>,
+    /// The nodes in the order they were expanded, which is by cost.
+    order: Vec<usize>,
+    goals: Vec<usize>,
+}
+
+impl<N: Clone> ShortestPaths<N> {
+    /// All the goals that can be reached at the lowest cost.
+    pub fn goals(&self) -> impl Iterator<Item = &N> {
+        self.goals.iter().map(move |&g| &self.nodes[g])
+    }
+
+    /// The number of distinct shortest paths, from any start to any of the goals.
+    pub fn count(&self) -> u64 {
+        // a node's parents were all expanded before it, so their counts are done by then
+        let mut counts = vec![0u64; self.nodes.len()];
+        for &i in &self.order {
+            counts[i] = if self.parents[i].is_empty() {
+                1
+            } else {
+                self.parents[i].iter().map(|&p| counts[p]).sum()
+            };
+        }
+        self.goals.iter().map(|&g| counts[g]).sum()
+    }
+
+    /// Every shortest path, there may be exponentially many.
+    pub fn paths(&self) -> Vec<Path<N>> {
+        let mut paths = vec![];
+        for &g in self.goals.iter() {
+            self.collect_paths(g, &mut vec![], &mut paths);
+        }
+        paths
+    }
+
+    fn collect_paths(&self, i: usize, suffix: &mut Vec<usize>, paths: &mut Vec<Path<N>>) {
+        suffix.push(i);
+        if self.parents[i].is_empty() {
+            paths.push(Path {
+                nodes: suffix
+                    .iter()
+                    .rev()
+                    .map(|&n| self.nodes[n].clone())
+                    .collect(),
+                cost: self.cost,
+            });
+        }
+        for &p in self.parents[i].iter() {
+            self.collect_paths(p, suffix, paths);
+        }
+        suffix.pop();
     }
 }
 
-pub fn search<E: Hash + Eq + Clone + Debug, Iter: Iterator<Item = HeapElem<E>>>(
-    start: E,
-    is_goal: impl Fn(&E) -> bool,
-    mut successors: impl FnMut(E, u64) -> Iter,
-) -> (E, u64) {
-    let _time = super::time_block("    search");
-    let mut queue: BinaryHeap<HeapElem<E>> = BinaryHeap::new();
-    let mut visited: HashSet<E> = HashSet::new();
-    queue.push(HeapElem {
-        elem: start,
-        distance: 0,
-        heuristic: 0,
-    });
+/// Dijkstra's algorithm keeping every equally short route to each node, so all of the shortest
+/// paths to the goal can be counted or listed. Move costs must be non-zero.
+pub fn dijkstra_all<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    mut is_goal: impl FnMut(&N) -> bool,
+) -> Option<ShortestPaths<N>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    let mut index: HashMap<N, usize> = HashMap::new();
+    let mut nodes: Vec<N> = vec![];
+    let mut costs: Vec<u64> = vec![];
+    let mut parents: Vec<Vec<usize>> = vec![];
+    let mut queue = BinaryHeap::new();
+
+    for s in starts {
+        if let Entry::Vacant(e) = index.entry(s) {
+            let i = nodes.len();
+            nodes.push(e.key().clone());
+            e.insert(i);
+            costs.push(0);
+            parents.push(vec![]);
+            queue.push(Reverse((0, i)));
+        }
+    }
 
-    while let Some(HeapElem {
-        elem,
-        distance,
-        heuristic: _,
-    }) = queue.pop()
-    {
-        if visited.contains(&elem) {
+    let mut found: Option<u64> = None;
+    let mut goals = vec![];
+    let mut expanded = vec![false; nodes.len()];
+    let mut order = vec![];
+
+    while let Some(Reverse((cost, i))) = queue.pop() {
+        if found.is_some_and(|f| cost > f) {
+            break;
+        }
+        if expanded[i] || cost > costs[i] {
+            continue;
+        }
+        expanded[i] = true;
+        order.push(i);
+        if is_goal(&nodes[i]) {
+            found = Some(cost);
+            goals.push(i);
             continue;
         }
-        if is_goal(&elem) {
-            return (elem, distance);
+        for (next, step) in successors(&nodes[i]) {
+            assert!(step > 0, "dijkstra_all needs positive move costs");
+            let next_cost = cost + step;
+            match index.entry(next) {
+                Entry::Vacant(e) => {
+                    let n = nodes.len();
+                    nodes.push(e.key().clone());
+                    e.insert(n);
+                    costs.push(next_cost);
+                    parents.push(vec![i]);
+                    expanded.push(false);
+                    queue.push(Reverse((next_cost, n)));
+                }
+                Entry::Occupied(e) => {
+                    let n = *e.get();
+                    if next_cost < costs[n] {
+                        costs[n] = next_cost;
+                        parents[n] = vec![i];
+                        queue.push(Reverse((next_cost, n)));
+                    } else if next_cost == costs[n] {
+                        parents[n].push(i);
+                    }
+                }
+            }
         }
+    }
+
+    found.map(|cost| ShortestPaths {
+        cost,
+        nodes,
+        parents,
+        order,
+        goals,
+    })
+}
 
-        for next in successors(elem.clone(), distance) {
-            queue.push(next)
+/// As [`dijkstra_all`], where every move costs 1.
+pub fn bfs_all<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(&N) -> I,
+    is_goal: impl FnMut(&N) -> bool,
+) -> Option<ShortestPaths<N>>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = N>,
+{
+    dijkstra_all(
+        starts,
+        |n| successors(n).into_iter().map(|s| (s, 1)),
+        is_goal,
+    )
+}
+
+/// The number of distinct shortest paths to the cheapest goals, along with their cost.
+pub fn count_shortest_paths<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    successors: impl FnMut(&N) -> I,
+    is_goal: impl FnMut(&N) -> bool,
+) -> Option<(u64, u64)>
+where
+    N: Hash + Eq + Clone,
+    I: IntoIterator<Item = (N, u64)>,
+{
+    dijkstra_all(starts, successors, is_goal).map(|sp| (sp.cost, sp.count()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAZE: &str = "\
+S..#....
+.#.#.##.
+.#...#..
+.####.#.
+......#G";
+
+    type Point = (i64, i64);
+
+    fn open((x, y): Point) -> bool {
+        x >= 0
+            && y >= 0
+            && MAZE
+                .lines()
+                .nth(y as usize)
+                .and_then(|r| r.as_bytes().get(x as usize))
+                .is_some_and(|&c| c != b'#')
+    }
+
+    fn neighbours(&(x, y): &Point) -> Vec<Point> {
+        vec![(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+            .into_iter()
+            .filter(|&p| open(p))
+            .collect()
+    }
+
+    fn weighted(p: &Point) -> Vec<(Point, u64)> {
+        neighbours(p).into_iter().map(|n| (n, 1)).collect()
+    }
+
+    const GOAL: Point = (7, 4);
+
+    #[test]
+    fn bfs_finds_shortest_path() {
+        let path = bfs(vec![(0, 0)], neighbours, |p| *p == GOAL).unwrap();
+        assert_eq!(path.cost, 15);
+        assert_eq!(path.steps(), 15);
+        assert_eq!(*path.start(), (0, 0));
+        assert_eq!(*path.goal(), GOAL);
+        for w in path.nodes.windows(2) {
+            assert!(neighbours(&w[0]).contains(&w[1]));
+        }
+    }
+
+    #[test]
+    fn unreachable_goal_is_none() {
+        assert_eq!(bfs(vec![(0, 0)], neighbours, |p| *p == (3, 0)), None);
+        assert_eq!(dijkstra(vec![(0, 0)], weighted, |p| *p == (3, 0)), None);
+        assert!(dijkstra_all(vec![(0, 0)], weighted, |p| *p == (3, 0)).is_none());
+    }
+
+    #[test]
+    fn multiple_starts_and_goals() {
+        let path = bfs(vec![(0, 0), (7, 1)], neighbours, |p| p.1 == 4).unwrap();
+        assert_eq!(path.cost, 3);
+        assert_eq!(*path.start(), (7, 1));
+        assert_eq!(*path.goal(), GOAL);
+    }
+
+    #[test]
+    fn weighted_searches_agree() {
+        let expensive_rows = |p: &Point| -> Vec<(Point, u64)> {
+            neighbours(p)
+                .into_iter()
+                .map(|n| (n, if n.1 == 4 { 5 } else { 1 }))
+                .collect()
+        };
+        let manhattan = |p: &Point| ((GOAL.0 - p.0).abs() + (GOAL.1 - p.1).abs()) as u64;
+
+        let d = dijkstra(vec![(0, 0)], expensive_rows, |p| *p == GOAL).unwrap();
+        let a = astar(vec![(0, 0)], expensive_rows, manhattan, |p| *p == GOAL).unwrap();
+        assert_eq!(d.cost, 19);
+        assert_eq!(a.cost, d.cost);
+    }
+
+    #[test]
+    fn zero_one_bfs() {
+        // moving right is free
+        let path = bfs_0_1(
+            vec![(0, 0)],
+            |p| {
+                neighbours(p)
+                    .into_iter()
+                    .map(|n| (n, if n.0 > p.0 { 0 } else { 1 }))
+                    .collect::<Vec<_>>()
+            },
+            |p| *p == GOAL,
+        )
+        .unwrap();
+        assert_eq!(path.cost, 8);
+    }
+
+    #[test]
+    fn bidirectional_matches_bfs() {
+        let path = bidirectional_bfs((0, 0), GOAL, neighbours, neighbours).unwrap();
+        assert_eq!(path.cost, 15);
+        assert_eq!(path.nodes.len(), 16);
+        assert_eq!(*path.start(), (0, 0));
+        assert_eq!(*path.goal(), GOAL);
+        for w in path.nodes.windows(2) {
+            assert!(neighbours(&w[0]).contains(&w[1]));
         }
+        assert_eq!(
+            bidirectional_bfs((0, 0), (99, 99), neighbours, neighbours),
+            None
+        );
+    }
+
+    #[test]
+    fn counting_lattice_paths() {
+        // there are (4 + 3)! / (4! 3!) monotone paths across a 5x4 grid
+        let right_or_down = |&(x, y): &Point| {
+            let mut next = vec![];
+            if x < 4 {
+                next.push(((x + 1, y), 1));
+            }
+            if y < 3 {
+                next.push(((x, y + 1), 1));
+            }
+            next
+        };
+        assert_eq!(
+            count_shortest_paths(vec![(0, 0)], right_or_down, |p| *p == (4, 3)),
+            Some((7, 35))
+        );
 
-        visited.insert(elem);
+        let all = dijkstra_all(vec![(0, 0)], right_or_down, |p| *p == (4, 3)).unwrap();
+        let paths = all.paths();
+        assert_eq!(paths.len(), 35);
+        assert!(paths.iter().all(|p| p.steps() == 7 && *p.goal() == (4, 3)));
     }
 
-    unreachable!("Failed to reach goal");
+    #[test]
+    fn counting_long_paths() {
+        // two ways to 3, then a line far longer than the stack could recurse along
+        let line = |&n: &u32| match n {
+            0 => vec![1, 2],
+            1 | 2 => vec![3],
+            n if n < 500_000 => vec![n + 1],
+            _ => vec![],
+        };
+        let all = bfs_all(vec![0], line, |&n| n == 500_000).unwrap();
+        assert_eq!(all.cost, 499_999);
+        assert_eq!(all.count(), 2);
+    }
+
+    #[test]
+    fn all_paths_in_maze() {
+        let all = bfs_all(vec![(0, 0)], neighbours, |p| *p == GOAL).unwrap();
+        assert_eq!(all.cost, 15);
+        assert_eq!(all.goals().collect::<Vec<_>>(), vec![&GOAL]);
+        assert_eq!(all.count(), all.paths().len() as u64);
+    }
 }
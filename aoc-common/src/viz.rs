@@ -0,0 +1,321 @@
+//! Record simulations as asciinema v2 `.cast` files, and replay them in the terminal.
+//!
+//! Recording is off unless a binary is run with `--record`, so the timed runs aren't affected.
+//! A day creates a [`Recorder`] and pushes frames into it. When the recorder is dropped the
+//! frames are written to `recordings/<name>.cast`.
+
+use crate::files::Res;
+use std::fmt::Write as _;
+use std::fs::{self, read_to_string};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::thread::sleep;
+use std::time::Duration;
+
+static RECORD_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Clear the screen and move the cursor home, written before every frame.
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// Turn on recording for the rest of the run, writing casts into `dir`.
+pub fn enable(dir: impl Into<PathBuf>) {
+    let _ = RECORD_DIR.set(dir.into());
+}
+
+pub fn is_enabled() -> bool {
+    RECORD_DIR.get().is_some()
+}
+
+/// Handle the `--record` and `--replay <file> [--speed <x>]` flags.
+/// Returns `true` if a cast was replayed, in which case there's nothing left to run.
+pub fn init_from_args(args: &[String]) -> Res<bool> {
+    if let Some(i) = args.iter().position(|a| a == "--replay") {
+        let file = args.get(i + 1).ok_or("--replay needs a .cast file")?;
+        let speed = match args.iter().position(|a| a == "--speed") {
+            Some(i) => args.get(i + 1).ok_or("--speed needs a value")?.parse()?,
+            None => 1.0,
+        };
+        replay(file, speed)?;
+        return Ok(true);
+    }
+    if args.iter().any(|a| a == "--record") {
+        enable("recordings");
+    }
+    Ok(false)
+}
+
+/// Collects frames of a simulation. Does nothing unless recording is enabled.
+pub struct Recorder {
+    name: String,
+    dir: Option<PathBuf>,
+    frame_time: f64,
+    frames: Vec<String>,
+}
+
+impl Recorder {
+    /// A recorder that writes `<name>.cast` if recording was enabled with `--record`.
+    pub fn new(name: &str) -> Self {
+        Recorder {
+            name: name.to_string(),
+            dir: RECORD_DIR.get().cloned(),
+            frame_time: 1.0 / 30.0,
+            frames: vec![],
+        }
+    }
+
+    /// A recorder that always records, into `dir`.
+    pub fn in_dir(name: &str, dir: impl Into<PathBuf>) -> Self {
+        let mut recorder = Recorder::new(name);
+        recorder.dir = Some(dir.into());
+        recorder
+    }
+
+    /// Seconds between frames when played back at normal speed.
+    #[must_use]
+    pub fn with_frame_time(mut self, seconds: f64) -> Self {
+        self.frame_time = seconds;
+        self
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Add a frame. The closure is only called when recording, so drawing costs nothing otherwise.
+    pub fn frame(&mut self, draw: impl FnOnce() -> String) {
+        if self.is_recording() {
+            self.frames.push(draw());
+        }
+    }
+
+    /// Render the frames as an asciicast v2 document.
+    pub fn to_cast(&self) -> String {
+        let width = self
+            .frames
+            .iter()
+            .flat_map(|f| f.lines())
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let height = self
+            .frames
+            .iter()
+            .map(|f| f.lines().count())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let mut cast = format!(
+            "{{\"version\": 2, \"width\": {}, \"height\": {}, \"title\": {}}}\n",
+            width,
+            height,
+            json_string(&self.name)
+        );
+        for (i, frame) in self.frames.iter().enumerate() {
+            let data = format!("{}{}", CLEAR, frame.replace('\n', "\r\n"));
+            writeln!(
+                cast,
+                "[{:.6}, \"o\", {}]",
+                i as f64 * self.frame_time,
+                json_string(&data)
+            )
+            .unwrap();
+        }
+        cast
+    }
+
+    /// Write the cast file now, rather than waiting for the recorder to be dropped.
+    pub fn save(&mut self) -> io::Result<Option<PathBuf>> {
+        let dir = match &self.dir {
+            Some(dir) if !self.frames.is_empty() => dir,
+            _ => return Ok(None),
+        };
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.cast", self.name));
+        fs::write(&path, self.to_cast())?;
+        self.frames.clear();
+        Ok(Some(path))
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        match self.save() {
+            Ok(Some(path)) => println!("  recorded {}", path.display()),
+            Ok(None) => {}
+            Err(e) => eprintln!("  failed to record {}: {}", self.name, e),
+        }
+    }
+}
+
+/// Play a cast file back in the terminal. A `speed` of 2.0 plays twice as fast.
+pub fn replay(path: impl AsRef<Path>, speed: f64) -> Res<()> {
+    let cast = read_to_string(path)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut last = 0.0;
+    for (time, data) in parse_cast(&cast)? {
+        let wait = (time - last) / speed;
+        if wait > 0.0 {
+            sleep(Duration::from_secs_f64(wait));
+        }
+        last = time;
+        out.write_all(data.as_bytes())?;
+        out.flush()?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Parse the output events of an asciicast v2 document into `(time, data)` pairs.
+pub fn parse_cast(cast: &str) -> Res<Vec<(f64, String)>> {
+    let mut lines = cast.lines();
+    let header = lines.next().ok_or("empty cast file")?;
+    if !header.contains("\"version\": 2") && !header.contains("\"version\":2") {
+        return Err(format!("not an asciicast v2 header: {}", header).into());
+    }
+
+    let mut events = vec![];
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let event = parse_event(line).ok_or_else(|| format!("bad cast event: {}", line))?;
+        if let Some(event) = event {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+/// Parses `[time, "type", "data"]`, returning `Some(None)` for events that aren't output.
+fn parse_event(line: &str) -> Option<Option<(f64, String)>> {
+    let body = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let (time, rest) = body.split_once(',')?;
+    let time = time.trim().parse().ok()?;
+    let (kind, rest) = parse_json_string(rest.trim_start())?;
+    let rest = rest.trim_start().strip_prefix(',')?;
+    let (data, _) = parse_json_string(rest.trim_start())?;
+    Some(if kind == "o" {
+        Some((time, data))
+    } else {
+        None
+    })
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Parse a JSON string literal from the start of `s`, returning it and the remaining input.
+fn parse_json_string(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut out = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &s[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'b' => out.push('\u{8}'),
+                'f' => out.push('\u{c}'),
+                'u' => {
+                    let mut code = read_hex(&mut chars)?;
+                    if (0xD800..0xDC00).contains(&code) {
+                        // a surrogate pair, the low half follows as another \u escape
+                        if chars.next()?.1 != '\\' || chars.next()?.1 != 'u' {
+                            return None;
+                        }
+                        let low = read_hex(&mut chars)?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return None;
+                        }
+                        code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                    }
+                    out.push(char::from_u32(code)?);
+                }
+                c => out.push(c),
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+fn read_hex(chars: &mut std::str::CharIndices) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4 {
+        code = code * 16 + chars.next()?.1.to_digit(16)?;
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_recording_by_default() {
+        let mut rec = Recorder::new("quiet");
+        rec.frame(|| unreachable!("frames aren't drawn unless recording"));
+        assert!(rec.is_empty());
+    }
+
+    #[test]
+    fn cast_round_trip() {
+        let dir = std::env::temp_dir().join("aoc-viz-test");
+        let mut rec = Recorder::in_dir("round_trip", &dir).with_frame_time(0.5);
+        rec.frame(|| "#.#\n.\".".to_string());
+        rec.frame(|| "\\é\t".to_string());
+
+        let cast = rec.to_cast();
+        assert!(cast.starts_with(
+            "{\"version\": 2, \"width\": 3, \"height\": 2, \"title\": \"round_trip\"}\n"
+        ));
+
+        let events = parse_cast(&cast).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                (0.0, format!("{}#.#\r\n.\".", CLEAR)),
+                (0.5, format!("{}\\é\t", CLEAR)),
+            ]
+        );
+
+        let path = rec.save().unwrap().unwrap();
+        assert_eq!(parse_cast(&read_to_string(path).unwrap()).unwrap(), events);
+    }
+
+    #[test]
+    fn parse_escapes() {
+        assert_eq!(
+            parse_json_string(r#""a\u001b[H\ud83d\ude00" rest"#),
+            Some(("a\x1b[H😀".to_string(), " rest"))
+        );
+        assert_eq!(parse_event(r#"[1.5, "i", "x"]"#), Some(None));
+        // a high surrogate without a low one after it
+        assert_eq!(parse_json_string(r#""\ud83d\u0041""#), None);
+    }
+}
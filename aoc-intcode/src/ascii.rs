@@ -0,0 +1,251 @@
+//! Talking to programs that speak ASCII: they print text, read lines of it, and say anything
+//! that isn't text, like an answer at the end, as a value too big to be a character.
+
+use crate::machine::{Machine, Status};
+use std::io::{self, BufRead, Write};
+
+/// What a program said before it stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reply {
+    pub text: String,
+    /// Outputs that aren't ASCII, in order.
+    pub values: Vec<i64>,
+    /// Why it stopped. Never [`Status::Output`].
+    pub status: Status,
+}
+
+impl Reply {
+    fn new() -> Self {
+        Reply {
+            text: String::new(),
+            values: vec![],
+            status: Status::NeedsInput,
+        }
+    }
+
+    fn append(&mut self, other: Reply) {
+        self.text.push_str(&other.text);
+        self.values.extend(other.values);
+        self.status = other.status;
+    }
+}
+
+/// A [`Machine`] running an ASCII program, sending it lines and reading back text.
+///
+/// With [`AsciiConsole::with_transcript`], everything said both ways is kept, as it'd look on
+/// a terminal.
+#[derive(Clone, Debug)]
+pub struct AsciiConsole {
+    machine: Machine,
+    transcript: Option<String>,
+}
+
+impl AsciiConsole {
+    pub fn new(machine: Machine) -> Self {
+        AsciiConsole {
+            machine,
+            transcript: None,
+        }
+    }
+
+    /// Record everything from here on.
+    pub fn with_transcript(mut self) -> Self {
+        self.transcript = Some(String::new());
+        self
+    }
+
+    pub fn transcript(&self) -> Option<&str> {
+        self.transcript.as_deref()
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    pub fn into_machine(self) -> Machine {
+        self.machine
+    }
+
+    /// Queue some text, without a newline.
+    pub fn send(&mut self, text: &str) {
+        self.machine.extend_input(text.bytes().map(i64::from));
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push_str(text);
+        }
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        self.send(line);
+        self.send("\n");
+    }
+
+    /// Run until the program needs input it doesn't have, halts or faults.
+    pub fn read(&mut self) -> Reply {
+        self.read_until("")
+    }
+
+    /// Run until the text ends with `prompt`, or the program stops first. An empty prompt
+    /// never matches.
+    pub fn read_until(&mut self, prompt: &str) -> Reply {
+        let mut reply = Reply::new();
+        loop {
+            match self.machine.run() {
+                Status::Output(value @ 0..=127) => {
+                    reply.text.push(value as u8 as char);
+                    if !prompt.is_empty() && reply.text.ends_with(prompt) {
+                        break;
+                    }
+                }
+                Status::Output(value) => reply.values.push(value),
+                status => {
+                    reply.status = status;
+                    break;
+                }
+            }
+        }
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push_str(&reply.text);
+            for value in &reply.values {
+                transcript.push_str(&format!("{}\n", value));
+            }
+        }
+        reply
+    }
+
+    /// Send a line and read the answer.
+    pub fn command(&mut self, line: &str) -> Reply {
+        self.send_line(line);
+        self.read()
+    }
+
+    /// Read what the program says, then send each line once it asks for input, and read the
+    /// answer. Everything it said is in the one reply. Lines left over when it stops asking
+    /// aren't sent.
+    pub fn run_script<S: AsRef<str>>(&mut self, lines: impl IntoIterator<Item = S>) -> Reply {
+        let mut reply = self.read();
+        for line in lines {
+            if reply.status != Status::NeedsInput {
+                break;
+            }
+            let answer = self.command(line.as_ref());
+            reply.append(answer);
+        }
+        reply
+    }
+
+    /// Play in a terminal: print what the program says, and send it lines from `input` until
+    /// it stops or `input` runs out.
+    pub fn interact(&mut self, mut input: impl BufRead, mut out: impl Write) -> io::Result<Status> {
+        loop {
+            let reply = self.read();
+            write!(out, "{}", reply.text)?;
+            for value in &reply.values {
+                writeln!(out, "{}", value)?;
+            }
+            out.flush()?;
+            if reply.status != Status::NeedsInput {
+                return Ok(reply.status);
+            }
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(Status::NeedsInput);
+            }
+            self.send_line(line.trim_end_matches(&['\r', '\n'][..]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble::assemble;
+
+    /// Asks `?` for lines and says `ok` to each, until an empty one. Then it outputs how many
+    /// characters it was sent, times 1000, and says `bye`.
+    fn chatty() -> AsciiConsole {
+        let tape = assemble(
+            "
+            start:
+                out #63
+                out #10
+                add #0, #0 -> [n]
+            read:
+                in -> [c]
+                eq [c], #10 -> [t]
+                jnz [t], #line
+                add [n], #1 -> [n]
+                add [total], #1 -> [total]
+                jz #0, #read
+            line:
+                jz [n], #done
+                out #111
+                out #107
+                out #10
+                jz #0, #start
+            done:
+                mul [total], #1000 -> [t]
+                out [t]
+                out #98
+                out #121
+                out #101
+                out #10
+                hlt
+            n: data 0
+            c: data 0
+            t: data 0
+            total: data 0
+            ",
+        )
+        .unwrap();
+        AsciiConsole::new(Machine::new(&tape))
+    }
+
+    #[test]
+    fn lines_and_prompts() {
+        let mut console = chatty();
+        let reply = console.read();
+        assert_eq!(reply.text, "?\n");
+        assert_eq!(reply.status, Status::NeedsInput);
+
+        console.send_line("hello");
+        assert_eq!(console.read_until("ok\n").text, "ok\n");
+        assert_eq!(console.read().text, "?\n");
+
+        let reply = console.command("");
+        assert_eq!(reply.text, "bye\n");
+        assert_eq!(reply.values, vec![5000]);
+        assert_eq!(reply.status, Status::Halted);
+    }
+
+    #[test]
+    fn script() {
+        let mut console = chatty().with_transcript();
+        let reply = console.run_script(["hi", "there", "", "never sent"]);
+        assert_eq!(reply.text, "?\nok\n?\nok\n?\nbye\n");
+        assert_eq!(reply.values, vec![7000]);
+        assert_eq!(reply.status, Status::Halted);
+        assert_eq!(
+            console.transcript(),
+            Some("?\nhi\nok\n?\nthere\nok\n?\n\nbye\n7000\n")
+        );
+    }
+
+    #[test]
+    fn interactive() {
+        let mut console = chatty();
+        let mut out = vec![];
+        let status = console.interact(&b"abc\r\n"[..], &mut out).unwrap();
+        assert_eq!(status, Status::NeedsInput);
+        assert_eq!(String::from_utf8(out).unwrap(), "?\nok\n?\n");
+
+        let mut out = vec![];
+        let status = console.interact(&b"\n"[..], &mut out).unwrap();
+        assert_eq!(status, Status::Halted);
+        assert_eq!(String::from_utf8(out).unwrap(), "bye\n3000\n");
+    }
+}
@@ -0,0 +1,234 @@
+//! Turning text like the [`crate::disassemble`] listings back into a program, for writing
+//! test programs in.
+//!
+//! Each line is an instruction, or `data` and some comma separated values, and can start
+//! with a `label:`. Anything after a `;` is a comment. Parameters are `#value`, `[addr]` or
+//! `rb+offset`, with any written one after an arrow, and a label can stand in for a value or
+//! an address: `jz [count], #done`.
+
+use crate::instruction::{Instruction, Mode, Opcode};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssembleError {
+    /// Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+/// A cell of the program, which might be waiting to find out where a label is.
+enum Cell<'a> {
+    Value(i64),
+    Label { name: &'a str, line: usize },
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssembleError> {
+    let mut labels = HashMap::new();
+    let mut cells = vec![];
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let err = |message: String| AssembleError { line, message };
+        let mut rest = text.split(';').next().unwrap_or_default().trim();
+        while let Some((name, after)) = split_label(rest) {
+            if labels.insert(name, cells.len()).is_some() {
+                return Err(err(format!("{} is defined twice", name)));
+            }
+            rest = after.trim_start();
+        }
+        if rest.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = match rest.find(char::is_whitespace) {
+            Some(i) => (&rest[..i], rest[i..].trim()),
+            None => (rest, ""),
+        };
+        if mnemonic == "data" {
+            for v in operands.split(',').map(str::trim) {
+                cells.push(value(v, line).ok_or_else(|| err(format!("bad value {:?}", v)))?);
+            }
+            continue;
+        }
+
+        let opcode = Opcode::from_mnemonic(mnemonic)
+            .ok_or_else(|| err(format!("unknown instruction {:?}", mnemonic)))?;
+        let (reads, dest) = match operands.split_once("->") {
+            Some((reads, dest)) => (reads.trim(), Some(dest.trim())),
+            None => (operands, None),
+        };
+        if opcode.writes() != dest.is_some() {
+            return Err(err(if opcode.writes() {
+                format!("{} needs somewhere to write to after ->", mnemonic)
+            } else {
+                format!("{} doesn't write anywhere", mnemonic)
+            }));
+        }
+        let mut params: Vec<&str> = match reads {
+            "" => vec![],
+            _ => reads.split(',').map(str::trim).collect(),
+        };
+        params.extend(dest);
+        if params.len() != opcode.arity() {
+            return Err(err(format!(
+                "{} takes {} parameters",
+                mnemonic,
+                opcode.arity()
+            )));
+        }
+
+        let mut modes = [Mode::Position; 3];
+        let mut values = vec![];
+        for (mode, param) in modes.iter_mut().zip(params) {
+            let (m, v) =
+                operand(param, line).ok_or_else(|| err(format!("bad parameter {:?}", param)))?;
+            *mode = m;
+            values.push(v);
+        }
+        if opcode.writes() && modes[opcode.arity() - 1] == Mode::Immediate {
+            return Err(err("can't write to an immediate".to_owned()));
+        }
+        cells.push(Cell::Value(Instruction { opcode, modes }.encode()));
+        cells.extend(values);
+    }
+
+    cells
+        .into_iter()
+        .map(|cell| match cell {
+            Cell::Value(v) => Ok(v),
+            Cell::Label { name, line } => match labels.get(name) {
+                Some(&addr) => Ok(addr as i64),
+                None => Err(AssembleError {
+                    line,
+                    message: format!("{} isn't defined", name),
+                }),
+            },
+        })
+        .collect()
+}
+
+/// A `label:` at the start of the line, and what follows it.
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (name, rest) = text.split_once(':')?;
+    Some((name.trim(), rest)).filter(|(name, _)| is_label(name))
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn value(text: &str, line: usize) -> Option<Cell<'_>> {
+    match text.parse() {
+        Ok(v) => Some(Cell::Value(v)),
+        Err(_) if is_label(text) => Some(Cell::Label { name: text, line }),
+        Err(_) => None,
+    }
+}
+
+fn operand(text: &str, line: usize) -> Option<(Mode, Cell<'_>)> {
+    if let Some(v) = text.strip_prefix('#') {
+        Some((Mode::Immediate, value(v.trim(), line)?))
+    } else if let Some(addr) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        Some((Mode::Position, value(addr.trim(), line)?))
+    } else if let Some(offset) = text.strip_prefix("rb") {
+        let offset: String = offset.chars().filter(|c| !c.is_whitespace()).collect();
+        let offset = if offset.is_empty() {
+            0
+        } else {
+            offset.parse().ok()?
+        };
+        Some((Mode::Relative, Cell::Value(offset)))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disassemble::{disassemble, Disassembler};
+    use crate::machine::Machine;
+    use crate::trace::Trace;
+
+    #[test]
+    fn source() {
+        let tape = assemble(
+            "
+            ; count down from the input
+                in -> [n]
+            loop: out [n]
+                add [n], #-1 -> [n]
+                jnz [n], #loop
+                hlt
+            n:  data 0
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            tape,
+            vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0]
+        );
+
+        let tape = assemble("arb rb\nadd rb - 2, #1 -> rb+3\ndata end, -7\nend:").unwrap();
+        assert_eq!(tape, vec![209, 0, 21201, -2, 1, 3, 8, -7]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let tapes: [&[i64]; 4] = [
+            // the quine
+            &[
+                109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+            ],
+            // is the input below, equal to or above 8
+            &[
+                3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36,
+                98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000,
+                1, 20, 4, 20, 1105, 1, 46, 98, 99,
+            ],
+            &[1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+            // not code at all
+            &[42, -1, 0, 99999, 1, 2],
+        ];
+        for tape in tapes.iter() {
+            let text = disassemble(tape).to_string();
+            assert_eq!(assemble(&text).as_deref(), Ok(*tape), "{}", text);
+
+            let mut machine = Machine::new(tape)
+                .with_input(vec![8])
+                .with_trace(Trace::new(0));
+            machine.run();
+            let trace = machine.trace().unwrap();
+            let text = Disassembler::new(tape).with_counts(trace).disassemble();
+            assert_eq!(assemble(&text.to_string()).as_deref(), Ok(*tape));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let line_of = |source: &str| assemble(source).unwrap_err().line;
+        assert_eq!(line_of("hlt\nfoo [1]"), 2);
+        assert_eq!(line_of("a: hlt\na: hlt"), 2);
+        assert_eq!(line_of("add #1, #2"), 1);
+        assert_eq!(line_of("out #1 -> [2]"), 1);
+        assert_eq!(line_of("add #1 -> [2]"), 1);
+        assert_eq!(line_of("add #1, #2 -> #3"), 1);
+        assert_eq!(line_of("out {1}"), 1);
+        assert_eq!(line_of("data 1, 2x"), 1);
+        assert_eq!(line_of("hlt\n\njz #0, #nowhere"), 3);
+        assert_eq!(
+            assemble("in -> #1").unwrap_err().to_string(),
+            "line 1: can't write to an immediate"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+//! Stepping through a program, forwards and backwards, from a prompt.
+//!
+//! Every step records how to undo it, so [`Debugger::back`] can rewind the most recent
+//! steps. Edits made through [`Debugger::machine_mut`] aren't recorded, and stay put.
+
+use crate::disassemble::{Disassembler, Item};
+use crate::instruction::Opcode;
+use crate::machine::{Machine, Status};
+use crate::read_input;
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{self, BufRead, Write};
+
+/// How many steps can be undone.
+const HISTORY: usize = 100_000;
+
+/// What stops [`Debugger::resume`] before the program gets there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before executing the instruction at this address.
+    Address(usize),
+    /// Before executing any instruction with this opcode.
+    Opcode(Opcode),
+    /// After any output.
+    Output,
+}
+
+/// Why the debugger handed back control.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// Took as many steps as asked.
+    Stepped,
+    Breakpoint(Breakpoint),
+    /// After a write to a watched address.
+    Watchpoint {
+        addr: usize,
+        old: i64,
+        new: i64,
+    },
+    /// The machine can't carry on by itself: it needs input, halted or faulted.
+    Machine(Status),
+}
+
+/// What a step changed, so it can be put back.
+struct Undo {
+    ip: usize,
+    relative_base: i64,
+    write: Option<(usize, i64)>,
+    input: Option<i64>,
+    output: bool,
+}
+
+pub struct Debugger {
+    machine: Machine,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: BTreeSet<usize>,
+    history: VecDeque<Undo>,
+    output: Vec<i64>,
+    steps: u64,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Self {
+        Debugger {
+            machine,
+            breakpoints: vec![],
+            watchpoints: BTreeSet::new(),
+            history: VecDeque::new(),
+            output: vec![],
+            steps: 0,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    /// Everything output so far.
+    pub fn output(&self) -> &[i64] {
+        &self.output
+    }
+
+    /// How many instructions have been executed, less any stepped back over.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    /// Remove the `n`th breakpoint.
+    pub fn remove_breakpoint(&mut self, n: usize) -> Option<Breakpoint> {
+        if n < self.breakpoints.len() {
+            Some(self.breakpoints.remove(n))
+        } else {
+            None
+        }
+    }
+
+    pub fn watch(&mut self, addr: usize) {
+        self.watchpoints.insert(addr);
+    }
+
+    pub fn unwatch(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr)
+    }
+
+    /// Execute one instruction, returning why to stop if there's a reason to.
+    fn step_once(&mut self) -> Option<Stop> {
+        let ip = self.machine.ip();
+        let relative_base = self.machine.relative_base();
+        let write = self
+            .machine
+            .destination()
+            .map(|addr| (addr, self.machine.peek(addr)));
+        let reads_input =
+            matches!(self.machine.instruction(), Ok(ins) if ins.opcode == Opcode::Input);
+        let queued = self.machine.pending_input().front().copied();
+        let status = self.machine.step();
+        if let Some(status @ (Status::NeedsInput | Status::Halted | Status::Fault(_))) = status {
+            // nothing happened
+            return Some(Stop::Machine(status));
+        }
+
+        self.history.push_back(Undo {
+            ip,
+            relative_base,
+            write,
+            input: queued.filter(|_| reads_input),
+            output: status.is_some(),
+        });
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+        self.steps += 1;
+
+        if let Some(Status::Output(value)) = status {
+            self.output.push(value);
+            if self.breakpoints.contains(&Breakpoint::Output) {
+                return Some(Stop::Breakpoint(Breakpoint::Output));
+            }
+        }
+        match write {
+            Some((addr, old)) if self.watchpoints.contains(&addr) => Some(Stop::Watchpoint {
+                addr,
+                old,
+                new: self.machine.peek(addr),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The breakpoint for the next instruction, if there is one.
+    fn breakpoint_here(&self) -> Option<Breakpoint> {
+        let opcode = self.machine.instruction().ok().map(|ins| ins.opcode);
+        self.breakpoints.iter().copied().find(|&b| match b {
+            Breakpoint::Address(addr) => addr == self.machine.ip(),
+            Breakpoint::Opcode(op) => Some(op) == opcode,
+            Breakpoint::Output => false,
+        })
+    }
+
+    /// Execute `n` instructions, stopping early for output breakpoints, watchpoints, or the
+    /// machine stopping.
+    pub fn step(&mut self, n: usize) -> Stop {
+        for _ in 0..n {
+            if let Some(stop) = self.step_once() {
+                return stop;
+            }
+        }
+        Stop::Stepped
+    }
+
+    /// Run until something stops it. A breakpoint on the instruction it starts from doesn't
+    /// count, so resuming from a breakpoint gets past it.
+    pub fn resume(&mut self) -> Stop {
+        loop {
+            if let Some(stop) = self.step_once() {
+                return stop;
+            }
+            if let Some(breakpoint) = self.breakpoint_here() {
+                return Stop::Breakpoint(breakpoint);
+            }
+        }
+    }
+
+    /// Undo up to `n` steps, returning how many there were to undo.
+    pub fn back(&mut self, n: usize) -> usize {
+        for undone in 0..n {
+            let undo = match self.history.pop_back() {
+                Some(undo) => undo,
+                None => return undone,
+            };
+            self.machine.set_ip(undo.ip);
+            self.machine.set_relative_base(undo.relative_base);
+            if let Some((addr, old)) = undo.write {
+                self.machine.poke(addr, old);
+            }
+            if let Some(value) = undo.input {
+                self.machine.unread_input(value);
+            }
+            if undo.output {
+                self.output.pop();
+            }
+            self.steps -= 1;
+        }
+        n
+    }
+
+    /// The next instruction as text.
+    pub fn here(&self) -> String {
+        let ip = self.machine.ip();
+        let text = match self.machine.instruction() {
+            Ok(ins) => Item::Code {
+                ins,
+                params: (1..ins.size()).map(|i| self.machine.peek(ip + i)).collect(),
+            }
+            .text(&BTreeSet::new()),
+            Err(_) => format!("data {}", self.machine.peek(ip)),
+        };
+        format!("{:>6}: {}", ip, text)
+    }
+
+    fn describe(&self, stop: Stop) -> String {
+        let reason = match stop {
+            Stop::Stepped => return self.here(),
+            Stop::Breakpoint(Breakpoint::Output) => {
+                format!("output {}", self.output.last().unwrap_or(&0))
+            }
+            Stop::Breakpoint(Breakpoint::Address(addr)) => format!("breakpoint at {}", addr),
+            Stop::Breakpoint(Breakpoint::Opcode(op)) => format!("breakpoint on {}", op.mnemonic()),
+            Stop::Watchpoint { addr, old, new } => format!("[{}] {} -> {}", addr, old, new),
+            Stop::Machine(Status::NeedsInput) => "waiting for input".to_owned(),
+            Stop::Machine(Status::Halted) => "halted".to_owned(),
+            Stop::Machine(Status::Fault(e)) => format!("fault: {}", e),
+            Stop::Machine(Status::Output(value)) => format!("output {}", value),
+        };
+        format!("{}\n{}", reason, self.here())
+    }
+
+    /// Carry out a command from the prompt, returning what to show for it.
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (&name, args) = match words.split_first() {
+            Some(split) => split,
+            None => return Ok(String::new()),
+        };
+        let number = |i: usize| -> Result<i64, String> {
+            let arg = args.get(i).ok_or("not enough arguments")?;
+            arg.trim_end_matches(',')
+                .parse()
+                .map_err(|_| format!("not a number: {}", arg))
+        };
+        let addr = |i: usize| -> Result<usize, String> {
+            let n = number(i)?;
+            if n < 0 {
+                Err(format!("negative address: {}", n))
+            } else {
+                Ok(n as usize)
+            }
+        };
+        let count = |default: usize| {
+            if args.is_empty() {
+                Ok(default)
+            } else {
+                addr(0)
+            }
+        };
+
+        Ok(match name {
+            "s" | "step" => {
+                let stop = self.step(count(1)?);
+                self.describe(stop)
+            }
+            "c" | "continue" => {
+                let stop = self.resume();
+                self.describe(stop)
+            }
+            "back" => {
+                let undone = self.back(count(1)?);
+                format!("back {}\n{}", undone, self.here())
+            }
+            "b" | "break" => {
+                let breakpoint = match args {
+                    ["out"] => Breakpoint::Output,
+                    ["op", mnemonic] => Breakpoint::Opcode(
+                        Opcode::from_mnemonic(mnemonic)
+                            .ok_or_else(|| format!("unknown instruction: {}", mnemonic))?,
+                    ),
+                    _ => Breakpoint::Address(addr(0)?),
+                };
+                self.add_breakpoint(breakpoint);
+                self.list_breakpoints()
+            }
+            "breaks" => self.list_breakpoints(),
+            "delete" => {
+                self.remove_breakpoint(addr(0)?)
+                    .ok_or("no such breakpoint")?;
+                self.list_breakpoints()
+            }
+            "watch" => {
+                self.watch(addr(0)?);
+                format!("watching {:?}", self.watchpoints)
+            }
+            "unwatch" => {
+                self.unwatch(addr(0)?);
+                format!("watching {:?}", self.watchpoints)
+            }
+            "mem" => {
+                let start = addr(0)?;
+                let len = if args.len() > 1 { addr(1)? } else { 8 };
+                (start..start + len)
+                    .step_by(8)
+                    .map(|row| {
+                        let values: Vec<String> = (row..(row + 8).min(start + len))
+                            .map(|a| self.machine.peek(a).to_string())
+                            .collect();
+                        format!("{:>6}: {}", row, values.join(" "))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            "set" => {
+                let (a, value) = (addr(0)?, number(1)?);
+                self.machine.poke(a, value);
+                format!("[{}] = {}", a, value)
+            }
+            "rb" => {
+                if !args.is_empty() {
+                    self.machine.set_relative_base(number(0)?);
+                }
+                format!("rb = {}", self.machine.relative_base())
+            }
+            "ip" => {
+                if !args.is_empty() {
+                    self.machine.set_ip(addr(0)?);
+                }
+                self.here()
+            }
+            "info" => format!(
+                "ip = {}, rb = {}, steps = {}, input = {:?}, {} output",
+                self.machine.ip(),
+                self.machine.relative_base(),
+                self.steps,
+                self.machine.pending_input(),
+                self.output.len()
+            ),
+            "pages" => self.machine.memory().report().to_string(),
+            "in" | "input" => {
+                for i in 0..args.len() {
+                    self.machine.push_input(number(i)?);
+                }
+                format!("input = {:?}", self.machine.pending_input())
+            }
+            "ascii" => {
+                let text = line.trim_start()[name.len()..].trim_start();
+                self.machine
+                    .extend_input(text.bytes().chain(Some(b'\n')).map(i64::from));
+                format!("{} input queued", self.machine.pending_input().len())
+            }
+            "file" => {
+                let path = args.first().ok_or("which file?")?;
+                let input = read_input(path).map_err(|e| e.to_string())?;
+                self.machine.extend_input(input);
+                format!("{} input queued", self.machine.pending_input().len())
+            }
+            "out" | "output" => {
+                let mut text = format!("{:?}", self.output);
+                if self.output.iter().all(|&v| (0..128).contains(&v)) {
+                    text.push('\n');
+                    text.extend(self.output.iter().map(|&v| v as u8 as char));
+                }
+                text
+            }
+            "l" | "list" => {
+                let from = if args.is_empty() {
+                    self.machine.ip()
+                } else {
+                    addr(0)?
+                };
+                let lines = if args.len() > 1 { addr(1)? } else { 10 };
+                self.list(from, lines)
+            }
+            "h" | "help" => HELP.to_owned(),
+            _ => return Err(format!("unknown command: {} (try help)", name)),
+        })
+    }
+
+    fn list_breakpoints(&self) -> String {
+        self.breakpoints
+            .iter()
+            .enumerate()
+            .map(|(i, b)| match b {
+                Breakpoint::Address(addr) => format!("{}: at {}", i, addr),
+                Breakpoint::Opcode(op) => format!("{}: on {}", i, op.mnemonic()),
+                Breakpoint::Output => format!("{}: on output", i),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Disassemble memory as it is now, tracing from where it's up to.
+    fn list(&self, from: usize, lines: usize) -> String {
+        let ip = self.machine.ip();
+        let tape = self.machine.memory().to_vec();
+        let listing = Disassembler::new(&tape).entry(ip).entry(from).disassemble();
+        listing
+            .lines
+            .iter()
+            .filter(|line| line.addr + line.item.size() > from)
+            .take(lines)
+            .map(|line| {
+                let marker = if line.addr == ip { '>' } else { ' ' };
+                format!(
+                    "{}{:>5}: {}",
+                    marker,
+                    line.addr,
+                    line.item.text(&listing.labels)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+const HELP: &str = "\
+s, step [n]         execute n instructions
+c, continue         run until a breakpoint, watchpoint, input is needed, or it halts
+back [n]            undo n steps
+b, break <addr>     break before the instruction at addr
+break op <op>       break before any instruction like add, in or jnz
+break out           break after any output
+breaks, delete <n>  list or remove breakpoints
+watch, unwatch <a>  stop after writes to an address
+mem <addr> [len]    show memory
+set <addr> <value>  change memory
+pages               show which pages of memory are in use
+rb [value], ip [a]  show or change the relative base or where it's up to
+in <values>         queue input
+ascii <text>        queue a line of text as input
+file <path>         queue the numbers in a file, or its text
+out                 show the output so far
+l, list [addr] [n]  disassemble
+info                show where it's up to
+q, quit";
+
+/// Read commands from `input` until it runs out or says to quit.
+pub fn repl(debugger: &mut Debugger, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+    writeln!(out, "{}", debugger.here())?;
+    write!(out, "> ")?;
+    out.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if matches!(line.trim(), "q" | "quit") {
+            break;
+        }
+        match debugger.command(&line) {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => writeln!(out, "{}", text)?,
+            Err(e) => writeln!(out, "error: {}", e)?,
+        }
+        write!(out, "> ")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble::assemble;
+
+    /// Outputs the sum of each pair of inputs, until a pair adds to 0.
+    fn adder() -> Debugger {
+        let tape = assemble(
+            "
+            start:
+                in -> [a]
+                in -> [b]
+                add [a], [b] -> [sum]
+                out [sum]
+                jnz [sum], #start
+                hlt
+            a: data 0
+            b: data 0
+            sum: data 0
+            ",
+        )
+        .unwrap();
+        Debugger::new(Machine::new(&tape))
+    }
+
+    #[test]
+    fn stepping_and_breakpoints() {
+        let mut debugger = adder();
+        assert_eq!(debugger.step(1), Stop::Machine(Status::NeedsInput));
+        debugger.machine_mut().extend_input(vec![1, 2, 3, 4, 5, -5]);
+        assert_eq!(debugger.step(3), Stop::Stepped);
+        assert_eq!(debugger.machine().ip(), 8);
+
+        debugger.add_breakpoint(Breakpoint::Output);
+        assert_eq!(debugger.resume(), Stop::Breakpoint(Breakpoint::Output));
+        assert_eq!(debugger.output(), &[3]);
+
+        debugger.add_breakpoint(Breakpoint::Address(4));
+        assert_eq!(debugger.resume(), Stop::Breakpoint(Breakpoint::Address(4)));
+        assert_eq!(debugger.remove_breakpoint(0), Some(Breakpoint::Output));
+        debugger.add_breakpoint(Breakpoint::Opcode(Opcode::Halt));
+        // carries on past the breakpoint it's at, and comes round to it again
+        assert_eq!(debugger.resume(), Stop::Breakpoint(Breakpoint::Address(4)));
+        assert_eq!(debugger.output(), &[3, 7]);
+        assert_eq!(
+            debugger.resume(),
+            Stop::Breakpoint(Breakpoint::Opcode(Opcode::Halt))
+        );
+        assert_eq!(debugger.resume(), Stop::Machine(Status::Halted));
+        assert_eq!(debugger.output(), &[3, 7, 0]);
+    }
+
+    #[test]
+    fn watching_and_going_back() {
+        let mut debugger = adder();
+        debugger.machine_mut().extend_input(vec![1, 2, 3, 4]);
+        let sum = 16;
+        debugger.watch(sum);
+        assert_eq!(
+            debugger.resume(),
+            Stop::Watchpoint {
+                addr: sum,
+                old: 0,
+                new: 3
+            }
+        );
+        let before = debugger.machine().clone();
+        let steps = debugger.steps();
+        debugger.step(3);
+        assert_eq!(debugger.output(), &[3]);
+        assert_eq!(debugger.machine().pending_input().len(), 1);
+
+        assert_eq!(debugger.back(3), 3);
+        assert_eq!(debugger.machine(), &before);
+        assert_eq!(debugger.steps(), steps);
+        assert!(debugger.output().is_empty());
+
+        assert_eq!(debugger.back(100), 3);
+        assert_eq!(debugger.machine().pending_input().len(), 4);
+        assert_eq!(debugger.machine().peek(sum), 0);
+        assert_eq!(debugger.machine().ip(), 0);
+    }
+
+    #[test]
+    fn commands() {
+        let mut debugger = adder();
+        let mut run = |line: &str| debugger.command(line).unwrap();
+        assert_eq!(run("list 0 2"), ">    0: in -> [14]\n     2: in -> [15]");
+        assert_eq!(run("in 20, 22"), "input = [20, 22]");
+        assert_eq!(run("break out"), "0: on output");
+        assert_eq!(run("c"), "output 42\n    10: jnz [16], #0");
+        assert_eq!(run("step 2"), "waiting for input\n     0: in -> [14]");
+        assert_eq!(run("ascii *"), "2 input queued");
+        assert_eq!(run("set 15 -42"), "[15] = -42");
+        assert_eq!(run("mem 14 3"), "    14: 20 -42 42");
+        assert_eq!(run("s 4"), "output 52\n    10: jnz [16], #0");
+        assert_eq!(run("out"), "[42, 52]\n*4");
+        assert_eq!(run("back 2"), "back 2\n     4: add [14], [15] -> [16]");
+        assert_eq!(run("rb 7"), "rb = 7");
+        assert!(run("info").starts_with("ip = 4, rb = 7, steps = 7"));
+        assert_eq!(
+            run("pages"),
+            "17 cells, 1 pages of 256 in use (1 dense, 0 sparse), 0 shared\npages 0"
+        );
+        assert!(debugger.command("break op nop").is_err());
+        assert!(debugger.command("mem -1").is_err());
+        assert!(debugger.command("frobnicate").is_err());
+
+        let mut out = vec![];
+        repl(
+            &mut debugger,
+            "delete 0\nip 13\nquit\nhlt\n".as_bytes(),
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "     4: add [14], [15] -> [16]\n> >     13: hlt\n> ");
+    }
+}
@@ -0,0 +1,236 @@
+//! The [`Engine::Decoded`](crate::machine::Engine) interpreter, which decodes each instruction
+//! once and caches it by address.
+//!
+//! A decoded instruction is its raw parameters and a handler written for its exact parameter
+//! modes, so running it doesn't divide out the modes or match on them. Writing to memory drops
+//! whatever was decoded from the cells written to, so self-modifying programs still run the
+//! same as with the reference interpreter. Only instructions in memory's dense pages are
+//! cached, so code far off in a sparse page is decoded every time it runs rather than growing
+//! the cache out to it.
+
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::machine::{Machine, Status};
+use std::fmt;
+use std::sync::Arc;
+
+const POSITION: u8 = 0;
+const IMMEDIATE: u8 = 1;
+const RELATIVE: u8 = 2;
+
+/// The most cells an instruction takes up.
+const MAX_SIZE: usize = 4;
+
+type Outcome = Result<Option<Status>, IntcodeError>;
+type Handler = fn(&mut Machine, &[i64; 3]) -> Outcome;
+
+#[derive(Clone, Copy)]
+pub(crate) struct Decoded {
+    handler: Handler,
+    params: [i64; 3],
+    size: u8,
+}
+
+impl Decoded {
+    /// The instruction at `addr`, or `None` if it isn't valid.
+    pub(crate) fn decode(machine: &Machine, addr: usize) -> Option<Self> {
+        let ins = Instruction::decode(addr, machine.peek(addr)).ok()?;
+        let mut params = [0; 3];
+        for (n, param) in params.iter_mut().enumerate().take(ins.opcode.arity()) {
+            *param = machine.peek(addr + 1 + n);
+        }
+        Some(Decoded {
+            handler: handler(&ins),
+            params,
+            size: ins.size() as u8,
+        })
+    }
+
+    pub(crate) fn execute(&self, machine: &mut Machine) -> Outcome {
+        (self.handler)(machine, &self.params)
+    }
+}
+
+/// Pick the version of `$f` for the modes given, one const parameter per mode.
+macro_rules! specialise {
+    ($f:ident [$($m:ident),*]) => {
+        $f::<$($m),*> as Handler
+    };
+    ($f:ident [$($m:ident),*] $mode:expr $(, $rest:expr)*) => {
+        match $mode {
+            Mode::Position => specialise!($f [$($m,)* POSITION] $($rest),*),
+            Mode::Immediate => specialise!($f [$($m,)* IMMEDIATE] $($rest),*),
+            Mode::Relative => specialise!($f [$($m,)* RELATIVE] $($rest),*),
+        }
+    };
+}
+
+fn handler(ins: &Instruction) -> Handler {
+    let [a, b, c] = ins.modes;
+    match ins.opcode {
+        Opcode::Add => specialise!(add [] a, b, c),
+        Opcode::Mul => specialise!(mul [] a, b, c),
+        Opcode::Input => specialise!(input [] a),
+        Opcode::Output => specialise!(output [] a),
+        Opcode::JumpIfTrue => specialise!(jump_if_true [] a, b),
+        Opcode::JumpIfFalse => specialise!(jump_if_false [] a, b),
+        Opcode::LessThan => specialise!(less_than [] a, b, c),
+        Opcode::Equals => specialise!(equals [] a, b, c),
+        Opcode::AdjustBase => specialise!(adjust_base [] a),
+        Opcode::Halt => halt,
+    }
+}
+
+#[inline(always)]
+fn load<const MODE: u8>(machine: &Machine, raw: i64) -> Result<i64, IntcodeError> {
+    Ok(match MODE {
+        POSITION => machine.peek(machine.address(raw)?),
+        IMMEDIATE => raw,
+        _ => machine.peek(machine.address(machine.relative_base().wrapping_add(raw))?),
+    })
+}
+
+#[inline(always)]
+fn dest<const MODE: u8>(machine: &Machine, raw: i64) -> Result<usize, IntcodeError> {
+    match MODE {
+        POSITION => machine.address(raw),
+        IMMEDIATE => Err(IntcodeError::ImmediateWrite { addr: machine.ip() }),
+        _ => machine.address(machine.relative_base().wrapping_add(raw)),
+    }
+}
+
+#[inline(always)]
+fn advance(machine: &mut Machine, size: usize) -> Outcome {
+    machine.set_ip(machine.ip() + size);
+    Ok(None)
+}
+
+fn add<const A: u8, const B: u8, const C: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    let value = load::<A>(m, p[0])?.wrapping_add(load::<B>(m, p[1])?);
+    m.store(dest::<C>(m, p[2])?, value)?;
+    advance(m, 4)
+}
+
+fn mul<const A: u8, const B: u8, const C: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    let value = load::<A>(m, p[0])?.wrapping_mul(load::<B>(m, p[1])?);
+    m.store(dest::<C>(m, p[2])?, value)?;
+    advance(m, 4)
+}
+
+fn input<const A: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    let dest = dest::<A>(m, p[0])?;
+    match m.read_input() {
+        Some(value) => {
+            if let Err(e) = m.store(dest, value) {
+                m.unread_input(value);
+                return Err(e);
+            }
+        }
+        None => return Ok(Some(Status::NeedsInput)),
+    }
+    advance(m, 2)
+}
+
+fn output<const A: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    let value = load::<A>(m, p[0])?;
+    advance(m, 2)?;
+    Ok(Some(Status::Output(value)))
+}
+
+fn jump_if_true<const A: u8, const B: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    if load::<A>(m, p[0])? != 0 {
+        m.set_ip(m.address(load::<B>(m, p[1])?)?);
+        Ok(None)
+    } else {
+        advance(m, 3)
+    }
+}
+
+fn jump_if_false<const A: u8, const B: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    if load::<A>(m, p[0])? == 0 {
+        m.set_ip(m.address(load::<B>(m, p[1])?)?);
+        Ok(None)
+    } else {
+        advance(m, 3)
+    }
+}
+
+fn less_than<const A: u8, const B: u8, const C: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    let value = (load::<A>(m, p[0])? < load::<B>(m, p[1])?) as i64;
+    m.store(dest::<C>(m, p[2])?, value)?;
+    advance(m, 4)
+}
+
+fn equals<const A: u8, const B: u8, const C: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    let value = (load::<A>(m, p[0])? == load::<B>(m, p[1])?) as i64;
+    m.store(dest::<C>(m, p[2])?, value)?;
+    advance(m, 4)
+}
+
+fn adjust_base<const A: u8>(m: &mut Machine, p: &[i64; 3]) -> Outcome {
+    let base = m.relative_base().wrapping_add(load::<A>(m, p[0])?);
+    m.set_relative_base(base);
+    advance(m, 2)
+}
+
+fn halt(_: &mut Machine, _: &[i64; 3]) -> Outcome {
+    Ok(Some(Status::Halted))
+}
+
+/// The decoded instructions, by address. It's only ever a copy of what's in memory, so it
+/// doesn't count towards two machines being equal. Like memory, it's shared between copies of
+/// a machine until one of them changes it.
+#[derive(Clone, Default)]
+pub(crate) struct Cache(Arc<Vec<Option<Decoded>>>);
+
+impl Cache {
+    pub(crate) fn get(&self, addr: usize) -> Option<Decoded> {
+        self.0.get(addr).copied().flatten()
+    }
+
+    /// How many addresses the cache reaches, decoded or not.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub(crate) fn insert(&mut self, addr: usize, decoded: Decoded) {
+        let cache = Arc::make_mut(&mut self.0);
+        if addr >= cache.len() {
+            cache.resize(addr + 1, None);
+        }
+        cache[addr] = Some(decoded);
+    }
+
+    /// Forget any instruction that `addr` is part of.
+    pub(crate) fn invalidate(&mut self, addr: usize) {
+        let end = (addr + 1).min(self.0.len());
+        let start = (addr + 1).saturating_sub(MAX_SIZE).min(end);
+        let stale = |cache: &[Option<Decoded>], at: usize| matches!(cache[at], Some(decoded) if at + decoded.size as usize > addr);
+        // most writes are to data, so don't copy a shared cache unless something changes
+        if !(start..end).any(|at| stale(&self.0, at)) {
+            return;
+        }
+        let cache = Arc::make_mut(&mut self.0);
+        for at in start..end {
+            if stale(cache, at) {
+                cache[at] = None;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let count = self.0.iter().filter(|decoded| decoded.is_some()).count();
+        write!(f, "Cache({} decoded)", count)
+    }
+}
+
+impl PartialEq for Cache {
+    fn eq(&self, _: &Cache) -> bool {
+        true
+    }
+}
+
+impl Eq for Cache {}
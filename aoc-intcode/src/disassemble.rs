@@ -0,0 +1,347 @@
+//! Listing a program as text, one instruction per line.
+//!
+//! Parameters are shown by mode: `[12]` reads address 12, `#3` is the value 3, and `rb+4` is
+//! 4 past the relative base. A parameter that's written to comes after an arrow, as in
+//! `add [12], #3 -> rb+4`. Only what can be reached from the entry points by following the
+//! jumps is decoded as code, everything else is listed as `data`.
+
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::trace::Trace;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// How many values go on each `data` line.
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Code { ins: Instruction, params: Vec<i64> },
+    Data(Vec<i64>),
+}
+
+impl Item {
+    /// The text of the line, with jumps to any of the `labels` going to the label.
+    pub fn text(&self, labels: &BTreeSet<usize>) -> String {
+        match self {
+            Item::Code { ins, params } => {
+                let operands: Vec<String> = params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &value)| {
+                        let is_label = ins.opcode.jumps()
+                            && i == 1
+                            && ins.modes[i] == Mode::Immediate
+                            && value >= 0
+                            && labels.contains(&(value as usize));
+                        operand(ins.modes[i], value, is_label)
+                    })
+                    .collect();
+                let (reads, writes) = if ins.opcode.writes() {
+                    operands.split_at(operands.len() - 1)
+                } else {
+                    (&operands[..], &[][..])
+                };
+                let mut text = ins.opcode.mnemonic().to_owned();
+                if !reads.is_empty() {
+                    text.push(' ');
+                    text.push_str(&reads.join(", "));
+                }
+                if let Some(dest) = writes.first() {
+                    text.push_str(" -> ");
+                    text.push_str(dest);
+                }
+                text
+            }
+            Item::Data(values) => {
+                let values: Vec<String> = values.iter().map(i64::to_string).collect();
+                format!("data {}", values.join(", "))
+            }
+        }
+    }
+
+    /// How many cells it covers.
+    pub fn size(&self) -> usize {
+        match self {
+            Item::Code { params, .. } => params.len() + 1,
+            Item::Data(values) => values.len(),
+        }
+    }
+}
+
+fn operand(mode: Mode, value: i64, is_label: bool) -> String {
+    match mode {
+        Mode::Position => format!("[{}]", value),
+        Mode::Immediate if is_label => format!("#{}", label(value as usize)),
+        Mode::Immediate => format!("#{}", value),
+        Mode::Relative if value < 0 => format!("rb{}", value),
+        Mode::Relative => format!("rb+{}", value),
+    }
+}
+
+/// The name given to a jump target.
+pub fn label(addr: usize) -> String {
+    format!("l{}", addr)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub addr: usize,
+    pub item: Item,
+    /// How many times the instruction ran, when there were counts to go on.
+    pub count: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Listing {
+    pub lines: Vec<Line>,
+    /// The addresses that are jumped to.
+    pub labels: BTreeSet<usize>,
+}
+
+impl Listing {
+    /// The line covering `addr`.
+    pub fn line_at(&self, addr: usize) -> Option<&Line> {
+        let i = self.lines.partition_point(|line| line.addr <= addr);
+        let line = self.lines.get(i.checked_sub(1)?)?;
+        Some(line).filter(|line| addr < line.addr + line.item.size())
+    }
+}
+
+/// One line per instruction or run of data, with the address and any count in a comment.
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            if self.labels.contains(&line.addr) {
+                writeln!(f, "{}:", label(line.addr))?;
+            }
+            write!(
+                f,
+                "    {:<28} ; {}",
+                line.item.text(&self.labels),
+                line.addr
+            )?;
+            if let Some(count) = line.count {
+                write!(f, " x{}", count)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recursive descent disassembly, starting from address 0 and any other entry points.
+pub struct Disassembler<'a> {
+    tape: &'a [i64],
+    entries: Vec<usize>,
+    counts: Option<&'a Trace>,
+}
+
+impl<'a> Disassembler<'a> {
+    pub fn new(tape: &'a [i64]) -> Self {
+        Disassembler {
+            tape,
+            entries: vec![0],
+            counts: None,
+        }
+    }
+
+    /// Trace from here too, for code that's only reached by computed jumps.
+    pub fn entry(mut self, addr: usize) -> Self {
+        self.entries.push(addr);
+        self
+    }
+
+    /// Annotate the instructions with how many times they ran, from a machine's [`Trace`].
+    /// Everything that ran is traced from as well.
+    pub fn with_counts(mut self, trace: &'a Trace) -> Self {
+        self.counts = Some(trace);
+        self
+    }
+
+    /// The instruction at `addr`, if it's valid and fits in the tape.
+    fn decode(&self, addr: usize) -> Option<Instruction> {
+        let ins = Instruction::decode(addr, *self.tape.get(addr)?).ok()?;
+        Some(ins).filter(|ins| addr + ins.size() <= self.tape.len())
+    }
+
+    /// The start of every instruction reachable from the entry points, and the jump targets.
+    fn trace(&self) -> (BTreeSet<usize>, BTreeSet<usize>) {
+        let mut code = BTreeSet::new();
+        let mut targets = BTreeSet::new();
+        let mut todo = self.entries.clone();
+        todo.extend(
+            self.counts
+                .iter()
+                .flat_map(|trace| trace.counts())
+                .map(|(addr, _)| addr),
+        );
+        while let Some(addr) = todo.pop() {
+            if code.contains(&addr) {
+                continue;
+            }
+            let ins = match self.decode(addr) {
+                Some(ins) => ins,
+                None => continue,
+            };
+            code.insert(addr);
+            let params = &self.tape[addr + 1..addr + ins.size()];
+            let falls_through = match ins.opcode {
+                Opcode::Halt => false,
+                // a constant condition is an unconditional jump
+                Opcode::JumpIfTrue => !(ins.modes[0] == Mode::Immediate && params[0] != 0),
+                Opcode::JumpIfFalse => !(ins.modes[0] == Mode::Immediate && params[0] == 0),
+                _ => true,
+            };
+            if ins.opcode.jumps() && ins.modes[1] == Mode::Immediate && params[1] >= 0 {
+                targets.insert(params[1] as usize);
+                todo.push(params[1] as usize);
+            }
+            if falls_through {
+                todo.push(addr + ins.size());
+            }
+        }
+        (code, targets)
+    }
+
+    pub fn disassemble(&self) -> Listing {
+        let (code, targets) = self.trace();
+        let mut lines = vec![];
+        let mut addr = 0;
+        while addr < self.tape.len() {
+            match self.decode(addr).filter(|_| code.contains(&addr)) {
+                Some(ins) => {
+                    let count = self.counts.map(|trace| trace.count(addr));
+                    let params = self.tape[addr + 1..addr + ins.size()].to_vec();
+                    lines.push(Line {
+                        addr,
+                        item: Item::Code { ins, params },
+                        count,
+                    });
+                    addr += ins.size();
+                }
+                None => {
+                    let next_code = code.range(addr..).next().copied();
+                    let end = next_code
+                        .unwrap_or(self.tape.len())
+                        .min(addr + DATA_PER_LINE);
+                    lines.push(Line {
+                        addr,
+                        item: Item::Data(self.tape[addr..end].to_vec()),
+                        count: None,
+                    });
+                    addr = end;
+                }
+            }
+        }
+        // a target in the middle of an earlier instruction doesn't get a line to label
+        let labels = lines
+            .iter()
+            .filter(|line| matches!(line.item, Item::Code { .. }))
+            .map(|line| line.addr)
+            .filter(|addr| targets.contains(addr))
+            .collect();
+        Listing { lines, labels }
+    }
+}
+
+/// Disassemble from address 0.
+pub fn disassemble(tape: &[i64]) -> Listing {
+    Disassembler::new(tape).disassemble()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::{Machine, Status};
+
+    /// Count down from the input, outputting each number, then some data.
+    const COUNTDOWN: [i64; 18] = [
+        3, 17, 4, 17, 1001, 17, -1, 17, 1005, 17, 2, 1106, 0, 16, 7, 7, 99, 0,
+    ];
+
+    #[test]
+    fn listing() {
+        let listing = disassemble(&COUNTDOWN);
+        assert_eq!(
+            listing.labels.iter().copied().collect::<Vec<_>>(),
+            vec![2, 16]
+        );
+        let expected = "    in -> [17]                   ; 0
+l2:
+    out [17]                     ; 2
+    add [17], #-1 -> [17]        ; 4
+    jnz [17], #l2                ; 8
+    jz #0, #l16                  ; 11
+    data 7, 7                    ; 14
+l16:
+    hlt                          ; 16
+    data 0                       ; 17
+";
+        assert_eq!(listing.to_string(), expected);
+
+        assert_eq!(listing.line_at(9).unwrap().addr, 8);
+        assert_eq!(listing.line_at(15).unwrap().addr, 14);
+        assert_eq!(listing.line_at(18), None);
+
+        let relative = Item::Code {
+            ins: Instruction::decode(0, 22201).unwrap(),
+            params: vec![-1, 3, 4],
+        };
+        assert_eq!(relative.text(&BTreeSet::new()), "add rb-1, rb+3 -> rb+4");
+    }
+
+    #[test]
+    fn execution_counts() {
+        let mut machine = Machine::new(&COUNTDOWN)
+            .with_input(vec![3])
+            .with_trace(Trace::new(0));
+        let mut output = vec![];
+        while let Status::Output(value) = machine.run() {
+            output.push(value);
+        }
+        assert_eq!(output, vec![3, 2, 1]);
+
+        let listing = Disassembler::new(&COUNTDOWN)
+            .with_counts(machine.trace().unwrap())
+            .disassemble();
+        let counts: Vec<_> = listing.lines.iter().map(|line| line.count).collect();
+        assert_eq!(
+            counts,
+            vec![
+                Some(1),
+                Some(3),
+                Some(3),
+                Some(3),
+                Some(1),
+                None,
+                Some(1),
+                None
+            ]
+        );
+        assert!(listing.to_string().contains("; 2 x3\n"));
+    }
+
+    #[test]
+    fn entry_points() {
+        // jumps to wherever the input says, so the halt can't be found without help
+        let tape = [3, 6, 106, 0, 6, 99, 0];
+        let listing = disassemble(&tape);
+        assert_eq!(listing.lines.len(), 3);
+        assert_eq!(listing.lines[1].item.text(&listing.labels), "jz #0, [6]");
+        assert_eq!(listing.lines[2].item, Item::Data(vec![99, 0]));
+
+        let listing = Disassembler::new(&tape).entry(5).disassemble();
+        let texts: Vec<_> = listing
+            .lines
+            .iter()
+            .map(|line| line.item.text(&listing.labels))
+            .collect();
+        assert_eq!(texts, vec!["in -> [6]", "jz #0, [6]", "hlt", "data 0"]);
+
+        // jumps into the middle of itself, so there's no line to put the label on
+        let tape = [1105, 1, 2, 4, 99, 99];
+        let listing = disassemble(&tape);
+        assert!(listing.labels.is_empty());
+        assert_eq!(listing.lines[0].item.text(&listing.labels), "jnz #1, #2");
+    }
+}
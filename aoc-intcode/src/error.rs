@@ -0,0 +1,67 @@
+use std::fmt;
+
+/// Why a program couldn't carry on. `addr` is where the offending instruction is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntcodeError {
+    InvalidOpcode {
+        addr: usize,
+        value: i64,
+    },
+    InvalidMode {
+        addr: usize,
+        value: i64,
+    },
+    /// A parameter that's written to can't be in immediate mode.
+    ImmediateWrite {
+        addr: usize,
+    },
+    NegativeAddress {
+        addr: usize,
+        target: i64,
+    },
+    /// Writing to `target` needed more memory than the machine is allowed.
+    OutOfMemory {
+        addr: usize,
+        target: usize,
+    },
+    /// Waiting for input when the caller expected it to run to the end.
+    InputExhausted {
+        addr: usize,
+    },
+}
+
+impl IntcodeError {
+    pub fn addr(&self) -> usize {
+        use IntcodeError::*;
+        match *self {
+            InvalidOpcode { addr, .. }
+            | InvalidMode { addr, .. }
+            | ImmediateWrite { addr }
+            | NegativeAddress { addr, .. }
+            | OutOfMemory { addr, .. }
+            | InputExhausted { addr } => addr,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use IntcodeError::*;
+        match self {
+            InvalidOpcode { addr, value } => write!(f, "invalid instruction {} at {}", value, addr),
+            InvalidMode { addr, value } => {
+                write!(f, "invalid parameter mode in {} at {}", value, addr)
+            }
+            ImmediateWrite { addr } => write!(f, "writing in immediate mode at {}", addr),
+            NegativeAddress { addr, target } => {
+                write!(f, "negative address {} used at {}", target, addr)
+            }
+            OutOfMemory { addr, target } => {
+                write!(f, "out of memory writing to {} at {}", target, addr)
+            }
+            InputExhausted { addr } => write!(f, "ran out of input at {}", addr),
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
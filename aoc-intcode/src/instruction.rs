@@ -0,0 +1,224 @@
+//! Decoding the opcode and parameter modes packed into an instruction.
+
+use crate::error::IntcodeError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    Add,
+    Mul,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustBase,
+    Halt,
+}
+
+impl Opcode {
+    pub const ALL: [Opcode; 10] = [
+        Opcode::Add,
+        Opcode::Mul,
+        Opcode::Input,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::Equals,
+        Opcode::AdjustBase,
+        Opcode::Halt,
+    ];
+
+    pub fn from_code(code: i64) -> Option<Self> {
+        use Opcode::*;
+        Some(match code {
+            1 => Add,
+            2 => Mul,
+            3 => Input,
+            4 => Output,
+            5 => JumpIfTrue,
+            6 => JumpIfFalse,
+            7 => LessThan,
+            8 => Equals,
+            9 => AdjustBase,
+            99 => Halt,
+            _ => return None,
+        })
+    }
+
+    pub fn code(self) -> i64 {
+        use Opcode::*;
+        match self {
+            Add => 1,
+            Mul => 2,
+            Input => 3,
+            Output => 4,
+            JumpIfTrue => 5,
+            JumpIfFalse => 6,
+            LessThan => 7,
+            Equals => 8,
+            AdjustBase => 9,
+            Halt => 99,
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        use Opcode::*;
+        match self {
+            Add => "add",
+            Mul => "mul",
+            Input => "in",
+            Output => "out",
+            JumpIfTrue => "jnz",
+            JumpIfFalse => "jz",
+            LessThan => "lt",
+            Equals => "eq",
+            AdjustBase => "arb",
+            Halt => "hlt",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        Opcode::ALL
+            .iter()
+            .copied()
+            .find(|op| op.mnemonic() == mnemonic)
+    }
+
+    /// Whether it might not carry on to the next instruction.
+    pub fn jumps(self) -> bool {
+        matches!(self, Opcode::JumpIfTrue | Opcode::JumpIfFalse)
+    }
+
+    /// How many parameters follow the opcode.
+    pub fn arity(self) -> usize {
+        use Opcode::*;
+        match self {
+            Add | Mul | LessThan | Equals => 3,
+            JumpIfTrue | JumpIfFalse => 2,
+            Input | Output | AdjustBase => 1,
+            Halt => 0,
+        }
+    }
+
+    /// Whether the last parameter is an address that gets written to.
+    pub fn writes(self) -> bool {
+        use Opcode::*;
+        matches!(self, Add | Mul | Input | LessThan | Equals)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    pub fn from_digit(digit: i64) -> Option<Self> {
+        match digit {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn digit(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
+
+/// An opcode and the modes of its parameters. Modes past the opcode's arity are `Position`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    /// Split up the `value` found at `addr`.
+    /// Fails if the opcode is unknown, there are modes it doesn't have parameters for,
+    /// or a parameter it writes to is in immediate mode.
+    pub fn decode(addr: usize, value: i64) -> Result<Self, IntcodeError> {
+        let opcode =
+            Opcode::from_code(value % 100).ok_or(IntcodeError::InvalidOpcode { addr, value })?;
+        let mut modes = [Mode::Position; 3];
+        let mut digits = value / 100;
+        for mode in modes.iter_mut().take(opcode.arity()) {
+            *mode =
+                Mode::from_digit(digits % 10).ok_or(IntcodeError::InvalidMode { addr, value })?;
+            digits /= 10;
+        }
+        if digits != 0 {
+            return Err(IntcodeError::InvalidMode { addr, value });
+        }
+        if opcode.writes() && modes[opcode.arity() - 1] == Mode::Immediate {
+            return Err(IntcodeError::ImmediateWrite { addr });
+        }
+        Ok(Instruction { opcode, modes })
+    }
+
+    pub fn encode(&self) -> i64 {
+        self.modes
+            .iter()
+            .rev()
+            .fold(0, |acc, mode| acc * 10 + mode.digit())
+            * 100
+            + self.opcode.code()
+    }
+
+    /// How many cells the instruction and its parameters take up.
+    pub fn size(&self) -> usize {
+        1 + self.opcode.arity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoding() {
+        let add = Instruction::decode(0, 21101).unwrap();
+        assert_eq!(add.opcode, Opcode::Add);
+        assert_eq!(
+            add.modes,
+            [Mode::Immediate, Mode::Immediate, Mode::Relative]
+        );
+        assert_eq!(add.encode(), 21101);
+        assert_eq!(add.size(), 4);
+        assert_eq!(Instruction::decode(0, 99).unwrap().encode(), 99);
+
+        use IntcodeError::*;
+        assert_eq!(
+            Instruction::decode(3, 42),
+            Err(InvalidOpcode { addr: 3, value: 42 })
+        );
+        assert_eq!(
+            Instruction::decode(3, 304),
+            Err(InvalidMode {
+                addr: 3,
+                value: 304
+            })
+        );
+        assert_eq!(
+            Instruction::decode(3, 1104),
+            Err(InvalidMode {
+                addr: 3,
+                value: 1104
+            })
+        );
+        assert_eq!(
+            Instruction::decode(3, 11101),
+            Err(ImmediateWrite { addr: 3 })
+        );
+        assert_eq!(Instruction::decode(3, -1).map(|i| i.opcode).ok(), None);
+    }
+}
@@ -0,0 +1,56 @@
+//! The Intcode computer from Advent of Code 2019.
+//!
+//! A [`Machine`] runs until it has something to say: an output, a request for input it doesn't
+//! have yet, halting, or a [`IntcodeError`] instead of a panic when the program goes wrong.
+
+pub mod ascii;
+pub mod assemble;
+pub mod debugger;
+mod decoded;
+pub mod disassemble;
+pub mod error;
+pub mod instruction;
+pub mod machine;
+pub mod memory;
+pub mod network;
+pub mod trace;
+
+pub use ascii::{AsciiConsole, Reply};
+pub use assemble::{assemble, AssembleError};
+pub use disassemble::{disassemble, Disassembler, Listing};
+pub use error::IntcodeError;
+pub use instruction::{Instruction, Mode, Opcode};
+pub use machine::{Engine, Machine, Snapshot, Status};
+pub use memory::{Memory, MemoryReport};
+pub use network::{Event, Message, Network, Route};
+pub use trace::{Loop, Step, Trace};
+
+use aoc_common::files::Res;
+use std::fs;
+use std::path::Path;
+
+/// Parse a comma separated program.
+pub fn parse_tape(text: &str) -> Res<Vec<i64>> {
+    Ok(text
+        .trim()
+        .split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()?)
+}
+
+/// Read a program from a file.
+pub fn read_tape<P: AsRef<Path>>(file: P) -> Res<Vec<i64>> {
+    parse_tape(&fs::read_to_string(file)?)
+}
+
+/// Read a program's input from a file: numbers separated by commas or whitespace, or if it
+/// isn't that, text for an ASCII program.
+pub fn read_input<P: AsRef<Path>>(file: P) -> Res<Vec<i64>> {
+    let text = fs::read_to_string(file)?;
+    let numbers: Result<Vec<i64>, _> = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(str::parse)
+        .collect();
+    Ok(numbers.unwrap_or_else(|_| text.bytes().map(i64::from).collect()))
+}
@@ -0,0 +1,785 @@
+use crate::decoded::{Cache, Decoded};
+use crate::error::IntcodeError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::memory::{Memory, MemoryFull, PAGE_SIZE};
+use crate::trace::{Trace, Tracing};
+use std::cell::Cell;
+use std::collections::VecDeque;
+
+/// Why a [`Machine`] stopped running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// Blocked on an input instruction with nothing queued. Queue some and run again.
+    NeedsInput,
+    Output(i64),
+    Halted,
+    /// The program went wrong. The machine stays on the offending instruction.
+    Fault(IntcodeError),
+}
+
+/// How [`Machine::run`] executes instructions. Both give the same results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Engine {
+    /// Decode each instruction every time it's executed, as [`Machine::step`] does.
+    Reference,
+    /// Decode each instruction once, and cache it until its memory is written to.
+    Decoded,
+}
+
+thread_local! {
+    static DEFAULT_ENGINE: Cell<Engine> = const { Cell::new(Engine::Decoded) };
+}
+
+impl Engine {
+    /// Run `f` with this as the engine machines made on this thread use, to compare the
+    /// engines on whole solutions. The default goes back to what it was afterwards.
+    pub fn as_default<T>(self, f: impl FnOnce() -> T) -> T {
+        struct Restore(Engine);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                DEFAULT_ENGINE.with(|engine| engine.set(self.0));
+            }
+        }
+
+        let _restore = Restore(DEFAULT_ENGINE.with(|engine| engine.replace(self)));
+        f()
+    }
+}
+
+/// [`Engine::Decoded`], unless inside [`Engine::as_default`].
+impl Default for Engine {
+    fn default() -> Self {
+        DEFAULT_ENGINE.with(Cell::get)
+    }
+}
+
+/// A program's memory, where it's up to, and the input it hasn't read yet.
+/// Memory grows on writes past the end, and reads past the end are 0.
+///
+/// Cloning a machine is cheap, as the clones share memory until they write to it, so it can
+/// be forked to try out different inputs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Machine {
+    mem: Memory,
+    ip: usize,
+    relative_base: i64,
+    input: VecDeque<i64>,
+    engine: Engine,
+    cache: Cache,
+    tracing: Tracing,
+}
+
+/// A machine's state, from [`Machine::snapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot(Machine);
+
+impl Machine {
+    pub fn new(tape: &[i64]) -> Self {
+        Machine {
+            mem: Memory::new(tape),
+            ..Machine::default()
+        }
+    }
+
+    pub fn with_input(mut self, input: impl IntoIterator<Item = i64>) -> Self {
+        self.input.extend(input);
+        self
+    }
+
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    /// Record everything the program does from now on. It runs on the reference interpreter
+    /// while it's traced, whatever its engine.
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.tracing = Tracing(Some(Box::new(trace)));
+        self
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.tracing.0.as_deref()
+    }
+
+    /// Stop tracing, and hand back what was recorded.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.tracing.0.take().map(|trace| *trace)
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    pub fn extend_input(&mut self, input: impl IntoIterator<Item = i64>) {
+        self.input.extend(input);
+    }
+
+    /// Put a value back on the front of the input queue, as if it had never been read.
+    pub fn unread_input(&mut self, value: i64) {
+        self.input.push_front(value);
+    }
+
+    /// The input queued but not read yet.
+    pub fn pending_input(&self) -> &VecDeque<i64> {
+        &self.input
+    }
+
+    pub(crate) fn read_input(&mut self) -> Option<i64> {
+        self.input.pop_front()
+    }
+
+    /// The address of the next instruction.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Carry on from somewhere else.
+    pub fn set_ip(&mut self, ip: usize) {
+        self.ip = ip;
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.mem
+    }
+
+    pub fn peek(&self, addr: usize) -> i64 {
+        self.mem.get(addr)
+    }
+
+    /// Change memory from outside the program, which isn't held to any memory limit.
+    pub fn poke(&mut self, addr: usize, value: i64) {
+        self.mem.set(addr, value);
+        self.cache.invalidate(addr);
+    }
+
+    /// Fault when a write by the program would take its memory over `cells`, counting in
+    /// whole pages of [`PAGE_SIZE`](crate::memory::PAGE_SIZE). There's no limit by default.
+    pub fn with_memory_limit(mut self, cells: usize) -> Self {
+        self.mem.set_limit(Some(cells));
+        self
+    }
+
+    /// A write by the program, which is held to the memory limit.
+    pub(crate) fn store(&mut self, addr: usize, value: i64) -> Result<(), IntcodeError> {
+        self.mem
+            .try_set(addr, value)
+            .map_err(|MemoryFull| IntcodeError::OutOfMemory {
+                addr: self.ip,
+                target: addr,
+            })?;
+        self.cache.invalidate(addr);
+        Ok(())
+    }
+
+    /// Everything about the machine as it is now, to go back to later.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.clone())
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.clone_from(&snapshot.0);
+    }
+
+    /// A copy of the machine with `input` in place of whatever input it had, for running the
+    /// same program on lots of different inputs. It starts with everything this machine has
+    /// decoded, so [`Machine::predecode`] the program first.
+    pub fn spawn_with_input(&self, input: impl IntoIterator<Item = i64>) -> Machine {
+        let mut machine = self.clone();
+        machine.input = input.into_iter().collect();
+        machine
+    }
+
+    /// Decode whatever could be an instruction now, rather than as they're run. Anything that
+    /// turns out to be data is forgotten when it's written to, like any other instruction.
+    /// Only the dense pages that have been written to are looked at.
+    pub fn predecode(&mut self) {
+        let pages: Vec<usize> = self.mem.dense_pages().collect();
+        for n in pages {
+            for addr in n * PAGE_SIZE..(n + 1) * PAGE_SIZE {
+                if let Some(decoded) = Decoded::decode(self, addr) {
+                    self.cache.insert(addr, decoded);
+                }
+            }
+        }
+    }
+
+    /// The next instruction, if it's valid.
+    pub fn instruction(&self) -> Result<Instruction, IntcodeError> {
+        Instruction::decode(self.ip, self.peek(self.ip))
+    }
+
+    /// Where the next instruction will write to, if it writes anywhere.
+    pub fn destination(&self) -> Option<usize> {
+        let ins = self.instruction().ok()?;
+        if ins.opcode.writes() {
+            self.dest(&ins, ins.opcode.arity() - 1).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Execute one instruction, returning a status if it's something the caller needs to act
+    /// on and `None` if the machine can carry on.
+    pub fn step(&mut self) -> Option<Status> {
+        if let Some(mut trace) = self.tracing.0.take() {
+            let status = trace.step(self);
+            self.tracing.0 = Some(trace);
+            return status;
+        }
+        match self.execute() {
+            Ok(status) => status,
+            Err(e) => Some(Status::Fault(e)),
+        }
+    }
+
+    /// Execute instructions until the next output, input that isn't queued, halt or fault.
+    pub fn run(&mut self) -> Status {
+        match self.engine {
+            _ if self.tracing.0.is_some() => loop {
+                if let Some(status) = self.step() {
+                    return status;
+                }
+            },
+            Engine::Reference => loop {
+                if let Some(status) = self.step() {
+                    return status;
+                }
+            },
+            Engine::Decoded => loop {
+                let decoded = match self.cache.get(self.ip) {
+                    Some(decoded) => decoded,
+                    None => match Decoded::decode(self, self.ip) {
+                        Some(decoded) => {
+                            if self.ip < self.mem.dense_len() {
+                                self.cache.insert(self.ip, decoded);
+                            }
+                            decoded
+                        }
+                        // let the reference interpreter report what's wrong with it
+                        None => match self.step() {
+                            Some(status) => return status,
+                            None => continue,
+                        },
+                    },
+                };
+                match decoded.execute(self) {
+                    Ok(None) => {}
+                    Ok(Some(status)) => return status,
+                    Err(e) => return Status::Fault(e),
+                }
+            },
+        }
+    }
+
+    /// [`Machine::run`], asking `input` for values when the queue is empty.
+    /// Returns `NeedsInput` once `input` has nothing more to give.
+    pub fn run_with(&mut self, mut input: impl FnMut() -> Option<i64>) -> Status {
+        loop {
+            match self.run() {
+                Status::NeedsInput => match input() {
+                    Some(value) => self.push_input(value),
+                    None => return Status::NeedsInput,
+                },
+                status => return status,
+            }
+        }
+    }
+
+    /// The next output, or `None` if the program halts first.
+    /// Needing input that isn't queued is an error.
+    pub fn next_output(&mut self) -> Result<Option<i64>, IntcodeError> {
+        match self.run() {
+            Status::Output(value) => Ok(Some(value)),
+            Status::Halted => Ok(None),
+            Status::NeedsInput => Err(IntcodeError::InputExhausted { addr: self.ip }),
+            Status::Fault(e) => Err(e),
+        }
+    }
+
+    /// Run until the program halts, blocks on input, or faults, and return everything it
+    /// output on the way along with the status it stopped with.
+    pub fn run_until_blocked(&mut self) -> (Vec<i64>, Status) {
+        let mut output = vec![];
+        loop {
+            match self.run() {
+                Status::Output(value) => output.push(value),
+                status => return (output, status),
+            }
+        }
+    }
+
+    /// Run to the end, returning all the output. Needing input that isn't queued is an error.
+    pub fn run_to_halt(&mut self) -> Result<Vec<i64>, IntcodeError> {
+        match self.run_until_blocked() {
+            (output, Status::Halted) => Ok(output),
+            (_, Status::Fault(e)) => Err(e),
+            _ => Err(IntcodeError::InputExhausted { addr: self.ip }),
+        }
+    }
+
+    fn execute(&mut self) -> Result<Option<Status>, IntcodeError> {
+        let ins = self.instruction()?;
+        let mut next = self.ip + ins.size();
+        match ins.opcode {
+            Opcode::Add => {
+                let value = self.arg(&ins, 0)?.wrapping_add(self.arg(&ins, 1)?);
+                self.write(&ins, 2, value)?;
+            }
+            Opcode::Mul => {
+                let value = self.arg(&ins, 0)?.wrapping_mul(self.arg(&ins, 1)?);
+                self.write(&ins, 2, value)?;
+            }
+            Opcode::Input => {
+                // work out where it goes first, so a fault doesn't lose the input
+                let dest = self.dest(&ins, 0)?;
+                match self.input.pop_front() {
+                    Some(value) => {
+                        if let Err(e) = self.store(dest, value) {
+                            self.unread_input(value);
+                            return Err(e);
+                        }
+                    }
+                    None => return Ok(Some(Status::NeedsInput)),
+                }
+            }
+            Opcode::Output => {
+                let value = self.arg(&ins, 0)?;
+                self.ip = next;
+                return Ok(Some(Status::Output(value)));
+            }
+            Opcode::JumpIfTrue => {
+                if self.arg(&ins, 0)? != 0 {
+                    next = self.address(self.arg(&ins, 1)?)?;
+                }
+            }
+            Opcode::JumpIfFalse => {
+                if self.arg(&ins, 0)? == 0 {
+                    next = self.address(self.arg(&ins, 1)?)?;
+                }
+            }
+            Opcode::LessThan => {
+                let value = (self.arg(&ins, 0)? < self.arg(&ins, 1)?) as i64;
+                self.write(&ins, 2, value)?;
+            }
+            Opcode::Equals => {
+                let value = (self.arg(&ins, 0)? == self.arg(&ins, 1)?) as i64;
+                self.write(&ins, 2, value)?;
+            }
+            Opcode::AdjustBase => {
+                self.relative_base = self.relative_base.wrapping_add(self.arg(&ins, 0)?);
+            }
+            Opcode::Halt => return Ok(Some(Status::Halted)),
+        }
+        self.ip = next;
+        Ok(None)
+    }
+
+    pub(crate) fn address(&self, target: i64) -> Result<usize, IntcodeError> {
+        if target < 0 {
+            Err(IntcodeError::NegativeAddress {
+                addr: self.ip,
+                target,
+            })
+        } else {
+            Ok(target as usize)
+        }
+    }
+
+    /// The value of parameter `n`.
+    fn arg(&self, ins: &Instruction, n: usize) -> Result<i64, IntcodeError> {
+        let raw = self.peek(self.ip + 1 + n);
+        Ok(match ins.modes[n] {
+            Mode::Position => self.peek(self.address(raw)?),
+            Mode::Immediate => raw,
+            Mode::Relative => self.peek(self.address(self.relative_base.wrapping_add(raw))?),
+        })
+    }
+
+    /// The address parameter `n` says to write to.
+    fn dest(&self, ins: &Instruction, n: usize) -> Result<usize, IntcodeError> {
+        let raw = self.peek(self.ip + 1 + n);
+        match ins.modes[n] {
+            Mode::Position => self.address(raw),
+            Mode::Immediate => Err(IntcodeError::ImmediateWrite { addr: self.ip }),
+            Mode::Relative => self.address(self.relative_base.wrapping_add(raw)),
+        }
+    }
+
+    fn write(&mut self, ins: &Instruction, n: usize, value: i64) -> Result<(), IntcodeError> {
+        let dest = self.dest(ins, n)?;
+        self.store(dest, value)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble::assemble;
+
+    /// Run with both engines, checking they end up the same.
+    fn run(source: &str, input: Vec<i64>) -> Result<Vec<i64>, IntcodeError> {
+        let tape = assemble(source).unwrap();
+        let mut reference = Machine::new(&tape)
+            .with_engine(Engine::Reference)
+            .with_input(input.clone());
+        let mut decoded = Machine::new(&tape)
+            .with_engine(Engine::Decoded)
+            .with_input(input);
+        let output = decoded.run_to_halt();
+        assert_eq!(reference.run_to_halt(), output);
+        assert_eq!(reference.memory(), decoded.memory());
+        assert_eq!(reference.ip(), decoded.ip());
+        output
+    }
+
+    #[test]
+    fn produce_copy() {
+        let input = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut machine = Machine::new(&input);
+        assert_eq!(machine.run_to_halt(), Ok(input));
+    }
+
+    #[test]
+    fn output_long_number() {
+        let input = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
+        let mut machine = Machine::new(&input);
+        let length = format!("{}", machine.next_output().unwrap().unwrap()).len();
+        assert_eq!(length, 16);
+    }
+
+    #[test]
+    fn output_middle() {
+        let input = vec![104, 1125899906842624, 99];
+        let mut machine = Machine::new(&input);
+        assert_eq!(machine.run(), Status::Output(input[1]));
+        assert_eq!(machine.run(), Status::Halted);
+        assert_eq!(machine.run(), Status::Halted);
+    }
+
+    #[test]
+    fn increases_relative_base() {
+        let input = vec![109, 19];
+        let mut machine = Machine::new(&input);
+        machine.set_relative_base(2000);
+        assert_eq!(machine.step(), None);
+        assert_eq!(machine.relative_base(), 2019);
+    }
+
+    #[test]
+    fn input_from_queue_and_callback() {
+        // add two inputs and output the sum, until given a zero
+        let input = vec![3, 20, 1006, 20, 13, 3, 21, 1, 20, 21, 22, 4, 22, 99];
+        let mut machine = Machine::new(&input).with_input(vec![3]);
+        assert_eq!(machine.run(), Status::NeedsInput);
+        assert_eq!(machine.ip(), 5);
+        machine.push_input(4);
+        assert_eq!(machine.run(), Status::Output(7));
+        assert_eq!(machine.run(), Status::Halted);
+
+        let mut values = vec![5, 6].into_iter();
+        let mut machine = Machine::new(&input);
+        assert_eq!(machine.run_with(|| values.next()), Status::Output(11));
+
+        let mut machine = Machine::new(&input).with_input(vec![0]);
+        assert_eq!(machine.run_to_halt(), Ok(vec![]));
+        assert_eq!(
+            Machine::new(&input).run_to_halt(),
+            Err(IntcodeError::InputExhausted { addr: 0 })
+        );
+    }
+
+    #[test]
+    fn faults() {
+        use IntcodeError::*;
+        let mut machine = Machine::new(&[1, 0, 0, 0, 42]);
+        assert_eq!(
+            machine.run(),
+            Status::Fault(InvalidOpcode { addr: 4, value: 42 })
+        );
+        // stays put
+        assert_eq!(machine.ip(), 4);
+        assert_eq!(machine.peek(0), 2);
+        assert_eq!(
+            machine.run(),
+            Status::Fault(InvalidOpcode { addr: 4, value: 42 })
+        );
+
+        let mut machine = Machine::new(&[11101, 1, 1, 0, 99]);
+        assert_eq!(machine.run(), Status::Fault(ImmediateWrite { addr: 0 }));
+
+        let mut machine = Machine::new(&[4, -3, 99]);
+        assert_eq!(
+            machine.run(),
+            Status::Fault(NegativeAddress {
+                addr: 0,
+                target: -3
+            })
+        );
+
+        // runs off the end into zeroes
+        let mut machine = Machine::new(&[104, 1]);
+        assert_eq!(machine.next_output(), Ok(Some(1)));
+        assert_eq!(
+            machine.next_output(),
+            Err(InvalidOpcode { addr: 2, value: 0 })
+        );
+
+        // reads past the end are 0, writes past the end grow memory
+        let mut machine = Machine::new(&[1, 1000, 1, 10, 99]);
+        assert_eq!(machine.run_to_halt(), Ok(vec![]));
+        assert_eq!(machine.memory().len(), 11);
+        assert_eq!(machine.peek(10), 1000);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let source = "
+                in -> [x]
+                mul [x], #3 -> rb+0
+                add rb+0, #-1 -> [x]
+                out [x]
+                hlt
+            x:  data 0
+        ";
+        assert_eq!(run(source, vec![5]), Ok(vec![14]));
+        assert_eq!(run(source, vec![-2]), Ok(vec![-7]));
+        // rb is 0, so rb+0 is the first instruction
+        let overwritten = "mul #2, #2 -> rb+0\nout [0]\nhlt";
+        assert_eq!(run(overwritten, vec![]), Ok(vec![4]));
+    }
+
+    #[test]
+    fn comparisons() {
+        let source = "
+            in -> [x]
+            lt [x], #8 -> [y]
+            out [y]
+            eq #8, [x] -> [y]
+            out [y]
+            hlt
+            x: data 0
+            y: data 0
+        ";
+        assert_eq!(run(source, vec![7]), Ok(vec![1, 0]));
+        assert_eq!(run(source, vec![8]), Ok(vec![0, 1]));
+        assert_eq!(run(source, vec![9]), Ok(vec![0, 0]));
+    }
+
+    #[test]
+    fn jumps() {
+        // outputs 1 for a non-zero input then 2 for a zero input
+        let source = "
+                in -> [x]
+                jnz [x], #nonzero
+                jz [x], [zero_addr]
+                hlt
+            nonzero:
+                out #1
+                hlt
+            zero:
+                out #2
+                hlt
+            zero_addr: data zero
+            x: data 0
+        ";
+        assert_eq!(run(source, vec![3]), Ok(vec![1]));
+        assert_eq!(run(source, vec![0]), Ok(vec![2]));
+        assert_eq!(
+            run("jnz #1, #-1", vec![]),
+            Err(IntcodeError::NegativeAddress {
+                addr: 0,
+                target: -1
+            })
+        );
+    }
+
+    #[test]
+    fn relative_base() {
+        let source = "
+                arb #values
+                out rb+0
+                arb #2
+                out rb-1
+                in -> rb+0
+                out [after]
+                hlt
+            values: data 10, 11
+            after:
+        ";
+        assert_eq!(run(source, vec![12]), Ok(vec![10, 11, 12]));
+        assert_eq!(
+            run("arb #-5\nout rb+0", vec![]),
+            Err(IntcodeError::NegativeAddress {
+                addr: 2,
+                target: -5
+            })
+        );
+    }
+
+    #[test]
+    fn snapshots_and_spawning() {
+        // doubles each input
+        let tape =
+            assemble("loop: in -> [x]\nmul [x], #2 -> [x]\nout [x]\njz #0, #loop\nx: data 0");
+        let mut machine = Machine::new(&tape.unwrap()).with_input(vec![1, 2]);
+        machine.predecode();
+        // the 4 instructions, and the 2 parameter which looks like a mul
+        assert_eq!(format!("{:?}", machine.cache), "Cache(5 decoded)");
+
+        let snapshot = machine.snapshot();
+        assert_eq!(machine.run(), Status::Output(2));
+        // writing x copied the page, so it isn't shared with the snapshot any more
+        assert_eq!(machine.memory().report().shared, 0);
+        let forked = machine.clone();
+        assert_eq!(machine.run(), Status::Output(4));
+        assert_eq!(machine.run(), Status::NeedsInput);
+        machine.restore(&snapshot);
+        assert_eq!(machine.run(), Status::Output(2));
+        assert_eq!(machine, forked);
+
+        let mut probe = machine.spawn_with_input(vec![21]);
+        assert_eq!(probe.memory().report().shared, 1);
+        assert_eq!(probe.run(), Status::Output(42));
+        assert_eq!(probe.run(), Status::NeedsInput);
+        assert_eq!(machine.pending_input(), &[2]);
+    }
+
+    #[test]
+    fn default_engine() {
+        assert_eq!(Machine::new(&[99]).engine, Engine::Decoded);
+        let engines = Engine::Reference.as_default(|| {
+            let inner = Engine::Decoded.as_default(|| Machine::new(&[99]).engine);
+            (Machine::new(&[99]).engine, inner)
+        });
+        assert_eq!(engines, (Engine::Reference, Engine::Decoded));
+        assert_eq!(Machine::new(&[99]).engine, Engine::Decoded);
+
+        // other threads keep their own default
+        let other = Engine::Reference.as_default(|| {
+            std::thread::spawn(|| Machine::new(&[99]).engine)
+                .join()
+                .unwrap()
+        });
+        assert_eq!(other, Engine::Decoded);
+    }
+
+    #[test]
+    fn patching_a_shared_cache() {
+        // the input is written over the out's opcode, so 104 leaves it as `out #2`, and 4
+        // makes it `out [2]`, which reads the 4 back
+        let source = "in -> [patch]\npatch: out #2\nhlt";
+        let mut machine = Machine::new(&assemble(source).unwrap()).with_engine(Engine::Decoded);
+        machine.predecode();
+
+        let mut patched = machine.spawn_with_input(vec![4]);
+        assert_eq!(patched.run(), Status::Output(4));
+        // the copies that share the cache still have the instruction as it was
+        let mut unpatched = machine.spawn_with_input(vec![104]);
+        assert_eq!(unpatched.run(), Status::Output(2));
+        assert_eq!(machine.spawn_with_input(vec![4]).run(), Status::Output(4));
+        assert_eq!(run(source, vec![4]), Ok(vec![4]));
+    }
+
+    #[test]
+    fn far_off_memory() {
+        use crate::memory::PAGE_SIZE;
+
+        // which only takes one more page
+        let source = "add #1, #2 -> [1000000000]\nout [1000000000]\nin -> [2000000000]\nhlt";
+        assert_eq!(run(source, vec![4]), Ok(vec![3]));
+
+        let tape = assemble(source).unwrap();
+        for &engine in [Engine::Reference, Engine::Decoded].iter() {
+            let limited = |pages| {
+                Machine::new(&tape)
+                    .with_engine(engine)
+                    .with_input(vec![4])
+                    .with_memory_limit(pages * PAGE_SIZE)
+            };
+            let mut machine = limited(1);
+            let fault = IntcodeError::OutOfMemory {
+                addr: 0,
+                target: 1_000_000_000,
+            };
+            assert_eq!(machine.run(), Status::Fault(fault));
+
+            let mut machine = limited(2);
+            assert_eq!(machine.run(), Status::Output(3));
+            let fault = IntcodeError::OutOfMemory {
+                addr: 6,
+                target: 2_000_000_000,
+            };
+            assert_eq!(machine.run(), Status::Fault(fault));
+            // the input's still there for after the limit's sorted out
+            assert_eq!(machine.pending_input(), &[4]);
+            assert_eq!(machine.memory().report().touched, vec![0, 3906250]);
+        }
+    }
+
+    #[test]
+    fn far_off_code() {
+        // a halt written a long way off, and jumped to
+        let tape = assemble("add #99, #0 -> [100000000]\njz #0, #100000000").unwrap();
+        let mut machine = Machine::new(&tape)
+            .with_engine(Engine::Decoded)
+            .with_memory_limit(2 * PAGE_SIZE);
+        assert_eq!(machine.run(), Status::Halted);
+        assert_eq!(machine.ip(), 100_000_000);
+        // the far page isn't cached, so the cache stays the size of the program's page
+        assert!(machine.cache.len() <= PAGE_SIZE);
+        machine.predecode();
+        assert!(machine.cache.len() <= PAGE_SIZE);
+
+        // both engines run code written far off the same way
+        let source = "
+            add #104, #0 -> [1000000000]
+            add #7, #0 -> [1000000001]
+            add #99, #0 -> [1000000002]
+            jz #0, #1000000000
+        ";
+        assert_eq!(run(source, vec![]), Ok(vec![7]));
+    }
+
+    #[test]
+    fn self_modifying() {
+        // points the output at the data, which is at 5 once it's been rewritten
+        let source = "
+                add #value, #0 -> [5]
+                out [0]
+                hlt
+            value: data 42
+        ";
+        assert_eq!(run(source, vec![]), Ok(vec![42]));
+
+        // rewrites an instruction after it's been run, and so decoded, once already
+        let source = "
+            loop:
+                data 4, a ; out [a], and then the ones after
+                add [1], #1 -> [1]
+                eq [1], #end -> [t]
+                jz [t], #loop
+                add #99, #0 -> [loop]
+                jz #0, #loop
+            a: data 7, 8, 9
+            end:
+            t: data 0
+        ";
+        assert_eq!(run(source, vec![]), Ok(vec![7, 8, 9]));
+    }
+}
@@ -0,0 +1,328 @@
+//! A machine's memory, split into pages that are shared between copies of the machine until
+//! one of them writes to it.
+//!
+//! Pages from address 0 up to a little past the highest address written are kept in order,
+//! as programs mostly use the memory just past the end of themselves. Pages further out than
+//! that are kept by number, so writing to address 10^9 only takes one page.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+pub const PAGE_SIZE: usize = 256;
+
+/// How far past the end of the dense pages a write can be and still extend them, in pages.
+const DENSE_GAP: usize = 16;
+
+type Page = [i64; PAGE_SIZE];
+
+/// A write needed a new page, and there were already as many as the limit allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryFull;
+
+/// Memory that grows on writes past the end, where reads of anything not written are 0.
+/// Cloning it only clones the page pointers, and a page is copied the first time it's written
+/// to while shared.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Memory {
+    /// Pages from address 0, with `None` for any that haven't been written to.
+    dense: Vec<Option<Arc<Page>>>,
+    /// Pages past the dense ones, by page number.
+    sparse: BTreeMap<usize, Arc<Page>>,
+    len: usize,
+    /// How many pages have been written to.
+    pages: usize,
+    /// The most pages there can be.
+    limit: Option<usize>,
+}
+
+impl Memory {
+    pub fn new(values: &[i64]) -> Self {
+        let dense: Vec<_> = values
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Some(Arc::new(page))
+            })
+            .collect();
+        Memory {
+            pages: dense.len(),
+            dense,
+            len: values.len(),
+            ..Memory::default()
+        }
+    }
+
+    /// Limit how much memory can be used, in cells. It's rounded up to whole pages, and
+    /// doesn't stop writes to pages already in use.
+    pub fn set_limit(&mut self, cells: Option<usize>) {
+        self.limit = cells.map(|cells| cells.div_ceil(PAGE_SIZE));
+    }
+
+    /// One past the highest address that's been written to.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many cells the dense pages cover, from address 0.
+    pub(crate) fn dense_len(&self) -> usize {
+        self.dense.len() * PAGE_SIZE
+    }
+
+    /// The numbers of the dense pages that have been written to.
+    pub(crate) fn dense_pages(&self) -> impl Iterator<Item = usize> + '_ {
+        self.dense
+            .iter()
+            .enumerate()
+            .filter_map(|(n, page)| page.as_ref().map(|_| n))
+    }
+
+    #[inline]
+    pub fn get(&self, addr: usize) -> i64 {
+        let (n, offset) = (addr / PAGE_SIZE, addr % PAGE_SIZE);
+        match self.dense.get(n) {
+            Some(Some(page)) => page[offset],
+            Some(None) => 0,
+            None => self.sparse.get(&n).map_or(0, |page| page[offset]),
+        }
+    }
+
+    /// Write to memory, whatever the limit is.
+    pub fn set(&mut self, addr: usize, value: i64) {
+        self.page_mut(addr / PAGE_SIZE, false).unwrap()[addr % PAGE_SIZE] = value;
+        self.len = self.len.max(addr + 1);
+    }
+
+    /// Write to memory, unless it'd take a page over the limit.
+    pub fn try_set(&mut self, addr: usize, value: i64) -> Result<(), MemoryFull> {
+        self.page_mut(addr / PAGE_SIZE, true)?[addr % PAGE_SIZE] = value;
+        self.len = self.len.max(addr + 1);
+        Ok(())
+    }
+
+    /// Page `n`, ready to write to, adding it if it isn't there yet.
+    fn page_mut(&mut self, n: usize, limited: bool) -> Result<&mut Page, MemoryFull> {
+        if n >= self.dense.len() && n < self.dense.len() + DENSE_GAP {
+            // take in any sparse pages the dense ones now reach
+            let start = self.dense.len();
+            self.dense.resize(n + 1, None);
+            let reached: Vec<usize> = self.sparse.range(start..=n).map(|(&m, _)| m).collect();
+            for m in reached {
+                self.dense[m] = self.sparse.remove(&m);
+            }
+        }
+
+        let full = limited && self.limit.is_some_and(|limit| self.pages >= limit);
+        let page = match self.dense.get_mut(n) {
+            Some(Some(page)) => page,
+            Some(None) if full => return Err(MemoryFull),
+            Some(page @ None) => {
+                self.pages += 1;
+                page.insert(Arc::new([0; PAGE_SIZE]))
+            }
+            None => {
+                if !self.sparse.contains_key(&n) {
+                    if full {
+                        return Err(MemoryFull);
+                    }
+                    self.pages += 1;
+                }
+                self.sparse
+                    .entry(n)
+                    .or_insert_with(|| Arc::new([0; PAGE_SIZE]))
+            }
+        };
+        Ok(Arc::make_mut(page))
+    }
+
+    /// The dense part of memory, from address 0 up to the end or the first far off page.
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        let end = self.len.min(self.dense.len() * PAGE_SIZE);
+        self.dense
+            .iter()
+            .flat_map(|page| (0..PAGE_SIZE).map(move |i| page.as_ref().map_or(0, |page| page[i])))
+            .take(end)
+    }
+
+    pub fn to_vec(&self) -> Vec<i64> {
+        self.iter().collect()
+    }
+
+    /// Which pages have been written to, and how many are shared with another machine.
+    pub fn report(&self) -> MemoryReport {
+        let dense = self
+            .dense
+            .iter()
+            .enumerate()
+            .filter_map(|(n, page)| Some((n, page.as_ref()?)));
+        let pages: Vec<(usize, &Arc<Page>)> = dense
+            .chain(self.sparse.iter().map(|(&n, page)| (n, page)))
+            .collect();
+        MemoryReport {
+            len: self.len,
+            dense: self.dense.len(),
+            sparse: self.sparse.len(),
+            shared: pages
+                .iter()
+                .filter(|(_, page)| Arc::strong_count(page) > 1)
+                .count(),
+            touched: pages.iter().map(|&(n, _)| n).collect(),
+            limit: self.limit,
+        }
+    }
+}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()?;
+        if !self.sparse.is_empty() {
+            write!(f, " and pages {:?}", self.sparse.keys().collect::<Vec<_>>())?;
+        }
+        Ok(())
+    }
+}
+
+/// How a machine's memory is being used, in pages of [`PAGE_SIZE`] cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// One past the highest address written to.
+    pub len: usize,
+    /// Pages kept in order from address 0, whether they've been written to or not.
+    pub dense: usize,
+    /// Pages kept by number, past the dense ones.
+    pub sparse: usize,
+    /// Pages shared with another machine, until one of them writes to it.
+    pub shared: usize,
+    /// The numbers of the pages that have been written to.
+    pub touched: Vec<usize>,
+    /// The most pages there can be.
+    pub limit: Option<usize>,
+}
+
+/// A summary, then the touched pages as ranges: `pages 0-3, 7`.
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} cells, {} pages of {} in use ({} dense, {} sparse), {} shared",
+            self.len,
+            self.touched.len(),
+            PAGE_SIZE,
+            self.dense,
+            self.sparse,
+            self.shared
+        )?;
+        if let Some(limit) = self.limit {
+            write!(f, ", limit {} pages", limit)?;
+        }
+
+        let mut ranges: Vec<(usize, usize)> = vec![];
+        for &n in &self.touched {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == n => *end = n,
+                _ => ranges.push((n, n)),
+            }
+        }
+        let ranges: Vec<String> = ranges
+            .into_iter()
+            .map(|(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{}-{}", start, end)
+                }
+            })
+            .collect();
+        if !ranges.is_empty() {
+            write!(f, "\npages {}", ranges.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_on_write() {
+        let mut memory = Memory::new(&(0..600).collect::<Vec<_>>());
+        assert_eq!(memory.len(), 600);
+        assert_eq!(memory.report().touched, vec![0, 1, 2]);
+        assert_eq!(memory.report().shared, 0);
+        assert_eq!(memory.get(300), 300);
+        assert_eq!(memory.get(600), 0);
+
+        let mut copy = memory.clone();
+        assert_eq!(memory.report().shared, 3);
+        copy.set(1, -1);
+        assert_eq!(copy.report().shared, 2);
+        assert_eq!(memory.get(1), 1);
+        assert_eq!(copy.get(1), -1);
+        assert_ne!(memory, copy);
+
+        memory.set(1000, 7);
+        assert_eq!(memory.len(), 1001);
+        assert_eq!(memory.report().touched, vec![0, 1, 2, 3]);
+        assert_eq!(memory.report().shared, 2);
+        assert_eq!(memory.get(999), 0);
+        assert_eq!(memory.iter().last(), Some(7));
+    }
+
+    #[test]
+    fn sparse_pages() {
+        let mut memory = Memory::new(&[1, 2, 3]);
+        memory.set(1_000_000_000, 4);
+        memory.set(10 * PAGE_SIZE, 5);
+        assert_eq!(memory.len(), 1_000_000_001);
+        assert_eq!(memory.get(1_000_000_000), 4);
+        assert_eq!(memory.get(999_999_999), 0);
+
+        let report = memory.report();
+        assert_eq!((report.dense, report.sparse), (11, 1));
+        assert_eq!(report.touched, vec![0, 10, 1_000_000_000 / PAGE_SIZE]);
+        assert_eq!(
+            report.to_string(),
+            "1000000001 cells, 3 pages of 256 in use (11 dense, 1 sparse), 0 shared\n\
+             pages 0, 10, 3906250"
+        );
+        // only the dense pages are listed
+        assert_eq!(memory.iter().count(), 11 * PAGE_SIZE);
+
+        // a sparse page is taken in when the dense ones reach it
+        memory.set(40 * PAGE_SIZE, 6);
+        memory.set(30 * PAGE_SIZE, 7);
+        assert_eq!(memory.report().sparse, 3);
+        memory.set(25 * PAGE_SIZE, 8);
+        let report = memory.report();
+        assert_eq!((report.dense, report.sparse), (26, 3));
+        memory.set(35 * PAGE_SIZE, 9);
+        let report = memory.report();
+        assert_eq!((report.dense, report.sparse), (36, 2));
+        assert_eq!(memory.get(30 * PAGE_SIZE), 7);
+        assert_eq!(memory.get(40 * PAGE_SIZE), 6);
+        assert_eq!(memory.report().touched.len(), 7);
+    }
+
+    #[test]
+    fn limit() {
+        let mut memory = Memory::new(&[0; 300]);
+        memory.set_limit(Some(3 * PAGE_SIZE - 1));
+        assert_eq!(memory.try_set(299, 1), Ok(()));
+        assert_eq!(memory.try_set(600, 1), Ok(()));
+        assert_eq!(memory.try_set(1_000_000, 1), Err(MemoryFull));
+        assert_eq!(memory.try_set(800, 1), Err(MemoryFull));
+        assert_eq!(memory.get(1_000_000), 0);
+        assert_eq!(memory.try_set(700, 2), Ok(()));
+
+        // setting isn't limited
+        memory.set(1_000_000, 1);
+        assert_eq!(memory.report().touched.len(), 4);
+        assert!(memory.report().to_string().contains("limit 3 pages"));
+    }
+}
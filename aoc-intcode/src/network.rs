@@ -0,0 +1,541 @@
+//! Machines that talk to each other, like the amplifiers of 2019 day 7 and the network of
+//! 2019 day 23.
+//!
+//! Each machine's output is gathered into [`Message`]s, which are routed into the inbox of
+//! another machine, or out of the network to the caller. A machine that needs input reads the
+//! next message in its inbox. The machines can take turns on one thread with
+//! [`Network::run`], or each get a tokio task with [`Network::run_tasks`].
+
+use crate::error::IntcodeError;
+use crate::machine::{Machine, Status};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+
+pub type NodeId = usize;
+
+/// Something sent from one machine to another, made of a fixed number of values.
+pub trait Message: Sized {
+    /// How many output values make one up.
+    const LEN: usize;
+
+    fn from_output(values: &[i64]) -> Self;
+
+    /// The values the receiving machine reads.
+    fn to_input(&self) -> Vec<i64>;
+}
+
+impl Message for i64 {
+    const LEN: usize = 1;
+
+    fn from_output(values: &[i64]) -> Self {
+        values[0]
+    }
+
+    fn to_input(&self) -> Vec<i64> {
+        vec![*self]
+    }
+}
+
+/// Where a message goes next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Route {
+    To(NodeId),
+    /// Out of the network, stopping it so the caller can deal with it.
+    Out,
+    Drop,
+}
+
+/// Why the network stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event<M> {
+    Output(NodeId, M),
+    /// Every machine that hasn't halted is waiting on input, and there are no messages left
+    /// to read.
+    Idle,
+    Halted,
+    Undeliverable {
+        from: NodeId,
+        to: NodeId,
+        message: M,
+    },
+    Fault(NodeId, IntcodeError),
+}
+
+type Router<M> = Box<dyn FnMut(NodeId, &M) -> Route + Send>;
+
+struct Routing<M> {
+    links: Vec<Option<NodeId>>,
+    router: Option<Router<M>>,
+}
+
+impl<M> Routing<M> {
+    fn route(&mut self, from: NodeId, message: &M) -> Route {
+        match &mut self.router {
+            Some(router) => router(from, message),
+            None => self.links[from].map_or(Route::Out, Route::To),
+        }
+    }
+}
+
+struct Node<M> {
+    machine: Machine,
+    inbox: VecDeque<M>,
+    /// Output that isn't a whole message yet.
+    partial: Vec<i64>,
+    /// Times in a row it's been given the empty input.
+    empty_reads: usize,
+    halted: bool,
+}
+
+impl<M: Message> Node<M> {
+    /// Add an output value, returning the message if that completes one.
+    fn output(&mut self, value: i64) -> Option<M> {
+        self.empty_reads = 0;
+        self.partial.push(value);
+        if self.partial.len() == M::LEN {
+            let message = M::from_output(&self.partial);
+            self.partial.clear();
+            Some(message)
+        } else {
+            None
+        }
+    }
+
+    fn receive(&mut self, message: M) {
+        self.empty_reads = 0;
+        self.machine.extend_input(message.to_input());
+    }
+}
+
+pub struct Network<M> {
+    nodes: Vec<Node<M>>,
+    routing: Routing<M>,
+    empty_input: Option<i64>,
+}
+
+impl<M: Message> Network<M> {
+    /// Machines that aren't connected to anything, so all their messages go [`Route::Out`].
+    pub fn new(machines: Vec<Machine>) -> Self {
+        let links = vec![None; machines.len()];
+        let nodes = machines
+            .into_iter()
+            .map(|machine| Node {
+                machine,
+                inbox: VecDeque::new(),
+                partial: vec![],
+                empty_reads: 0,
+                halted: false,
+            })
+            .collect();
+        Network {
+            nodes,
+            routing: Routing {
+                links,
+                router: None,
+            },
+            empty_input: None,
+        }
+    }
+
+    /// Send everything `from` outputs to `to`.
+    pub fn connect(&mut self, from: NodeId, to: NodeId) {
+        self.routing.links[from] = Some(to);
+    }
+
+    /// Decide where each message goes, instead of following the connections.
+    pub fn with_router(mut self, router: impl FnMut(NodeId, &M) -> Route + Send + 'static) -> Self {
+        self.routing.router = Some(Box::new(router));
+        self
+    }
+
+    /// Machines read this instead of waiting when their inbox is empty. They count as idle
+    /// once they've read it twice in a row.
+    pub fn with_empty_input(mut self, value: i64) -> Self {
+        self.empty_input = Some(value);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn machine(&self, node: NodeId) -> &Machine {
+        &self.nodes[node].machine
+    }
+
+    /// Messages waiting to be read.
+    pub fn inbox(&self, node: NodeId) -> &VecDeque<M> {
+        &self.nodes[node].inbox
+    }
+
+    /// Put a message in a machine's inbox, from outside the network.
+    pub fn send(&mut self, node: NodeId, message: M) {
+        self.nodes[node].inbox.push_back(message);
+    }
+
+    fn is_idle(&self) -> bool {
+        self.nodes.iter().all(|node| {
+            node.halted
+                || (node.inbox.is_empty() && (self.empty_input.is_none() || node.empty_reads >= 2))
+        })
+    }
+
+    /// Give each machine a turn in order, until it's waiting on input, until something
+    /// stops the network.
+    pub fn run(&mut self) -> Event<M> {
+        loop {
+            for i in 0..self.nodes.len() {
+                if let Some(event) = self.run_node(i) {
+                    return event;
+                }
+            }
+            if self.nodes.iter().all(|node| node.halted) {
+                return Event::Halted;
+            }
+            if self.is_idle() {
+                return Event::Idle;
+            }
+        }
+    }
+
+    fn run_node(&mut self, i: NodeId) -> Option<Event<M>> {
+        while !self.nodes[i].halted {
+            let node = &mut self.nodes[i];
+            match node.machine.run() {
+                Status::Output(value) => {
+                    if let Some(message) = node.output(value) {
+                        let event = self.deliver(i, message);
+                        if event.is_some() {
+                            return event;
+                        }
+                    }
+                }
+                Status::NeedsInput => match node.inbox.pop_front() {
+                    Some(message) => node.receive(message),
+                    None => {
+                        if let Some(value) = self.empty_input {
+                            node.machine.push_input(value);
+                            node.empty_reads += 1;
+                        }
+                        return None;
+                    }
+                },
+                Status::Halted => node.halted = true,
+                Status::Fault(e) => return Some(Event::Fault(i, e)),
+            }
+        }
+        None
+    }
+
+    fn deliver(&mut self, from: NodeId, message: M) -> Option<Event<M>> {
+        match self.routing.route(from, &message) {
+            Route::To(to) if to < self.nodes.len() => {
+                self.nodes[to].inbox.push_back(message);
+                None
+            }
+            Route::To(to) => Some(Event::Undeliverable { from, to, message }),
+            Route::Out => Some(Event::Output(from, message)),
+            Route::Drop => None,
+        }
+    }
+}
+
+/// What the tasks share, to tell when the network is idle or halted.
+struct Shared<M> {
+    /// Machines waiting on input.
+    waiting: AtomicUsize,
+    /// Messages sent but not yet read.
+    in_flight: AtomicUsize,
+    /// Machines that haven't halted.
+    live: AtomicUsize,
+    event: Mutex<Option<Event<M>>>,
+    stop: watch::Sender<bool>,
+}
+
+impl<M> Shared<M> {
+    /// Stop every task, unless something already stopped them.
+    fn finish(&self, event: Event<M>) {
+        let mut stored = self.event.lock().unwrap();
+        if stored.is_none() {
+            *stored = Some(event);
+            self.stop.send_replace(true);
+        }
+    }
+
+    fn check_idle(&self) {
+        if self.waiting.load(SeqCst) == self.live.load(SeqCst) && self.in_flight.load(SeqCst) == 0 {
+            self.finish(Event::Idle);
+        }
+    }
+
+    fn start_waiting(&self) {
+        self.waiting.fetch_add(1, SeqCst);
+        self.check_idle();
+    }
+
+    fn halt(&self) {
+        if self.live.fetch_sub(1, SeqCst) == 1 {
+            self.finish(Event::Halted);
+        } else {
+            self.check_idle();
+        }
+    }
+}
+
+impl<M: Message + Send + 'static> Network<M> {
+    /// Run each machine in its own tokio task until something stops the network.
+    pub async fn run_tasks(self) -> (Self, Event<M>) {
+        let Network {
+            nodes,
+            routing,
+            empty_input,
+        } = self;
+        let count = nodes.len();
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..count).map(|_| mpsc::unbounded_channel()).unzip();
+        let (stop, stopped) = watch::channel(false);
+        let shared = Arc::new(Shared {
+            waiting: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            live: AtomicUsize::new(nodes.iter().filter(|node| !node.halted).count()),
+            event: Mutex::new(None),
+            stop,
+        });
+        if shared.live.load(SeqCst) == 0 {
+            shared.finish(Event::Halted);
+        }
+        let routing = Arc::new(Mutex::new(routing));
+
+        let mut tasks = vec![];
+        for (i, (mut node, mut inbox)) in nodes.into_iter().zip(receivers).enumerate() {
+            // what's already in the inbox goes through the channel like everything else
+            for message in node.inbox.drain(..) {
+                shared.in_flight.fetch_add(1, SeqCst);
+                senders[i].send(message).ok();
+            }
+            let senders = senders.clone();
+            let shared = shared.clone();
+            let routing = routing.clone();
+            let mut stopped = stopped.clone();
+            tasks.push(tokio::spawn(async move {
+                let mut waiting = false;
+                let received = |node: &mut Node<M>, waiting: &mut bool, message: M| {
+                    // stop counting as waiting before the message stops counting as in flight
+                    if std::mem::take(waiting) {
+                        shared.waiting.fetch_sub(1, SeqCst);
+                    }
+                    shared.in_flight.fetch_sub(1, SeqCst);
+                    node.receive(message);
+                };
+                while !node.halted && !*stopped.borrow() {
+                    match node.machine.run() {
+                        Status::Output(value) => {
+                            if std::mem::take(&mut waiting) {
+                                shared.waiting.fetch_sub(1, SeqCst);
+                            }
+                            let message = match node.output(value) {
+                                Some(message) => message,
+                                None => continue,
+                            };
+                            let route = routing.lock().unwrap().route(i, &message);
+                            match route {
+                                Route::To(to) if to < count => {
+                                    shared.in_flight.fetch_add(1, SeqCst);
+                                    senders[to].send(message).ok();
+                                }
+                                Route::To(to) => shared.finish(Event::Undeliverable {
+                                    from: i,
+                                    to,
+                                    message,
+                                }),
+                                Route::Out => shared.finish(Event::Output(i, message)),
+                                Route::Drop => {}
+                            }
+                        }
+                        Status::NeedsInput => {
+                            if let Ok(message) = inbox.try_recv() {
+                                received(&mut node, &mut waiting, message);
+                                continue;
+                            }
+                            if let Some(value) = empty_input {
+                                node.machine.push_input(value);
+                                node.empty_reads += 1;
+                                if node.empty_reads >= 2 && !waiting {
+                                    waiting = true;
+                                    shared.start_waiting();
+                                }
+                                tokio::task::yield_now().await;
+                                continue;
+                            }
+                            waiting = true;
+                            shared.start_waiting();
+                            tokio::select! {
+                                Some(message) = inbox.recv() => {
+                                    received(&mut node, &mut waiting, message)
+                                }
+                                _ = stopped.changed() => {}
+                            }
+                        }
+                        Status::Halted => {
+                            node.halted = true;
+                            shared.halt();
+                        }
+                        Status::Fault(e) => shared.finish(Event::Fault(i, e)),
+                    }
+                }
+                (node, inbox)
+            }));
+        }
+        drop(senders);
+
+        let mut nodes = vec![];
+        for task in tasks {
+            let (mut node, mut inbox) = task.await.expect("machine task panicked");
+            while let Ok(message) = inbox.try_recv() {
+                node.inbox.push_back(message);
+            }
+            nodes.push(node);
+        }
+        let event = shared.event.lock().unwrap().take().unwrap_or(Event::Idle);
+        let routing = match Arc::try_unwrap(routing) {
+            Ok(routing) => routing.into_inner().unwrap(),
+            Err(_) => unreachable!("the tasks have finished with the routing"),
+        };
+        let network = Network {
+            nodes,
+            routing,
+            empty_input,
+        };
+        (network, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble::assemble;
+
+    /// The feedback loop example from 2019 day 7.
+    fn amplifiers() -> Network<i64> {
+        let tape = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let machines = [9, 8, 7, 6, 5]
+            .iter()
+            .map(|&phase| Machine::new(&tape).with_input(vec![phase]))
+            .collect();
+        let mut network = Network::new(machines);
+        for i in 0..5 {
+            network.connect(i, (i + 1) % 5);
+        }
+        network.send(0, 0);
+        network
+    }
+
+    #[test]
+    fn feedback_loop() {
+        let mut network = amplifiers();
+        assert_eq!(network.run(), Event::Halted);
+        assert_eq!(network.inbox(0).back(), Some(&139629729));
+    }
+
+    #[tokio::test]
+    async fn feedback_loop_in_tasks() {
+        let (network, event) = amplifiers().run_tasks().await;
+        assert_eq!(event, Event::Halted);
+        assert_eq!(network.inbox(0).back(), Some(&139629729));
+    }
+
+    /// Echoes what it's sent, plus one, to whoever it's connected to, until it's sent 10.
+    fn echo() -> Vec<i64> {
+        assemble(
+            "
+            loop:
+                in -> [x]
+                eq [x], #10 -> [t]
+                jnz [t], #end
+                add [x], #1 -> [x]
+                out [x]
+                jz #0, #loop
+            end:
+                hlt
+            x: data 0
+            t: data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn idle_and_routing() {
+        // nobody sends anything
+        let mut network: Network<i64> = Network::new(vec![Machine::new(&echo()); 3]);
+        assert_eq!(network.run(), Event::Idle);
+
+        // a ping pong between the first two, until the first reads 10
+        network.connect(0, 1);
+        network.connect(1, 0);
+        network.send(0, 0);
+        assert_eq!(network.run(), Event::Idle);
+        assert_eq!(network.machine(0).ip(), echo().len() - 3);
+        assert!(network.inbox(0).is_empty());
+
+        let mut network =
+            Network::new(vec![Machine::new(&echo()); 2]).with_router(|from, &x| match x {
+                5 => Route::Out,
+                7 => Route::To(9),
+                _ => Route::To(1 - from),
+            });
+        network.send(0, 0);
+        assert_eq!(network.run(), Event::Output(0, 5));
+        assert_eq!(network.run(), Event::Idle);
+        network.send(1, 6);
+        assert_eq!(
+            network.run(),
+            Event::Undeliverable {
+                from: 1,
+                to: 9,
+                message: 7
+            }
+        );
+    }
+
+    /// Reads forever.
+    fn listener() -> Vec<i64> {
+        assemble("loop: in -> [x]\njz #0, #loop\nx: data 0").unwrap()
+    }
+
+    #[test]
+    fn empty_input() {
+        let mut network: Network<i64> =
+            Network::new(vec![Machine::new(&listener()); 3]).with_empty_input(-1);
+        assert_eq!(network.run(), Event::Idle);
+        network.send(2, 5);
+        assert_eq!(network.run(), Event::Idle);
+        assert_eq!(network.machine(2).peek(5), -1);
+    }
+
+    #[tokio::test]
+    async fn idle_in_tasks() {
+        let mut network: Network<i64> = Network::new(vec![Machine::new(&echo()); 3]);
+        network.connect(0, 1);
+        network.connect(1, 0);
+        network.send(0, 4);
+        let (network, event) = network.run_tasks().await;
+        assert_eq!(event, Event::Idle);
+        assert_eq!(network.machine(0).ip(), echo().len() - 3);
+        assert!(network.inbox(0).is_empty());
+
+        let network: Network<i64> =
+            Network::new(vec![Machine::new(&listener()); 3]).with_empty_input(-1);
+        let (_, event) = network.run_tasks().await;
+        assert_eq!(event, Event::Idle);
+    }
+}
@@ -0,0 +1,545 @@
+//! Recording what a program does as it runs, to find out where it spends its time.
+//!
+//! A [`Trace`] given to [`Machine::with_trace`] keeps the most recent instructions executed,
+//! and can also write every one of them to a file as it goes. Alongside that it counts how
+//! often each address is executed, read and written, which jumps back make loops, and when
+//! each input was read and output written. A traced machine runs on the reference
+//! interpreter, a step at a time, so it's a lot slower than one that isn't.
+
+use crate::instruction::{Mode, Opcode};
+use crate::machine::{Machine, Status};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// How many of the hottest addresses and loops [`Trace`]'s report shows.
+const REPORT_TOP: usize = 10;
+
+/// How many cells are counted together in the report's heatmaps.
+const REPORT_BUCKET: NonZeroUsize = NonZeroUsize::new(64).unwrap();
+
+/// One instruction executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// How many instructions were executed before this one.
+    pub n: u64,
+    pub addr: usize,
+    pub opcode: Opcode,
+    /// Each parameter's value with its mode applied, or for the one written to, its address.
+    pub operands: Vec<i64>,
+    /// Where it wrote, and what.
+    pub write: Option<(usize, i64)>,
+}
+
+impl Step {
+    pub fn to_json(&self) -> String {
+        let operands: Vec<String> = self.operands.iter().map(i64::to_string).collect();
+        let write = match self.write {
+            Some((addr, value)) => format!("[{}, {}]", addr, value),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"n\": {}, \"addr\": {}, \"op\": \"{}\", \"operands\": [{}], \"write\": {}}}",
+            self.n,
+            self.addr,
+            self.opcode.mnemonic(),
+            operands.join(", "),
+            write
+        )
+    }
+}
+
+/// A jump back from `end` to `start`, taken `iterations` times.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub start: usize,
+    pub end: usize,
+    pub iterations: u64,
+    /// How many times anything from `start` to `end` was executed, in or out of the loop.
+    pub executed: u64,
+}
+
+/// Where every step is written as a line of JSON. Writing stops at the first error, which is
+/// kept for [`Trace::flush`].
+#[derive(Debug)]
+struct Log {
+    out: BufWriter<File>,
+    error: Option<io::Error>,
+}
+
+/// What a machine has executed, from [`Machine::with_trace`].
+///
+/// Cloning a traced machine clones its trace too, with the same log file, so forks of it all
+/// write their steps to the one file.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    capacity: usize,
+    recent: VecDeque<Step>,
+    log: Option<Arc<Mutex<Log>>>,
+    executed: u64,
+    counts: HashMap<usize, u64>,
+    reads: HashMap<usize, u64>,
+    writes: HashMap<usize, u64>,
+    /// How often each jump back was taken, by where it went to and where it came from.
+    back_jumps: HashMap<(usize, usize), u64>,
+    inputs: Vec<(u64, i64)>,
+    outputs: Vec<(u64, i64)>,
+}
+
+impl Trace {
+    /// Keep the last `capacity` steps. Everything is still counted.
+    pub fn new(capacity: usize) -> Self {
+        Trace {
+            capacity,
+            recent: VecDeque::new(),
+            log: None,
+            executed: 0,
+            counts: HashMap::new(),
+            reads: HashMap::new(),
+            writes: HashMap::new(),
+            back_jumps: HashMap::new(),
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    /// Write every step to `path` as well, a line of JSON each.
+    pub fn with_log(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let log = Log {
+            out: BufWriter::new(File::create(path)?),
+            error: None,
+        };
+        self.log = Some(Arc::new(Mutex::new(log)));
+        Ok(self)
+    }
+
+    /// Write out what's left of the log, or give the error that stopped it being written.
+    pub fn flush(&self) -> io::Result<()> {
+        match &self.log {
+            Some(log) => {
+                let mut log = log.lock().unwrap();
+                match log.error.take() {
+                    Some(e) => Err(e),
+                    None => log.out.flush(),
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// How many instructions have been executed.
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// The last steps, oldest first.
+    pub fn recent(&self) -> &VecDeque<Step> {
+        &self.recent
+    }
+
+    /// How many times each address was executed, in address order.
+    pub fn counts(&self) -> Vec<(usize, u64)> {
+        sorted(&self.counts)
+    }
+
+    /// How many times the instruction at `addr` was executed.
+    pub fn count(&self, addr: usize) -> u64 {
+        self.counts.get(&addr).copied().unwrap_or(0)
+    }
+
+    /// The `n` most executed addresses, most first.
+    pub fn hottest(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut counts = self.counts();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Every loop, the one with the most executed in it first.
+    pub fn loops(&self) -> Vec<Loop> {
+        let mut loops: Vec<Loop> = self
+            .back_jumps
+            .iter()
+            .map(|(&(start, end), &iterations)| Loop {
+                start,
+                end,
+                iterations,
+                executed: (start..=end)
+                    .filter_map(|addr| self.counts.get(&addr))
+                    .sum(),
+            })
+            .collect();
+        loops.sort_by(|a, b| {
+            (b.executed, b.iterations)
+                .cmp(&(a.executed, a.iterations))
+                .then((a.start, a.end).cmp(&(b.start, b.end)))
+        });
+        loops
+    }
+
+    /// How many times each address was read by a parameter, in address order.
+    pub fn reads(&self) -> Vec<(usize, u64)> {
+        sorted(&self.reads)
+    }
+
+    pub fn writes(&self) -> Vec<(usize, u64)> {
+        sorted(&self.writes)
+    }
+
+    /// Reads counted together by `bucket` cells, as the first address of each bucket with any.
+    pub fn read_heatmap(&self, bucket: NonZeroUsize) -> Vec<(usize, u64)> {
+        heatmap(&self.reads, bucket)
+    }
+
+    pub fn write_heatmap(&self, bucket: NonZeroUsize) -> Vec<(usize, u64)> {
+        heatmap(&self.writes, bucket)
+    }
+
+    /// Each input read, with how many instructions were executed before it.
+    pub fn inputs(&self) -> &[(u64, i64)] {
+        &self.inputs
+    }
+
+    /// Each output, with how many instructions were executed before it.
+    pub fn outputs(&self) -> &[(u64, i64)] {
+        &self.outputs
+    }
+
+    /// Everything counted, and the recent steps, as a JSON object.
+    pub fn to_json(&self) -> String {
+        let loops: Vec<String> = self
+            .loops()
+            .iter()
+            .map(|l| {
+                format!(
+                    "{{\"start\": {}, \"end\": {}, \"iterations\": {}, \"executed\": {}}}",
+                    l.start, l.end, l.iterations, l.executed
+                )
+            })
+            .collect();
+        let recent: Vec<String> = self.recent.iter().map(Step::to_json).collect();
+
+        let mut json = String::from("{\n");
+        writeln!(json, "  \"executed\": {},", self.executed).unwrap();
+        writeln!(json, "  \"counts\": {},", json_pairs(&self.counts())).unwrap();
+        writeln!(json, "  \"loops\": [{}],", loops.join(", ")).unwrap();
+        writeln!(json, "  \"reads\": {},", json_pairs(&self.reads())).unwrap();
+        writeln!(json, "  \"writes\": {},", json_pairs(&self.writes())).unwrap();
+        writeln!(json, "  \"inputs\": {},", json_pairs(&self.inputs)).unwrap();
+        writeln!(json, "  \"outputs\": {},", json_pairs(&self.outputs)).unwrap();
+        writeln!(json, "  \"recent\": [{}]", recent.join(", ")).unwrap();
+        json.push('}');
+        json
+    }
+
+    /// Execute one instruction on `machine`, which mustn't be traced itself, and record it.
+    pub(crate) fn step(&mut self, machine: &mut Machine) -> Option<Status> {
+        let addr = machine.ip();
+        let ins = match machine.instruction() {
+            Ok(ins) => ins,
+            Err(_) => return machine.step(),
+        };
+
+        let arity = ins.opcode.arity();
+        let mut operands = Vec::with_capacity(arity);
+        let mut reads = Vec::with_capacity(arity);
+        for n in 0..arity {
+            let raw = machine.peek(addr + 1 + n);
+            let target = match ins.modes[n] {
+                Mode::Position => raw,
+                Mode::Immediate => {
+                    operands.push(raw);
+                    continue;
+                }
+                Mode::Relative => machine.relative_base().wrapping_add(raw),
+            };
+            if ins.opcode.writes() && n + 1 == arity {
+                operands.push(target);
+            } else if target >= 0 {
+                reads.push(target as usize);
+                operands.push(machine.peek(target as usize));
+            }
+        }
+
+        let status = machine.step();
+        if let Some(Status::NeedsInput | Status::Fault(_)) = status {
+            // nothing was executed
+            return status;
+        }
+
+        let n = self.executed;
+        self.executed += 1;
+        *self.counts.entry(addr).or_default() += 1;
+        for read in reads {
+            *self.reads.entry(read).or_default() += 1;
+        }
+        let write = if ins.opcode.writes() {
+            let dest = operands[arity - 1] as usize;
+            *self.writes.entry(dest).or_default() += 1;
+            Some((dest, machine.peek(dest)))
+        } else {
+            None
+        };
+        match (ins.opcode, status, write) {
+            (Opcode::Input, _, Some((_, value))) => self.inputs.push((n, value)),
+            (_, Some(Status::Output(value)), _) => self.outputs.push((n, value)),
+            _ => {}
+        }
+        if ins.opcode.jumps() && machine.ip() <= addr {
+            *self.back_jumps.entry((machine.ip(), addr)).or_default() += 1;
+        }
+
+        let step = Step {
+            n,
+            addr,
+            opcode: ins.opcode,
+            operands,
+            write,
+        };
+        if let Some(log) = &self.log {
+            let mut log = log.lock().unwrap();
+            if log.error.is_none() {
+                if let Err(e) = writeln!(log.out, "{}", step.to_json()) {
+                    log.error = Some(e);
+                }
+            }
+        }
+        if self.capacity > 0 {
+            if self.recent.len() == self.capacity {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(step);
+        }
+        status
+    }
+}
+
+fn sorted(counts: &HashMap<usize, u64>) -> Vec<(usize, u64)> {
+    let mut counts: Vec<(usize, u64)> = counts.iter().map(|(&a, &n)| (a, n)).collect();
+    counts.sort_unstable();
+    counts
+}
+
+fn json_pairs<A: fmt::Display, B: fmt::Display>(pairs: &[(A, B)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(a, b)| format!("[{}, {}]", a, b))
+        .collect();
+    format!("[{}]", pairs.join(", "))
+}
+
+fn heatmap(counts: &HashMap<usize, u64>, bucket: NonZeroUsize) -> Vec<(usize, u64)> {
+    let bucket = bucket.get();
+    let mut buckets = BTreeMap::new();
+    for (&addr, &n) in counts {
+        *buckets.entry(addr / bucket * bucket).or_default() += n;
+    }
+    buckets.into_iter().collect()
+}
+
+/// The hottest addresses and loops, and where memory's read and written.
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} instructions executed, {} inputs, {} outputs",
+            self.executed,
+            self.inputs.len(),
+            self.outputs.len()
+        )?;
+
+        writeln!(f, "hottest addresses:")?;
+        for (addr, n) in self.hottest(REPORT_TOP) {
+            writeln!(f, "  {:>6}  {}", addr, n)?;
+        }
+        writeln!(f, "hottest loops:")?;
+        for l in self.loops().iter().take(REPORT_TOP) {
+            writeln!(
+                f,
+                "  {:>6}-{:<6} {} times, {} executed",
+                l.start, l.end, l.iterations, l.executed
+            )?;
+        }
+
+        for (name, heatmap) in &[
+            ("reads", self.read_heatmap(REPORT_BUCKET)),
+            ("writes", self.write_heatmap(REPORT_BUCKET)),
+        ] {
+            writeln!(f, "{} by {} cells:", name, REPORT_BUCKET)?;
+            for (start, n) in heatmap {
+                writeln!(
+                    f,
+                    "  {:>6}-{:<6} {}",
+                    start,
+                    start + REPORT_BUCKET.get() - 1,
+                    n
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A machine's trace, if it has one. It's a record of what the machine did rather than part
+/// of its state, so it doesn't count towards two machines being equal.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Tracing(pub(crate) Option<Box<Trace>>);
+
+impl PartialEq for Tracing {
+    fn eq(&self, _: &Tracing) -> bool {
+        true
+    }
+}
+
+impl Eq for Tracing {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble::assemble;
+
+    /// Adds up the numbers from its input, until a 0.
+    fn summer() -> Vec<i64> {
+        assemble(
+            "
+            loop:
+                in -> [x]
+                jz [x], #done
+                add [total], [x] -> [total]
+                jz #0, #loop
+            done:
+                out [total]
+                hlt
+            x: data 0
+            total: data 0
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn counting() {
+        let mut machine = Machine::new(&summer())
+            .with_input(vec![3, 4, 5, 0])
+            .with_trace(Trace::new(3));
+        assert_eq!(machine.run_to_halt(), Ok(vec![12]));
+        let trace = machine.take_trace().unwrap();
+        assert!(machine.trace().is_none());
+
+        // 3 times round the loop, then in, jz, out and hlt
+        assert_eq!(trace.executed(), 3 * 4 + 4);
+        assert_eq!(
+            trace.counts(),
+            vec![(0, 4), (2, 4), (5, 3), (9, 3), (12, 1), (14, 1)]
+        );
+        assert_eq!(trace.hottest(2), vec![(0, 4), (2, 4)]);
+        assert_eq!(
+            trace.loops(),
+            vec![Loop {
+                start: 0,
+                end: 9,
+                iterations: 3,
+                executed: 14,
+            }]
+        );
+
+        // x is 15 and total is 16
+        assert_eq!(trace.reads(), vec![(15, 7), (16, 4)]);
+        assert_eq!(trace.writes(), vec![(15, 4), (16, 3)]);
+        let ten = NonZeroUsize::new(10).unwrap();
+        assert_eq!(trace.read_heatmap(ten), vec![(10, 11)]);
+        assert_eq!(
+            trace.write_heatmap(NonZeroUsize::MIN),
+            vec![(15, 4), (16, 3)]
+        );
+        assert_eq!(trace.inputs(), &[(0, 3), (4, 4), (8, 5), (12, 0)]);
+        assert_eq!(trace.outputs(), &[(14, 12)]);
+
+        let recent: Vec<_> = trace.recent().iter().map(|step| step.n).collect();
+        assert_eq!(recent, vec![13, 14, 15]);
+        assert_eq!(
+            trace.recent()[0].to_json(),
+            r#"{"n": 13, "addr": 2, "op": "jz", "operands": [0, 12], "write": null}"#
+        );
+    }
+
+    #[test]
+    fn steps() {
+        let mut machine = Machine::new(&summer())
+            .with_input(vec![7])
+            .with_trace(Trace::new(10));
+        // blocked on the second input, which isn't a step
+        assert_eq!(machine.run(), Status::NeedsInput);
+        let trace = machine.trace().unwrap();
+        assert_eq!(trace.executed(), 4);
+        assert_eq!(
+            trace.recent()[2],
+            Step {
+                n: 2,
+                addr: 5,
+                opcode: Opcode::Add,
+                operands: vec![0, 7, 16],
+                write: Some((16, 7)),
+            }
+        );
+        assert_eq!(trace.recent()[0].operands, vec![15]);
+        assert_eq!(trace.recent()[0].write, Some((15, 7)));
+    }
+
+    #[test]
+    fn report_and_json() {
+        let mut machine = Machine::new(&summer())
+            .with_input(vec![1, 0])
+            .with_trace(Trace::new(0));
+        machine.run_to_halt().unwrap();
+        let trace = machine.trace().unwrap();
+        assert!(trace.recent().is_empty());
+
+        let report = trace.to_string();
+        assert!(report.starts_with("8 instructions executed, 2 inputs, 1 outputs\n"));
+        assert!(report.contains("hottest loops:\n       0-9      1 times, 6 executed\n"));
+        assert!(report.contains("reads by 64 cells:\n       0-63     5\n"));
+
+        let json = trace.to_json();
+        assert!(json.contains("\"executed\": 8,"));
+        assert!(json.contains(
+            "\"loops\": [{\"start\": 0, \"end\": 9, \"iterations\": 1, \"executed\": 6}],"
+        ));
+        assert!(json.contains("\"inputs\": [[0, 1], [4, 0]],"));
+        assert!(json.contains("\"outputs\": [[6, 1]],"));
+        assert!(json.contains("\"recent\": []"));
+    }
+
+    #[test]
+    fn log_file() {
+        let path = std::env::temp_dir().join(format!("intcode_trace_{}.jsonl", std::process::id()));
+        let trace = Trace::new(0).with_log(&path).unwrap();
+        let mut machine = Machine::new(&summer())
+            .with_input(vec![0])
+            .with_trace(trace);
+        machine.run_to_halt().unwrap();
+        machine.trace().unwrap().flush().unwrap();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[3],
+            r#"{"n": 3, "addr": 14, "op": "hlt", "operands": [], "write": null}"#
+        );
+    }
+
+    #[test]
+    fn same_as_untraced() {
+        let tape = summer();
+        let mut plain = Machine::new(&tape).with_input(vec![5, 6, 0]);
+        let mut traced = plain.clone().with_trace(Trace::new(1));
+        assert_eq!(plain.run_to_halt(), traced.run_to_halt());
+        assert_eq!(plain, traced);
+    }
+}
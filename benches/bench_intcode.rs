@@ -0,0 +1,51 @@
+use aoc_2019::{day_15::Day15, day_17::Day17, day_19::Day19};
+use aoc_common::aoc_day::AocDay;
+use aoc_intcode::{read_tape, Engine, Machine};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const ENGINES: [Engine; 2] = [Engine::Reference, Engine::Decoded];
+
+pub fn criterion_benchmark_engines(c: &mut Criterion) {
+    day_9(c);
+    bench::<Day15>(c);
+    bench::<Day17>(c);
+    bench::<Day19>(c);
+}
+
+/// Day 9 isn't an `AocDay`, so run the program directly.
+fn day_9(c: &mut Criterion) {
+    let tape = read_tape("data/2019/day_9.in").unwrap();
+
+    let mut group = c.benchmark_group("intcode 2019 9");
+    for &engine in ENGINES.iter() {
+        for &input in [1, 2].iter() {
+            group.bench_function(format!("{:?} {}", engine, input), |b| {
+                b.iter(|| {
+                    Machine::new(&tape)
+                        .with_engine(engine)
+                        .with_input(vec![input])
+                        .run_to_halt()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench<Day: AocDay>(c: &mut Criterion) {
+    let input = Day::load().unwrap();
+
+    let mut group = c.benchmark_group(format!("intcode 2019 {}", Day::day()));
+    for &engine in ENGINES.iter() {
+        group.bench_function(format!("{:?} 1", engine), |b| {
+            b.iter(|| engine.as_default(|| Day::part_1(&input)))
+        });
+        group.bench_function(format!("{:?} 2", engine), |b| {
+            b.iter(|| engine.as_default(|| Day::part_2(&input)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark_engines);
+criterion_main!(benches);
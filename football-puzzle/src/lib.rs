@@ -1,7 +1,8 @@
 use aoc_common::files::Res;
+use aoc_common::numtheory::{crt, lcm_all};
 use aoc_common::{time, time_block};
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
 
 pub fn run_all() -> Res<()> {
     // these _must_ be coprime
@@ -37,55 +38,7 @@ pub fn run_all() -> Res<()> {
 }
 
 fn part_1(input: &[i64]) -> i64 {
-    let mut known_primes = vec![2, 3];
-
-    let all_prime_factors = input
-        .iter()
-        .map(|x| get_prime_factors(*x, &mut known_primes))
-        .collect::<Vec<_>>();
-
-    let mut product = 1;
-    for p in known_primes.iter() {
-        let max_occur = all_prime_factors
-            .iter()
-            .map(|pfs| pfs.get(p).unwrap_or(&0))
-            .max()
-            .unwrap_or(&0);
-        product *= p.pow(*max_occur as u32);
-    }
-
-    product
-}
-
-fn get_prime_factors(mut n: i64, known_primes: &mut Vec<i64>) -> HashMap<i64, i64> {
-    let mut prime_factors: HashMap<i64, i64> = HashMap::new();
-    let mut d = 2;
-    loop {
-        if n == 1 {
-            return prime_factors;
-        } else if n % d == 0 {
-            *prime_factors.entry(d).or_insert(0) += 1;
-            n = n / d
-        } else {
-            d = get_next_prime(d, known_primes)
-        }
-    }
-}
-
-fn get_next_prime(mut p: i64, known_primes: &mut Vec<i64>) -> i64 {
-    for &known_p in known_primes.iter() {
-        if known_p > p {
-            return known_p;
-        }
-    }
-
-    loop {
-        p += 2;
-        if known_primes.iter().all(|x| p % x != 0) {
-            known_primes.push(p);
-            return p;
-        }
-    }
+    lcm_all(input.iter().copied())
 }
 
 /// Fold over all the players, and at each step find an `offset` and `period` that can be used to find occurrences for the matching pattern.
@@ -111,25 +64,15 @@ fn find_pattern(
     period: i64,
     running_interval: i64,
 ) -> (i64, i64) {
-    // Keep iterating by the period until we find the new offset
-    let mut new_offset = offset;
-    loop {
-        if (new_offset + (player_index as i64) * running_interval) % player_speed == 0 {
-            break;
-        }
-        new_offset += period;
-    }
-
-    // The new period will be a multiple of the old period.
-    // Keep looping until you find it.
-    let mut new_period = period;
-    loop {
-        if (new_offset + new_period + (player_index as i64) * running_interval) % player_speed == 0
-        {
-            break;
-        }
-        new_period += period;
-    }
+    let (first, new_period) = crt(vec![
+        (offset, period),
+        (-(player_index as i64) * running_interval, player_speed),
+    ])
+    .expect("player speeds must be coprime");
+
+    // The first match that isn't before the current offset.
+    let behind = (offset - first).max(0);
+    let new_offset = first + (behind + new_period - 1) / new_period * new_period;
 
     (new_offset, new_period)
 }
@@ -1,9 +1,13 @@
 use aoc_2019;
+use aoc_common::viz;
 use std::env;
 
 fn main() -> Result<(), Box<dyn std::error::Error + Sync + Send>> {
     let args: Vec<String> = env::args().collect();
-    let day = args.get(1).and_then(|n| n.parse::<usize>().ok());
+    if viz::init_from_args(&args)? {
+        return Ok(());
+    }
+    let day = args.iter().skip(1).find_map(|n| n.parse::<usize>().ok());
 
     aoc_2019::main(day)
 }
@@ -1,10 +1,14 @@
 use aoc_2020;
+use aoc_common::viz;
 use std::env;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let args: Vec<String> = env::args().collect();
-    let day = args.get(1).and_then(|n| n.parse::<usize>().ok());
+    if viz::init_from_args(&args)? {
+        return Ok(());
+    }
+    let day = args.iter().skip(1).find_map(|n| n.parse::<usize>().ok());
 
     aoc_2020::run_all(day).await
 }
@@ -0,0 +1,87 @@
+use aoc_intcode::debugger::{repl, Debugger};
+use aoc_intcode::{disassemble, read_input, read_tape, AsciiConsole, Machine, Trace};
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "usage:
+  aoc intcode debug <tape> [--input <file>]
+  aoc intcode disasm <tape>
+  aoc intcode ascii <tape> [--script <file>] [--transcript <file>]
+  aoc intcode trace <tape> [--input <file>] [--json <file>] [--log <file>]";
+
+/// How many of the last steps `trace` puts in its JSON.
+const TRACE_RECENT: usize = 1000;
+
+fn main() -> Result<(), Box<dyn std::error::Error + Sync + Send>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["intcode", "debug", tape, rest @ ..] => {
+            let mut debugger = Debugger::new(Machine::new(&read_tape(tape)?));
+            match rest {
+                [] => {}
+                ["--input", file] => {
+                    debugger.command(&format!("file {}", file))?;
+                }
+                _ => usage(),
+            }
+            let stdin = io::stdin();
+            repl(&mut debugger, stdin.lock(), io::stdout())?;
+        }
+        ["intcode", "ascii", tape, rest @ ..] => {
+            let mut console = AsciiConsole::new(Machine::new(&read_tape(tape)?));
+            let mut script = String::new();
+            let mut transcript = None;
+            for option in rest.chunks(2) {
+                match option {
+                    ["--script", file] => script = fs::read_to_string(file)?,
+                    ["--transcript", file] => transcript = Some(file),
+                    _ => usage(),
+                }
+            }
+            if transcript.is_some() {
+                console = console.with_transcript();
+            }
+            // the script's lines go first, as if they'd been typed in
+            let stdin = io::stdin();
+            let input = script.as_bytes().chain(stdin.lock());
+            console.interact(input, io::stdout())?;
+            if let (Some(file), Some(text)) = (transcript, console.transcript()) {
+                fs::write(file, text)?;
+            }
+        }
+        ["intcode", "trace", tape, rest @ ..] => {
+            let mut machine = Machine::new(&read_tape(tape)?);
+            let mut trace = Trace::new(TRACE_RECENT);
+            let mut json = None;
+            for option in rest.chunks(2) {
+                match option {
+                    ["--input", file] => machine.extend_input(read_input(file)?),
+                    ["--json", file] => json = Some(file),
+                    ["--log", file] => trace = trace.with_log(file)?,
+                    _ => usage(),
+                }
+            }
+            let mut machine = machine.with_trace(trace);
+            let (output, status) = machine.run_until_blocked();
+            let trace = machine.take_trace().unwrap();
+            trace.flush()?;
+            println!("{:?} after {} outputs", status, output.len());
+            print!("{}", trace);
+            if let Some(file) = json {
+                fs::write(file, trace.to_json())?;
+            }
+        }
+        ["intcode", "disasm", tape] => print!("{}", disassemble(&read_tape(tape)?)),
+        _ => usage(),
+    }
+    Ok(())
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1)
+}